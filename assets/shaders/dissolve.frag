@@ -0,0 +1,30 @@
+//dissolve.frag
+// Material params: progress (float), edge_color (vec4). Textures: noise.
+
+#version 450
+
+layout(location=0) in vec2 v_tex_coords;
+layout(location=1) in vec4 v_color;
+layout(location=0) out vec4 f_color;
+
+layout(set=0, binding=0) uniform texture2D t_diffuse;
+layout(set=0, binding=1) uniform sampler s_diffuse;
+
+layout(set=2, binding=0) uniform Dissolve {
+	vec4 u_progress;
+	vec4 u_edge_color;
+};
+layout(set=2, binding=1) uniform texture2D t_noise;
+layout(set=2, binding=2) uniform sampler s_noise;
+
+// Pixels whose noise is below the progress are gone, the ones just above it glow
+void main(){
+	vec4 color = texture(sampler2D(t_diffuse, s_diffuse), v_tex_coords) * v_color;
+	float noise = texture(sampler2D(t_noise, s_noise), v_tex_coords).r;
+	float progress = u_progress.x;
+	if (noise < progress) {
+		discard;
+	}
+	float edge = 1.0 - smoothstep(0.0, 0.05, noise - progress);
+	f_color = vec4(mix(color.rgb, u_edge_color.rgb, edge * step(0.001, progress)), color.a);
+}
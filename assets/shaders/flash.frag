@@ -0,0 +1,21 @@
+//flash.frag
+// Material params: amount (float), flash_color (vec4), e.g. white when hit.
+
+#version 450
+
+layout(location=0) in vec2 v_tex_coords;
+layout(location=1) in vec4 v_color;
+layout(location=0) out vec4 f_color;
+
+layout(set=0, binding=0) uniform texture2D t_diffuse;
+layout(set=0, binding=1) uniform sampler s_diffuse;
+
+layout(set=2, binding=0) uniform Flash {
+	vec4 u_amount;
+	vec4 u_flash_color;
+};
+
+void main(){
+	vec4 color = texture(sampler2D(t_diffuse, s_diffuse), v_tex_coords) * v_color;
+	f_color = vec4(mix(color.rgb, u_flash_color.rgb, u_amount.x), color.a);
+}
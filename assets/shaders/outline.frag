@@ -0,0 +1,28 @@
+//outline.frag
+// Material params: outline_color (vec4), thickness (float, in texels).
+
+#version 450
+
+layout(location=0) in vec2 v_tex_coords;
+layout(location=1) in vec4 v_color;
+layout(location=0) out vec4 f_color;
+
+layout(set=0, binding=0) uniform texture2D t_diffuse;
+layout(set=0, binding=1) uniform sampler s_diffuse;
+
+layout(set=2, binding=0) uniform Outline {
+	vec4 u_outline_color;
+	vec4 u_thickness;
+};
+
+// Transparent pixels next to opaque ones are drawn in the outline color
+void main(){
+	vec4 color = texture(sampler2D(t_diffuse, s_diffuse), v_tex_coords) * v_color;
+	vec2 texel = u_thickness.x / vec2(textureSize(sampler2D(t_diffuse, s_diffuse), 0));
+	float neighbours = texture(sampler2D(t_diffuse, s_diffuse), v_tex_coords + vec2(texel.x, 0.0)).a
+		+ texture(sampler2D(t_diffuse, s_diffuse), v_tex_coords - vec2(texel.x, 0.0)).a
+		+ texture(sampler2D(t_diffuse, s_diffuse), v_tex_coords + vec2(0.0, texel.y)).a
+		+ texture(sampler2D(t_diffuse, s_diffuse), v_tex_coords - vec2(0.0, texel.y)).a;
+	float outline = step(0.5, neighbours) * (1.0 - step(0.5, color.a));
+	f_color = mix(color, u_outline_color, outline);
+}
@@ -1,17 +1,41 @@
 use anyhow::*;
 use glob::glob;
-use std::fs::{read_to_string, write};
-use std::path::PathBuf;
+use std::fmt::Write as _;
+use std::fs::write;
+use std::path::{Path, PathBuf};
+
+#[allow(dead_code)]
+#[path = "src/paopu_renderer/shader_compiler.rs"]
+mod shader_compiler;
+#[allow(dead_code)]
+#[path = "src/paopu_renderer/shader_preprocessor.rs"]
+mod shader_preprocessor;
+#[allow(dead_code)]
+#[path = "src/paopu_renderer/shader_reflection.rs"]
+mod shader_reflection;
+
+use shader_compiler::{parse_glsl, parse_wgsl, write_spirv};
+use shader_preprocessor::{PreprocessedSource, ShaderManifest, ShaderPreprocessor};
+use shader_reflection::ShaderReflection;
+
+const SHADER_DIR: &str = "src/paopu_renderer";
+const MANIFEST: &str = "src/paopu_renderer/shaders.manifest";
+
+enum ShaderKind {
+    Glsl(naga::ShaderStage),
+    Wgsl,
+}
 
 struct ShaderData {
-    src: String,
-    src_path: PathBuf,
+    src: PreprocessedSource,
     spv_path: PathBuf,
-    kind: shaderc::ShaderKind,
+    kind: ShaderKind,
 }
 
 impl ShaderData {
-    pub fn load(src_path: PathBuf) -> Result<Self> {
+    /// Reads a variant of a shader, named by its permutations
+    /// (e.g. `LIGHTING.NORMAL_MAP`) or empty for the base variant.
+    pub fn load(src_path: PathBuf, variant: &str, preprocessor: &ShaderPreprocessor) -> Result<Self> {
         let extension = src_path
             .extension()
             .context("Files has no extension")?
@@ -19,61 +43,122 @@ impl ShaderData {
             .context("Extension cannot be converted to &str")?;
 
         let kind = match extension {
-            "vert" => shaderc::ShaderKind::Vertex,
-            "frag" => shaderc::ShaderKind::Fragment,
-            "comp" => shaderc::ShaderKind::Compute,
+            "vert" => ShaderKind::Glsl(naga::ShaderStage::Vertex),
+            "frag" => ShaderKind::Glsl(naga::ShaderStage::Fragment),
+            "comp" => ShaderKind::Glsl(naga::ShaderStage::Compute),
+            "wgsl" => ShaderKind::Wgsl,
             _ => bail!("Unsupported shader: {}", src_path.display()),
         };
 
-        let src = read_to_string(src_path.clone())?;
-        let spv_path = src_path.with_extension(format!("{}.spv", extension));
+        let src = preprocessor.process_file(&src_path)?;
+        let spv_path = if variant.is_empty() {
+            src_path.with_extension(format!("{}.spv", extension))
+        } else {
+            src_path.with_extension(format!("{}.{}.spv", extension, variant))
+        };
 
         Ok(Self {
             src,
-            src_path,
             spv_path,
             kind,
         })
     }
+
+    /// Compiles the shader to SPIR-V, reporting errors by file and line.
+    pub fn compile(&self, preprocessor: &ShaderPreprocessor, debug_level: u32) -> Result<(Vec<u32>, ShaderReflection)> {
+        let mut module = match self.kind {
+            ShaderKind::Glsl(stage) => parse_glsl(&self.src, stage, preprocessor.get_defines())?,
+            ShaderKind::Wgsl => parse_wgsl(&self.src)?,
+        };
+        let words = write_spirv(&mut module, &self.src, debug_level)?;
+        Ok((words, ShaderReflection::from_module(&module)))
+    }
+}
+
+/// Writes the reflections as a `builtin_reflections` function, included by
+/// the renderer to check its layouts at startup.
+fn reflection_source(reflections: &[(String, String, ShaderReflection)]) -> String {
+    let mut source = String::from(
+        "/// The reflection of every shader compiled by build.rs, by its file\n\
+         /// relative to the renderer's sources and its variant.\n\
+         pub(crate) fn builtin_reflections() -> Vec<(&'static str, &'static str, ShaderReflection)> {\n    vec![\n",
+    );
+    for (file, variant, reflection) in reflections {
+        let _ = writeln!(source, "        ({:?}, {:?}, ShaderReflection {{", file, variant);
+        source.push_str("            bindings: vec![\n");
+        for binding in reflection.bindings.iter() {
+            let _ = writeln!(
+                source,
+                "                ReflectedBinding {{ group: {}, binding: {}, ty: BindingType::{:?} }},",
+                binding.group, binding.binding, binding.ty
+            );
+        }
+        source.push_str("            ],\n            vertex_inputs: vec![\n");
+        for input in reflection.vertex_inputs.iter() {
+            let _ = writeln!(
+                source,
+                "                VertexInput {{ location: {}, scalar: VertexScalar::{:?}, components: {} }},",
+                input.location, input.scalar, input.components
+            );
+        }
+        source.push_str("            ],\n        }),\n");
+    }
+    source.push_str("    ]\n}\n");
+    source
 }
 
 fn main() -> Result<()> {
-    // Collect all shaders recursively within /src/
+    println!("cargo:rerun-if-changed={}", MANIFEST);
+    println!("cargo:rerun-if-env-changed=PAOPU_SHADER_DEBUG_LEVEL");
+    let manifest = ShaderManifest::load(Path::new(MANIFEST))?;
+    let debug_level = match std::env::var("PAOPU_SHADER_DEBUG_LEVEL").ok() {
+        Some(level) => level
+            .parse()
+            .with_context(|| format!("Invalid PAOPU_SHADER_DEBUG_LEVEL {}", level))?,
+        None => manifest.debug_level,
+    };
+
+    // Collect all shaders recursively within /src/, leaving out includes
     let mut shader_paths = [
-        glob("./src/paopu_renderer/**/*.vert")?,
-        glob("./src/paopu_renderer/**/*.frag")?,
-        glob("./src/paopu_renderer/**/*.comp")?,
+        glob("src/paopu_renderer/**/*.vert")?,
+        glob("src/paopu_renderer/**/*.frag")?,
+        glob("src/paopu_renderer/**/*.comp")?,
+        glob("src/paopu_renderer/**/*.wgsl")?,
     ];
-
-    // This could be parallelized
-    let shaders = shader_paths
+    let shader_paths = shader_paths
         .iter_mut()
         .flatten()
-        .map(|glob_result| ShaderData::load(glob_result?))
-        .collect::<Vec<Result<_>>>()
+        .collect::<std::result::Result<Vec<_>, _>>()?
         .into_iter()
-        .collect::<Result<Vec<_>>>()?;
-
-    let mut compiler = shaderc::Compiler::new().context("Unable to create shader compiler")?;
-
-    // This cannot be parallelized. The [shaderc::Compiler] is not thread safe.
-
-    for shader in shaders {
-        // Tells cargo to rerun this script if something in /src/ changes
-        println!(
-            "cargo:rerun-if-changed={}",
-            shader.src_path.as_os_str().to_str().unwrap()
-        );
-
-        let compiled = compiler.compile_into_spirv(
-            &shader.src,
-            shader.kind,
-            &shader.src_path.to_str().unwrap(),
-            "main",
-            None,
-        )?;
-        write(shader.spv_path, compiled.as_binary_u8())?;
+        .filter(|path| match &manifest.include_dir {
+            Some(include_dir) => !path.starts_with(include_dir),
+            None => true,
+        });
+
+    // This could be parallelized
+    let mut reflections = Vec::new();
+    for src_path in shader_paths {
+        let file = src_path
+            .strip_prefix(SHADER_DIR)?
+            .to_string_lossy()
+            .replace('\\', "/");
+        for variant in manifest.variants(&file) {
+            let preprocessor = manifest.preprocessor(&file, &variant);
+            let shader = ShaderData::load(src_path.clone(), &variant, &preprocessor)?;
+            // Tells cargo to rerun this script if the shader or its includes change
+            for source in shader.src.get_files() {
+                println!("cargo:rerun-if-changed={}", source.display());
+            }
+
+            let (compiled, reflection) = shader.compile(&preprocessor, debug_level)?;
+            let bytes: Vec<u8> = compiled.iter().flat_map(|word| word.to_le_bytes().to_vec()).collect();
+            write(&shader.spv_path, bytes)?;
+            reflections.push((file.clone(), variant, reflection));
+        }
     }
 
+    let out_dir = PathBuf::from(std::env::var("OUT_DIR")?);
+    write(out_dir.join("shader_reflection.rs"), reflection_source(&reflections))?;
+
     Ok(())
 }
@@ -1,13 +1,26 @@
 
 use paopu_rs::{
     core::application::*,
+    core::transform::Transform2D,
     window::window::*,
     renderer::renderer::*,
+    renderer::sprite::Sprite,
 };
 fn main() {
     let mut app = Application::default();
     app 
         .add_module::<Window>(Window::default())
         .add_module::<Renderer>(Renderer::default());
+
+    let mob = app
+        .get_module_mut::<Renderer>()
+        .unwrap()
+        .load_texture_from_bytes(include_bytes!("../../assets/s_mob_01_idle.png"), "mob sprite")
+        .unwrap();
+    app.world.spawn((
+        Transform2D::from_translation(0.0, 0.0).with_scale(4.0, 4.0),
+        Sprite::new(mob),
+    ));
+
     app.run();
 }
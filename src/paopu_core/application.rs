@@ -1,8 +1,18 @@
-use std::{any::TypeId, collections::HashMap};
+use std::{
+    any::TypeId,
+    collections::HashMap,
+    time::{Duration, Instant},
+};
 
 use winit::event::*;
 
+use crate::core::commands::Commands;
+use crate::core::events::{EventChannel, Events, RendererSetup, WindowId};
 use crate::core::module::Module;
+use crate::core::state::{State, StateStack, StateTransition};
+use crate::core::time::Time;
+use crate::core::transform::propagate_transforms;
+use crate::core::world::World;
 use crate::input::input::*;
 use crate::renderer::renderer::Renderer;
 
@@ -12,18 +22,35 @@ use crate::renderer::renderer::Renderer;
 
 pub struct Application {
     modules: HashMap<TypeId, Box<dyn Module>>,
+    // Modules are updated in the order they were added.
+    module_order: Vec<TypeId>,
+    events: HashMap<TypeId, Box<dyn EventChannel>>,
     loop_function: Box<dyn Fn(Application)>,
+    time: Time,
+    states: StateStack,
+    requested_termination: bool,
 
-    pub requested_termination: bool,
+    /// Every entity and component in the game.
+    pub world: World,
+    /// Deferred changes to `world`, applied at the end of every frame.
+    pub commands: Commands,
 }
 
 impl Default for Application {
     fn default() -> Self {
+        let world = World::default();
+        let commands = world.commands();
         Self {
             modules: HashMap::new(),
+            module_order: Vec::new(),
+            events: HashMap::new(),
             loop_function: Box::new(no_loop),
-
+            time: Time::default(),
+            states: StateStack::default(),
             requested_termination: false,
+
+            world,
+            commands,
         }
     }
 }
@@ -59,7 +86,10 @@ impl Application {
         T: Module,
     {
         module.build(self);
-        self.modules.insert(module.type_id(), Box::new(module));
+        let id = TypeId::of::<T>();
+        if self.modules.insert(id, Box::new(module)).is_none() {
+            self.module_order.push(id);
+        }
         self
     }
 
@@ -74,35 +104,153 @@ impl Application {
     /// Checks to see if `Application` has a module of type `T`
     /// and returns a mutable reference if found.
     /// Returns `Option<&mut T>
+    /// Note: A module is not accessible from within its own `update`.
     pub fn get_module_mut<T>(&mut self) -> Option<&mut T>
     where
         T: Module,
     {
-        Some(
-            self.modules
-                .get_mut(&TypeId::of::<T>())
-                .unwrap()
+        self.modules.get_mut(&TypeId::of::<T>()).map(|module| {
+            module
                 .as_any_mut()
                 .downcast_mut::<T>()
-                .expect("[Application]: Module downcast failed!"),
-        )
+                .expect("[Application]: Module downcast failed!")
+        })
     }
 
     /// Checks to see if `Application` has a module of type `T`
     /// and returns an immutable reference if found.
     /// Returns `Option<&T>
-    pub fn get_module<T>(&mut self) -> Option<&T>
+    /// Note: A module is not accessible from within its own `update`.
+    pub fn get_module<T>(&self) -> Option<&T>
     where
         T: Module,
     {
-        Some(
-            self.modules
-                .get(&TypeId::of::<T>())
-                .unwrap()
+        self.modules.get(&TypeId::of::<T>()).map(|module| {
+            module
                 .as_any()
                 .downcast_ref::<T>()
-                .expect("[Application]: Module downcast failed!"),
-        )
+                .expect("[Application]: Module downcast failed!")
+        })
+    }
+
+    /// Frame timing information.
+    pub fn get_time(&self) -> &Time {
+        &self.time
+    }
+
+    pub fn get_time_mut(&mut self) -> &mut Time {
+        &mut self.time
+    }
+
+    // ---------------------------------------------------------
+    //                  States
+    // ---------------------------------------------------------
+
+    /// Requests that `state` is pushed on top of the state stack.
+    pub fn push_state(&mut self, state: impl State) {
+        self.send_event(StateTransition::Push(Box::new(state)));
+    }
+
+    /// Requests that the top state is popped off the state stack.
+    pub fn pop_state(&mut self) {
+        self.send_event(StateTransition::Pop);
+    }
+
+    /// Requests that the top state is replaced by `state`.
+    pub fn switch_state(&mut self, state: impl State) {
+        self.send_event(StateTransition::Switch(Box::new(state)));
+    }
+
+    /// Requests that the application shuts down at the end of the frame.
+    pub fn request_termination(&mut self) {
+        self.send_event(StateTransition::Quit);
+    }
+
+    /// Returns true once a quit transition was applied.
+    pub fn is_termination_requested(&self) -> bool {
+        self.requested_termination
+    }
+
+    /// The number of states on the state stack.
+    pub fn state_count(&self) -> usize {
+        self.states.len()
+    }
+
+    /// Applies the requested state transitions, including the ones
+    /// requested by the state callbacks themselves.
+    fn apply_state_transitions(&mut self) {
+        let mut states = std::mem::take(&mut self.states);
+        while let Some(events) = self.get_events_mut::<StateTransition>() {
+            let transitions: Vec<StateTransition> = events.drain().collect();
+            if transitions.is_empty() {
+                break;
+            }
+            for transition in transitions {
+                if states.transition(transition, self) {
+                    self.requested_termination = true;
+                }
+            }
+        }
+        self.states = states;
+    }
+
+    // ---------------------------------------------------------
+    //                  Event Channels
+    // ---------------------------------------------------------
+
+    /// Registers an event channel for events of type `T`.
+    /// Does nothing if the channel already exists.
+    pub fn add_event<T>(&mut self) -> &mut Self
+    where
+        T: Send + Sync + 'static,
+    {
+        self.events
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(Events::<T>::default()));
+        self
+    }
+
+    /// Sends `event` through the channel of type `T`, registering the
+    /// channel if needed.
+    pub fn send_event<T>(&mut self, event: T)
+    where
+        T: Send + Sync + 'static,
+    {
+        self.add_event::<T>();
+        self.get_events_mut::<T>().unwrap().send(event);
+    }
+
+    /// Returns the event channel of type `T` if it was registered.
+    pub fn get_events<T>(&self) -> Option<&Events<T>>
+    where
+        T: Send + Sync + 'static,
+    {
+        self.events.get(&TypeId::of::<T>()).map(|channel| {
+            channel
+                .as_any()
+                .downcast_ref::<Events<T>>()
+                .expect("[Application]: Event channel downcast failed!")
+        })
+    }
+
+    /// Returns the event channel of type `T` if it was registered.
+    pub fn get_events_mut<T>(&mut self) -> Option<&mut Events<T>>
+    where
+        T: Send + Sync + 'static,
+    {
+        self.events.get_mut(&TypeId::of::<T>()).map(|channel| {
+            channel
+                .as_any_mut()
+                .downcast_mut::<Events<T>>()
+                .expect("[Application]: Event channel downcast failed!")
+        })
+    }
+
+    /// Swaps the buffers of every event channel.
+    fn update_events(&mut self) {
+        for channel in self.events.values_mut() {
+            channel.update();
+        }
     }
 
     // ---------------------------------------------------------
@@ -112,32 +260,83 @@ impl Application {
     /// Updates game logic.
     /// Called once every frame. 
     pub fn update(&mut self) {
-        // Update shit
+        self.time.update(Instant::now());
+        self.run_frame();
+    }
+
+    /// Updates game logic as if exactly `delta` passed since the last frame.
+    /// Used to drive the application with a controllable clock.
+    pub fn update_with_delta(&mut self, delta: Duration) {
+        self.time.advance(delta);
+        self.run_frame();
+    }
+
+    fn run_frame(&mut self) {
+        // Each module is taken out of the application while it updates
+        // so that it can freely access the rest of the application.
+        for index in 0..self.module_order.len() {
+            let id = self.module_order[index];
+            if let Some(mut module) = self.modules.remove(&id) {
+                module.update(self);
+                self.modules.insert(id, module);
+            }
+        }
+
+        let mut states = std::mem::take(&mut self.states);
+        states.update(self);
+        self.states = states;
+
+        self.commands.apply(&mut self.world);
+        self.apply_state_transitions();
+        propagate_transforms(&mut self.world);
+
+        // Swapped after the modules read them, so events sent before the
+        // frame, e.g. window events, are also readable during the next one.
+        self.update_events();
     }
     
     /// Renders the game entities to the window.
     /// Returns a boolean whether there was an error that 
     /// requires the application to shut down.
     pub fn render(&mut self) -> bool {
+        let mut states = std::mem::take(&mut self.states);
+        states.render(self);
+        self.states = states;
+
         let mut error = false;
-        if let Some(r) = self.get_module_mut::<Renderer>() {
-            error = r.update();
+        // Borrow the renderer and the world separately
+        let renderer = self
+            .modules
+            .get_mut(&TypeId::of::<Renderer>())
+            .and_then(|module| module.as_any_mut().downcast_mut::<Renderer>());
+        if let Some(r) = renderer {
+            r.submit_world(&mut self.world);
+            error = r.render_frame();
         }
         return error
     }
 
-    /// Handles the window resize
-    pub fn window_resize(&mut self, width: u32, height: u32) {
-        if let Some(r) = self.get_module_mut::<Renderer>() {
-            r.resize(width, height);
-        }
-    }
+    /// Handles the input events of `window`
+    pub fn update_input(&mut self, window: WindowId, event: &WindowEvent) -> bool {
+        let input_event = {
+            let mut input = INPUT.lock().unwrap();
+            input.set_event_window(window);
+            input.update(event)
+        };
 
-    /// Handles the window input events
-    pub fn update_input(&mut self, event: &WindowEvent) -> bool {
-        let input_event = INPUT.lock().unwrap().update(event);
+        if INPUT.lock().unwrap().get_key_pressed(PKeyCode::Escape) {
+            self.request_termination();
+        }
 
-        self.requested_termination = INPUT.lock().unwrap().get_key_pressed(PKeyCode::Escape);
+        if let Some(r) = self.get_module_mut::<Renderer>() {
+            let screenshot_pressed = r
+                .get_screenshot_key()
+                .map(|key_code| INPUT.lock().unwrap().get_key_pressed(key_code))
+                .unwrap_or(false);
+            if screenshot_pressed {
+                r.take_screenshot();
+            }
+        }
 
         input_event
     }
@@ -145,7 +344,35 @@ impl Application {
     pub fn create_state(&mut self, window: &winit::window::Window ) {
         if let Some(r) = self.get_module_mut::<Renderer>() {
             r.create_state(window);
+            let size = window.inner_size();
+            self.send_event(RendererSetup {
+                width: size.width,
+                height: size.height,
+            });
+        }
+    }
+
+    /// Creates the renderer's swap chain for a secondary window.
+    pub fn create_window_state(&mut self, id: WindowId, window: &winit::window::Window) {
+        if let Some(r) = self.get_module_mut::<Renderer>() {
+            r.create_window_state(id, window);
+        }
+    }
+
+    /// Drops the renderer's swap chain of a closed window.
+    pub fn remove_window_state(&mut self, id: WindowId) {
+        if let Some(r) = self.get_module_mut::<Renderer>() {
+            r.remove_window_state(id);
+        }
+    }
+
+    /// Creates an offscreen renderer state, for running without a window.
+    pub fn create_offscreen_state(&mut self, width: u32, height: u32) -> anyhow::Result<()> {
+        if let Some(r) = self.get_module_mut::<Renderer>() {
+            r.create_offscreen_state(width, height)?;
+            self.send_event(RendererSetup { width, height });
         }
+        Ok(())
     }
 
     
@@ -0,0 +1,150 @@
+use crate::core::{
+    entity::{Entity, EntityReserver},
+    world::{Component, World},
+};
+
+// -------------------------------------------------------------------------------
+//                      - Bundle -
+// -------------------------------------------------------------------------------
+
+/// A group of components that can be attached to an entity at once.
+/// Implemented for tuples of up to eight components.
+pub trait Bundle: Send + Sync + 'static {
+    fn insert_into(self, world: &mut World, entity: Entity);
+}
+
+macro_rules! impl_bundle_tuple {
+    ($($name:ident),*) => {
+        #[allow(non_snake_case)]
+        impl<$($name: Component),*> Bundle for ($($name,)*) {
+            fn insert_into(self, world: &mut World, entity: Entity) {
+                let ($($name,)*) = self;
+                $(world.insert(entity, $name);)*
+            }
+        }
+    };
+}
+
+impl Bundle for () {
+    fn insert_into(self, _world: &mut World, _entity: Entity) {}
+}
+
+impl_bundle_tuple!(A);
+impl_bundle_tuple!(A, B);
+impl_bundle_tuple!(A, B, C);
+impl_bundle_tuple!(A, B, C, D);
+impl_bundle_tuple!(A, B, C, D, E);
+impl_bundle_tuple!(A, B, C, D, E, F);
+impl_bundle_tuple!(A, B, C, D, E, F, G);
+impl_bundle_tuple!(A, B, C, D, E, F, G, H);
+
+// -------------------------------------------------------------------------------
+//                      - Commands -
+// -------------------------------------------------------------------------------
+
+type Command = Box<dyn FnOnce(&mut World) + Send + Sync>;
+
+/// Queues changes to a [`World`] so they can be requested while the world
+/// is being iterated. The [`Application`](crate::core::application::Application)
+/// applies its command buffer at the end of every frame.
+///
+/// Created with [`World::commands`], and only meant to be applied to
+/// that world.
+pub struct Commands {
+    queue: Vec<Command>,
+    reserver: EntityReserver,
+}
+
+impl Commands {
+    pub(crate) fn new(reserver: EntityReserver) -> Self {
+        Self {
+            queue: Vec::new(),
+            reserver,
+        }
+    }
+
+    /// Reserves an entity and spawns it with the components in `bundle`
+    /// once applied. The entity can be used by later commands right away,
+    /// e.g. to parent other entities to it.
+    pub fn spawn<B>(&mut self, bundle: B) -> Entity
+    where
+        B: Bundle,
+    {
+        let entity = self.reserver.reserve();
+        self.push(move |world| world.insert_bundle(entity, bundle));
+        entity
+    }
+
+    /// Despawns `entity` and all of its components.
+    pub fn despawn(&mut self, entity: Entity) -> &mut Self {
+        self.push(move |world| {
+            world.despawn(entity);
+        })
+    }
+
+    /// Attaches `component` to `entity`.
+    pub fn insert<T>(&mut self, entity: Entity, component: T) -> &mut Self
+    where
+        T: Component,
+    {
+        self.push(move |world| {
+            world.insert(entity, component);
+        })
+    }
+
+    /// Detaches the component of type `T` from `entity`.
+    pub fn remove<T>(&mut self, entity: Entity) -> &mut Self
+    where
+        T: Component,
+    {
+        self.push(move |world| {
+            world.remove::<T>(entity);
+        })
+    }
+
+    /// Queues an arbitrary change to the world.
+    pub fn push(&mut self, command: impl FnOnce(&mut World) + Send + Sync + 'static) -> &mut Self {
+        self.queue.push(Box::new(command));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Runs the queued commands in order and empties the buffer.
+    pub fn apply(&mut self, world: &mut World) {
+        world.flush_entities();
+        for command in self.queue.drain(..) {
+            command(world);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::{entity::Entity, world::World};
+
+    struct Name(&'static str);
+
+    struct Parent(Entity);
+
+    #[test]
+    fn spawned_entities_can_be_used_by_later_commands() {
+        let mut world = World::default();
+        let mut commands = world.commands();
+        let parent = commands.spawn((Name("parent"),));
+        let child = commands.spawn((Name("child"),));
+        commands.insert(child, Parent(parent));
+        // Entities spawned directly don't reuse reserved indices
+        let direct = world.spawn((Name("direct"),));
+        assert_ne!(direct, parent);
+        assert_ne!(direct, child);
+
+        world.apply_commands(&mut commands);
+        assert!(world.is_alive(parent));
+        assert_eq!(world.get::<Name>(parent).map(|name| name.0), Some("parent"));
+        assert_eq!(world.get::<Parent>(child).map(|parent| parent.0), Some(parent));
+        assert_eq!(world.len(), 3);
+    }
+}
@@ -0,0 +1,136 @@
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
+
+// -------------------------------------------------------------------------------
+//                      - Entity -
+// -------------------------------------------------------------------------------
+
+/// A handle to a game object stored in a [`World`](crate::core::world::World).
+///
+/// The `generation` is bumped every time an index is reused, so a handle
+/// to a despawned entity never aliases a newer one.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Entity {
+    index: u32,
+    generation: u32,
+}
+
+impl Entity {
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+// -------------------------------------------------------------------------------
+//                      - Entities -
+// -------------------------------------------------------------------------------
+
+/// Allocates entity handles and recycles the indices of despawned entities.
+#[derive(Default)]
+pub(crate) struct Entities {
+    generations: Vec<u32>,
+    alive: Vec<bool>,
+    free_indices: Vec<u32>,
+    len: usize,
+    // The first index that was never handed out, shared with the
+    // reservers of command buffers.
+    next_index: Arc<AtomicU32>,
+}
+
+impl Entities {
+    pub fn alloc(&mut self) -> Entity {
+        self.flush();
+        if let Some(index) = self.free_indices.pop() {
+            self.alive[index as usize] = true;
+            self.len += 1;
+            return Entity {
+                index,
+                generation: self.generations[index as usize],
+            };
+        }
+
+        let index = self.next_index.fetch_add(1, Ordering::Relaxed);
+        self.flush();
+        Entity {
+            index,
+            generation: 0,
+        }
+    }
+
+    /// Makes the entities reserved since the last flush alive.
+    pub fn flush(&mut self) {
+        let next_index = self.next_index.load(Ordering::Relaxed) as usize;
+        while self.generations.len() < next_index {
+            self.generations.push(0);
+            self.alive.push(true);
+            self.len += 1;
+        }
+    }
+
+    pub fn reserver(&self) -> EntityReserver {
+        EntityReserver(self.next_index.clone())
+    }
+
+    /// Frees the entity's index for reuse.
+    /// Returns false if the entity was already dead.
+    pub fn free(&mut self, entity: Entity) -> bool {
+        if !self.is_alive(entity) {
+            return false;
+        }
+        let index = entity.index as usize;
+        self.alive[index] = false;
+        self.generations[index] = self.generations[index].wrapping_add(1);
+        self.free_indices.push(entity.index);
+        self.len -= 1;
+        true
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        let index = entity.index as usize;
+        index < self.alive.len()
+            && self.alive[index]
+            && self.generations[index] == entity.generation
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Iterates over every live entity.
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.alive
+            .iter()
+            .zip(self.generations.iter())
+            .enumerate()
+            .filter(|(_, (alive, _))| **alive)
+            .map(|(index, (_, generation))| Entity {
+                index: index as u32,
+                generation: *generation,
+            })
+    }
+}
+
+// -------------------------------------------------------------------------------
+//                      - Entity Reserver -
+// -------------------------------------------------------------------------------
+
+/// Hands out entities of a world without borrowing it. The entities come
+/// alive, without any components, the next time the world allocates or
+/// applies commands.
+#[derive(Clone)]
+pub(crate) struct EntityReserver(Arc<AtomicU32>);
+
+impl EntityReserver {
+    pub fn reserve(&self) -> Entity {
+        Entity {
+            index: self.0.fetch_add(1, Ordering::Relaxed),
+            generation: 0,
+        }
+    }
+}
@@ -1,13 +1,419 @@
-use crate::renderer::state_descriptor::StateDescriptor;
+use std::any::Any;
+use std::marker::PhantomData;
+use std::path::PathBuf;
 
-pub enum PaopuEvent {
-    // -----------------------------------------------
-    //          - Window Events -
-    // -----------------------------------------------
-    WindowResize { id: i8, width: u32, height: u32 },
-    // -----------------------------------------------
-    //          - Renderer Events -
-    // -----------------------------------------------
-    RendererSetup { id: i8, state: Option<StateDescriptor> },
+// -------------------------------------------------------------------------------
+//                      - Events -
+// -------------------------------------------------------------------------------
+
+struct EventInstance<T> {
+    id: usize,
+    event: T,
+}
+
+/// A double buffered channel of events of type `T`.
+///
+/// Events sent during a frame, or before it like window events, can be
+/// read during that frame and the next one, after which they are dropped. Each consumer keeps its own
+/// [`EventReader`] so multiple modules can read the same events.
+pub struct Events<T> {
+    previous: Vec<EventInstance<T>>,
+    current: Vec<EventInstance<T>>,
+    event_count: usize,
+}
+
+impl<T> Default for Events<T> {
+    fn default() -> Self {
+        Self {
+            previous: Vec::new(),
+            current: Vec::new(),
+            event_count: 0,
+        }
+    }
+}
+
+impl<T> Events<T> {
+    /// Sends an event that readers can consume this frame and the next.
+    pub fn send(&mut self, event: T) {
+        self.current.push(EventInstance {
+            id: self.event_count,
+            event,
+        });
+        self.event_count += 1;
+    }
+
+    /// Creates a reader that will see every event still alive in the channel.
+    pub fn get_reader(&self) -> EventReader<T> {
+        EventReader {
+            last_event_count: 0,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Creates a reader that will only see events sent after its creation.
+    pub fn get_reader_current(&self) -> EventReader<T> {
+        EventReader {
+            last_event_count: self.event_count,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Swaps the buffers, dropping the events that were sent two frames ago.
+    /// Called by the [`Application`](crate::core::application::Application)
+    /// at the end of every frame, after the modules updated.
+    pub fn update(&mut self) {
+        self.previous = std::mem::take(&mut self.current);
+    }
+
+    /// Removes and returns every event still alive in the channel.
+    /// Meant for channels that have a single consumer taking ownership
+    /// of the events.
+    pub fn drain(&mut self) -> impl Iterator<Item = T> + '_ {
+        self.previous
+            .drain(..)
+            .chain(self.current.drain(..))
+            .map(|instance| instance.event)
+    }
+
+    /// Removes every event from the channel.
+    pub fn clear(&mut self) {
+        self.previous.clear();
+        self.current.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.previous.is_empty() && self.current.is_empty()
+    }
+
+    /// Iterates over every event still alive in the channel without a cursor.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.previous
+            .iter()
+            .chain(self.current.iter())
+            .map(|instance| &instance.event)
+    }
+}
+
+// -------------------------------------------------------------------------------
+//                      - Event Reader -
+// -------------------------------------------------------------------------------
+
+/// A per-consumer cursor into an [`Events`] channel.
+pub struct EventReader<T> {
+    last_event_count: usize,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Default for EventReader<T> {
+    fn default() -> Self {
+        Self {
+            last_event_count: 0,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> EventReader<T> {
+    /// Iterates over the events this reader hasn't seen yet and
+    /// advances the cursor past them.
+    pub fn iter<'a>(&mut self, events: &'a Events<T>) -> impl Iterator<Item = &'a T> {
+        let last_event_count = self.last_event_count;
+        self.last_event_count = events.event_count;
+        events
+            .previous
+            .iter()
+            .chain(events.current.iter())
+            .filter(move |instance| instance.id >= last_event_count)
+            .map(|instance| &instance.event)
+    }
+
+    /// Returns the newest event this reader hasn't seen yet, skipping the rest.
+    pub fn latest<'a>(&mut self, events: &'a Events<T>) -> Option<&'a T> {
+        self.iter(events).last()
+    }
+}
+
+// -------------------------------------------------------------------------------
+//                      - Event Channel -
+// -------------------------------------------------------------------------------
+
+/// Type erased access to an [`Events`] channel so the application can
+/// store channels of different event types together.
+pub(crate) trait EventChannel: Any + Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    fn update(&mut self);
+}
+
+impl<T> EventChannel for Events<T>
+where
+    T: Send + Sync + 'static,
+{
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn update(&mut self) {
+        Events::update(self);
+    }
+}
+
+// -------------------------------------------------------------------------------
+//                      - Engine Events -
+// -------------------------------------------------------------------------------
+
+/// Identifies one of the application's windows.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct WindowId(pub(crate) usize);
+
+impl WindowId {
+    /// The window the application starts with. Closing it quits.
+    pub const PRIMARY: WindowId = WindowId(0);
+}
+
+impl Default for WindowId {
+    fn default() -> Self {
+        WindowId::PRIMARY
+    }
+}
+
+/// Sent when the window's inner size changed, in physical pixels.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct WindowResized {
+    pub window: WindowId,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Sent when the window moved to a monitor with a different scale factor,
+/// or the OS scale setting changed. Followed by a [`WindowResized`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ScaleFactorChanged {
+    pub window: WindowId,
+    pub scale_factor: f64,
+}
+
+/// Sent when the window gained or lost keyboard focus.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct WindowFocused {
+    pub window: WindowId,
+    pub focused: bool,
+}
+
+/// Sent when the window was minimized or restored.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct WindowMinimized {
+    pub window: WindowId,
+    pub minimized: bool,
+}
+
+/// Sent when the window moved, with its new position in physical pixels.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct WindowMoved {
+    pub window: WindowId,
+    pub x: i32,
+    pub y: i32,
+}
+
+/// Sent when the user asked to close the window.
+/// The window closes once the frame ends unless the game vetoes it with
+/// [`WindowState::veto_close`](crate::window::window::WindowState::veto_close).
+/// Closing the primary window quits the application.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct WindowCloseRequested {
+    pub window: WindowId,
+}
+
+/// Sent when the cursor entered the window.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CursorEntered {
+    pub window: WindowId,
+}
+
+/// Sent when the cursor left the window.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CursorLeft {
+    pub window: WindowId,
+}
+
+/// Sent when a file is dragged over the window.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileHovered {
+    pub window: WindowId,
+    pub path: PathBuf,
+}
+
+/// Sent when files dragged over the window left it without being dropped.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FileHoverCancelled {
+    pub window: WindowId,
+}
+
+/// Sent when a file was dropped onto the window.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileDropped {
+    pub window: WindowId,
+    pub path: PathBuf,
+}
+
+/// The OS color scheme.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Theme {
+    Light,
+    Dark,
+}
+
+/// Sent when the OS color scheme changed. Only reported on Windows.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ThemeChanged {
+    pub window: WindowId,
+    pub theme: Theme,
 }
 
+/// Sent when a window other than the primary one was opened.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct WindowOpened {
+    pub window: WindowId,
+}
+
+/// Sent when a window other than the primary one was closed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct WindowClosed {
+    pub window: WindowId,
+}
+
+/// Sent once the renderer created its GPU state and is ready to draw.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RendererSetup {
+    pub width: u32,
+    pub height: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::any::Any;
+    use std::time::Duration;
+
+    use crate::core::{
+        application::Application,
+        events::{EventReader, Events},
+        module::Module,
+    };
+
+    fn read(reader: &mut EventReader<u32>, events: &Events<u32>) -> Vec<u32> {
+        reader.iter(events).copied().collect()
+    }
+
+    #[test]
+    fn readers_keep_their_own_cursor() {
+        let mut events = Events::default();
+        let mut first = events.get_reader();
+        let mut second = events.get_reader();
+        events.send(1);
+        events.send(2);
+        assert_eq!(read(&mut first, &events), vec![1, 2]);
+        assert!(read(&mut first, &events).is_empty());
+
+        events.send(3);
+        assert_eq!(read(&mut first, &events), vec![3]);
+        assert_eq!(read(&mut second, &events), vec![1, 2, 3]);
+        events.send(4);
+        events.send(5);
+        assert_eq!(first.latest(&events), Some(&5));
+        assert!(read(&mut first, &events).is_empty());
+    }
+
+    #[test]
+    fn events_live_for_two_updates() {
+        let mut events = Events::default();
+        let mut reader = events.get_reader();
+        events.send(1);
+        events.update();
+        events.send(2);
+        assert_eq!(events.iter().copied().collect::<Vec<_>>(), vec![1, 2]);
+
+        events.update();
+        assert_eq!(events.iter().copied().collect::<Vec<_>>(), vec![2]);
+        // Missed by a reader that didn't read in time
+        assert_eq!(read(&mut reader, &events), vec![2]);
+        events.update();
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn current_readers_skip_earlier_events() {
+        let mut events = Events::default();
+        events.send(1);
+        events.update();
+        events.send(2);
+        let mut reader = events.get_reader_current();
+        assert!(read(&mut reader, &events).is_empty());
+        events.send(3);
+        assert_eq!(read(&mut reader, &events), vec![3]);
+        assert_eq!(read(&mut events.get_reader(), &events), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn drain_takes_both_buffers() {
+        let mut events = Events::default();
+        let mut reader = events.get_reader();
+        events.send(1);
+        events.update();
+        events.send(2);
+        assert_eq!(events.drain().collect::<Vec<_>>(), vec![1, 2]);
+        assert!(events.is_empty());
+        assert!(read(&mut reader, &events).is_empty());
+        // Ids keep counting after a drain
+        events.send(3);
+        assert_eq!(read(&mut reader, &events), vec![3]);
+    }
+
+    /// Reads the `u32` events every other frame.
+    #[derive(Default)]
+    struct SlowReader {
+        skip: bool,
+        reader: EventReader<u32>,
+        read: Vec<u32>,
+    }
+
+    impl Module for SlowReader {
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn Any {
+            self
+        }
+
+        fn build(&self, _app: &mut Application) {}
+
+        fn update(&mut self, app: &mut Application) {
+            self.skip = !self.skip;
+            if !self.skip {
+                let events = app.get_events::<u32>().unwrap();
+                self.read.extend(self.reader.iter(events).copied());
+            }
+        }
+    }
+
+    #[test]
+    fn events_sent_before_a_frame_are_readable_in_the_next() {
+        let mut app = Application::default();
+        app.add_module(SlowReader::default());
+        // Sent before the frame, like window events
+        app.send_event(1u32);
+        app.update_with_delta(Duration::from_millis(16));
+        app.update_with_delta(Duration::from_millis(16));
+        app.send_event(2u32);
+        app.update_with_delta(Duration::from_millis(16));
+        app.send_event(3u32);
+        app.update_with_delta(Duration::from_millis(16));
+        assert_eq!(app.get_module::<SlowReader>().unwrap().read, vec![1, 2, 3]);
+    }
+}
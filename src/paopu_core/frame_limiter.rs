@@ -0,0 +1,87 @@
+use std::time::{Duration, Instant};
+
+// -------------------------------------------------------------------------------
+//                      - Frame Rate -
+// -------------------------------------------------------------------------------
+
+/// The target frame rate of the main game loop.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum FrameRate {
+    /// Runs frames as fast as possible.
+    /// Note: This forces the renderer's present mode to `Immediate`, or
+    /// `Fifo` where it isn't supported.
+    Uncapped,
+    /// Runs at most `N` frames per second.
+    Fixed(u32),
+    /// Lets the swap chain pace the frames to the display refresh rate.
+    /// Note: This forces the renderer's present mode to `Fifo`.
+    #[default]
+    VSync,
+}
+
+// -------------------------------------------------------------------------------
+//                      - Frame Limiter -
+// -------------------------------------------------------------------------------
+
+/// Keeps track of when the next frame is allowed to run.
+pub struct FrameLimiter {
+    frame_rate: FrameRate,
+    next_frame: Instant,
+}
+
+impl FrameLimiter {
+    pub fn new(frame_rate: FrameRate) -> Self {
+        Self {
+            frame_rate,
+            next_frame: Instant::now(),
+        }
+    }
+
+    pub fn frame_rate(&self) -> FrameRate {
+        self.frame_rate
+    }
+
+    pub fn set_frame_rate(&mut self, frame_rate: FrameRate) {
+        if self.frame_rate != frame_rate {
+            self.frame_rate = frame_rate;
+            self.next_frame = Instant::now();
+        }
+    }
+
+    /// The time a single frame is allowed to take, if the frame rate is capped.
+    pub fn frame_duration(&self) -> Option<Duration> {
+        match self.frame_rate {
+            FrameRate::Fixed(fps) if fps > 0 => Some(Duration::from_secs(1) / fps),
+            _ => None,
+        }
+    }
+
+    /// Returns true if a new frame should be run at `now`.
+    pub fn should_run(&self, now: Instant) -> bool {
+        now >= self.next_frame
+    }
+
+    /// Marks that a frame was started at `now` and schedules the next one.
+    pub fn begin_frame(&mut self, now: Instant) {
+        if let Some(frame_duration) = self.frame_duration() {
+            self.next_frame += frame_duration;
+            // If we fell more than a frame behind, don't try to catch up
+            // by running a burst of frames.
+            if self.next_frame < now {
+                self.next_frame = now + frame_duration;
+            }
+        }
+    }
+
+    /// The instant the next frame should run at, or `None` if frames
+    /// should run back to back.
+    pub fn next_frame(&self) -> Option<Instant> {
+        self.frame_duration().map(|_| self.next_frame)
+    }
+}
+
+impl Default for FrameLimiter {
+    fn default() -> Self {
+        Self::new(FrameRate::default())
+    }
+}
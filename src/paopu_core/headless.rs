@@ -0,0 +1,389 @@
+use std::{
+    any::Any,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    core::application::Application,
+    core::events::WindowId,
+    core::module::Module,
+    input::input::{Input, PKeyCode, PMouseButton, INPUT},
+    window::clipboard::Clipboard,
+};
+use lazy_static::lazy_static;
+use winit::event::ElementState;
+
+lazy_static! {
+    // Held while a headless frame has its input swapped into `INPUT`.
+    static ref HEADLESS_INPUT_LOCK: Mutex<()> = Mutex::new(());
+}
+
+// -------------------------------------------------------------------------------
+//                      - Headless -
+// -------------------------------------------------------------------------------
+
+/// Runs the application without a window or GPU, e.g. for dedicated
+/// servers. Replaces the loop function installed by the window module.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut app = Application::default();
+/// app.add_module(Headless::default().with_frame_limit(600));
+/// app.run();
+/// ```
+pub struct Headless {
+    frame_delta: Duration,
+    frame_limit: Option<u64>,
+    real_time: bool,
+}
+
+impl Module for Headless {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn build(&self, app: &mut Application) {
+        app.set_loop_function(headless_run);
+    }
+}
+
+impl Default for Headless {
+    fn default() -> Self {
+        Headless {
+            frame_delta: Duration::from_secs(1) / 60,
+            frame_limit: None,
+            real_time: true,
+        }
+    }
+}
+
+impl Headless {
+    /// Sets the simulated time between two frames.
+    pub fn with_frame_delta(mut self, frame_delta: Duration) -> Self {
+        self.frame_delta = frame_delta;
+        self
+    }
+
+    /// Stops the loop after `frames` frames, even if termination was never requested.
+    pub fn with_frame_limit(mut self, frames: u64) -> Self {
+        self.frame_limit = Some(frames);
+        self
+    }
+
+    /// Sets whether the loop sleeps so frames are spaced `frame_delta` apart
+    /// in real time. Disable it to simulate as fast as possible.
+    pub fn with_real_time(mut self, real_time: bool) -> Self {
+        self.real_time = real_time;
+        self
+    }
+}
+
+/// The loop function installed by the [`Headless`] module.
+pub fn headless_run(app: Application) {
+    let (frame_delta, frame_limit, real_time) = {
+        let headless = app.get_module::<Headless>().unwrap();
+        (headless.frame_delta, headless.frame_limit, headless.real_time)
+    };
+
+    let mut runner = HeadlessApp::new(app).with_frame_delta(frame_delta);
+    let mut next_frame = Instant::now();
+    loop {
+        if let Some(limit) = frame_limit {
+            if runner.frame_count() >= limit {
+                break;
+            }
+        }
+        if real_time {
+            let now = Instant::now();
+            if next_frame > now {
+                std::thread::sleep(next_frame - now);
+            }
+            next_frame += frame_delta;
+        }
+        if !runner.step() {
+            break;
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------
+//                      - Headless App -
+// -------------------------------------------------------------------------------
+
+/// Steps an [`Application`] frame by frame with a controllable clock, so
+/// gameplay modules can be driven from tests.
+///
+/// Every runner keeps its own [`Input`], swapped into [`INPUT`] only while
+/// its frames run, so runners on parallel test threads don't see each
+/// other's synthetic input. Its clipboard is an in-memory one, so tests
+/// never touch the system clipboard.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut app = Application::default();
+/// app.add_module(MyGameplay::default());
+/// let mut runner = HeadlessApp::new(app);
+/// runner.press_key(PKeyCode::Space);
+/// runner.run_frames(10);
+/// assert!(runner.app().world.len() > 0);
+/// ```
+pub struct HeadlessApp {
+    app: Application,
+    frame_delta: Duration,
+    frame_count: u64,
+    input: Input,
+}
+
+impl HeadlessApp {
+    pub fn new(app: Application) -> Self {
+        Self {
+            app,
+            frame_delta: Duration::from_secs(1) / 60,
+            frame_count: 0,
+            input: Input::default().with_clipboard(Clipboard::memory()),
+        }
+    }
+
+    /// Sets the time that passes between two frames.
+    pub fn with_frame_delta(mut self, frame_delta: Duration) -> Self {
+        self.frame_delta = frame_delta;
+        self
+    }
+
+    pub fn app(&self) -> &Application {
+        &self.app
+    }
+
+    pub fn app_mut(&mut self) -> &mut Application {
+        &mut self.app
+    }
+
+    /// Gives the application back, e.g. to inspect it after a test.
+    pub fn into_app(self) -> Application {
+        self.app
+    }
+
+    /// The number of frames run so far.
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// The runner's input, as the next frame will see it.
+    pub fn input(&self) -> &Input {
+        &self.input
+    }
+
+    /// The runner's in-memory clipboard.
+    pub fn clipboard_mut(&mut self) -> &mut Clipboard {
+        self.input.get_clipboard_mut()
+    }
+
+    /// Presses a key. The next frame sees it pressed, and it stays held
+    /// until [`HeadlessApp::release_key`].
+    pub fn press_key(&mut self, key_code: PKeyCode) {
+        self.input.add_key_input(key_code, &ElementState::Pressed);
+    }
+
+    /// Releases a key. The next frame sees it released.
+    pub fn release_key(&mut self, key_code: PKeyCode) {
+        self.input.add_key_input(key_code, &ElementState::Released);
+    }
+
+    /// Presses a mouse button, held until [`HeadlessApp::release_mouse_button`].
+    pub fn press_mouse_button(&mut self, button: PMouseButton) {
+        self.input.add_mouse_button_input(button, &ElementState::Pressed);
+    }
+
+    pub fn release_mouse_button(&mut self, button: PMouseButton) {
+        self.input.add_mouse_button_input(button, &ElementState::Released);
+    }
+
+    /// Moves the cursor to `position`, in physical pixels of `window`, or
+    /// out of every window if `None`.
+    pub fn set_cursor_position(&mut self, window: WindowId, position: Option<(f32, f32)>) {
+        self.input.set_cursor_position(window, position);
+    }
+
+    /// Sends a synthetic event, e.g. a
+    /// [`WindowResized`](crate::core::events::WindowResized), that the
+    /// next frame will see.
+    pub fn send_event<T>(&mut self, event: T)
+    where
+        T: Send + Sync + 'static,
+    {
+        self.app.send_event(event);
+    }
+
+    /// Runs a single frame with the configured frame delta.
+    /// Returns false once termination was requested.
+    pub fn step(&mut self) -> bool {
+        self.step_with_delta(self.frame_delta)
+    }
+
+    /// Runs a single frame as if `delta` passed since the last one.
+    /// Returns false once termination was requested.
+    pub fn step_with_delta(&mut self, delta: Duration) -> bool {
+        if self.app.is_termination_requested() {
+            return false;
+        }
+
+        // A frame that panicked still released the lock
+        let _lock = HEADLESS_INPUT_LOCK
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        std::mem::swap(&mut *INPUT.lock().unwrap(), &mut self.input);
+
+        self.app.update_with_delta(delta);
+        self.app.render();
+        self.frame_count += 1;
+
+        std::mem::swap(&mut *INPUT.lock().unwrap(), &mut self.input);
+        // Presses and releases last a single frame, held keys stay held
+        self.input.clear_keys();
+        !self.app.is_termination_requested()
+    }
+
+    /// Runs up to `frames` frames, stopping early if termination was
+    /// requested. Returns the number of frames that ran.
+    pub fn run_frames(&mut self, frames: u64) -> u64 {
+        let start = self.frame_count;
+        for _ in 0..frames {
+            if !self.step() {
+                break;
+            }
+        }
+        self.frame_count - start
+    }
+
+    /// Runs frames until termination is requested, or until `max_frames`
+    /// frames ran. Returns true if the application terminated.
+    pub fn run_until_terminated(&mut self, max_frames: u64) -> bool {
+        self.run_frames(max_frames);
+        self.app.is_termination_requested()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::any::Any;
+
+    use crate::{
+        core::{application::Application, events::WindowId, headless::HeadlessApp, module::Module},
+        input::input::{PKeyCode, PMouseButton, INPUT},
+    };
+
+    /// The input a frame saw.
+    struct FrameInput {
+        space_pressed: bool,
+        space_held: bool,
+        left_held: bool,
+        cursor: Option<(f32, f32)>,
+        clipboard: Option<String>,
+    }
+
+    /// Records the input every frame sees.
+    #[derive(Default)]
+    struct InputLog {
+        frames: Vec<FrameInput>,
+    }
+
+    impl Module for InputLog {
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn Any {
+            self
+        }
+
+        fn build(&self, _app: &mut Application) {}
+
+        fn update(&mut self, _app: &mut Application) {
+            let mut input = INPUT.lock().unwrap();
+            self.frames.push(FrameInput {
+                space_pressed: input.get_key_pressed(PKeyCode::Space),
+                space_held: input.get_key_held(PKeyCode::Space),
+                left_held: input.get_mouse_button_held(PMouseButton::Left),
+                cursor: input.get_cursor_position(),
+                clipboard: input.get_clipboard_mut().get_text().unwrap(),
+            });
+        }
+    }
+
+    fn runner() -> HeadlessApp {
+        let mut app = Application::default();
+        app.add_module(InputLog::default());
+        HeadlessApp::new(app)
+    }
+
+    fn log(runner: &HeadlessApp) -> &[FrameInput] {
+        &runner.app().get_module::<InputLog>().unwrap().frames
+    }
+
+    #[test]
+    fn keys_stay_held_until_released() {
+        let mut runner = runner();
+        runner.press_key(PKeyCode::Space);
+        runner.run_frames(3);
+        runner.release_key(PKeyCode::Space);
+        runner.run_frames(1);
+        assert_eq!(
+            log(&runner).iter().map(|frame| (frame.space_pressed, frame.space_held)).collect::<Vec<_>>(),
+            vec![(true, true), (false, true), (false, true), (false, false)]
+        );
+    }
+
+    #[test]
+    fn mouse_buttons_and_cursor_are_injected() {
+        let mut runner = runner();
+        runner.set_cursor_position(WindowId::PRIMARY, Some((4.0, 2.0)));
+        runner.press_mouse_button(PMouseButton::Left);
+        runner.run_frames(2);
+        runner.release_mouse_button(PMouseButton::Left);
+        runner.set_cursor_position(WindowId::PRIMARY, None);
+        runner.run_frames(1);
+        assert_eq!(
+            log(&runner).iter().map(|frame| (frame.left_held, frame.cursor)).collect::<Vec<_>>(),
+            vec![(true, Some((4.0, 2.0))), (true, Some((4.0, 2.0))), (false, None)]
+        );
+    }
+
+    #[test]
+    fn runners_use_their_own_memory_clipboard() {
+        let mut runner = runner();
+        runner.run_frames(1);
+        runner.clipboard_mut().set_text("spawn slime 3 4").unwrap();
+        runner.run_frames(1);
+        assert_eq!(
+            log(&runner).iter().map(|frame| frame.clipboard.as_deref()).collect::<Vec<_>>(),
+            vec![None, Some("spawn slime 3 4")]
+        );
+    }
+
+    #[test]
+    fn parallel_runners_keep_their_own_input() {
+        let threads: Vec<_> = (0..4)
+            .map(|index| {
+                std::thread::spawn(move || {
+                    let mut runner = runner();
+                    let held = index % 2 == 0;
+                    if held {
+                        runner.press_key(PKeyCode::Space);
+                    }
+                    runner.run_frames(200);
+                    log(&runner).iter().all(|frame| frame.space_held == held)
+                })
+            })
+            .collect();
+        for thread in threads {
+            assert!(thread.join().unwrap());
+        }
+    }
+}
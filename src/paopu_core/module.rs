@@ -9,6 +9,10 @@ pub trait Module: Any + Send + Sync {
 
     fn build(&self, app: &mut Application);
 
+    /// Called once every frame, in the order modules were added.
+    /// The module is detached from `app` for the duration of the call.
+    fn update(&mut self, _app: &mut Application) {}
+
     fn name(&self) -> &str {
         std::any::type_name::<Self>()
     }
@@ -1,4 +1,12 @@
 pub mod application;
-pub mod event_system;
+pub mod commands;
+pub mod entity;
 pub mod events;
+pub mod frame_limiter;
+pub mod headless;
 pub mod module;
+pub mod query;
+pub mod state;
+pub mod time;
+pub mod transform;
+pub mod world;
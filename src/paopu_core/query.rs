@@ -0,0 +1,533 @@
+use std::{
+    any::TypeId,
+    collections::HashMap,
+    marker::PhantomData,
+};
+
+use crate::core::{
+    entity::Entity,
+    world::{Component, ComponentStorage, SparseSet, World},
+};
+
+type RawStorages = HashMap<TypeId, *mut dyn ComponentStorage>;
+
+// -------------------------------------------------------------------------------
+//                      - Query -
+// -------------------------------------------------------------------------------
+
+/// A set of components that can be fetched together from a [`World`].
+///
+/// Implemented for `&T`, `&mut T`, `Option<&T>`, `Option<&mut T>`,
+/// [`Entity`] and tuples of up to eight of them.
+pub trait Query {
+    type Fetch: for<'w> Fetch<'w>;
+}
+
+/// Borrows the storages of a [`Query`] for the lifetime `'w`.
+pub trait Fetch<'w>: Sized {
+    type Item;
+
+    /// Records every component type this fetch reads (`false`) or writes (`true`).
+    fn access(access: &mut Vec<(TypeId, bool)>);
+
+    /// Returns `None` if a required storage doesn't exist yet, in which
+    /// case the query matches nothing.
+    ///
+    /// # Safety
+    /// The pointers in `storages` must be valid for `'w` and the access
+    /// reported by [`Fetch::access`] must not conflict.
+    unsafe fn new(storages: &RawStorages) -> Option<Self>;
+
+    /// The entities of the smallest required storage, if any.
+    fn candidates(&self) -> Option<&[Entity]>;
+
+    /// # Safety
+    /// Must be called at most once per entity while the items are alive.
+    unsafe fn fetch(&self, entity: Entity) -> Option<Self::Item>;
+}
+
+/// The storage of `T`, for fetches that only read it. Several of them can
+/// point into the same storage, so no `&mut` may be created from it.
+unsafe fn downcast_storage<T>(storages: &RawStorages) -> Option<*const SparseSet<T>>
+where
+    T: Component,
+{
+    storages.get(&TypeId::of::<T>()).map(|storage| {
+        (**storage)
+            .as_any()
+            .downcast_ref::<SparseSet<T>>()
+            .expect("[Query]: Storage downcast failed!") as *const SparseSet<T>
+    })
+}
+
+/// The storage of `T`, for the one fetch allowed to write it.
+unsafe fn downcast_storage_mut<T>(storages: &RawStorages) -> Option<*mut SparseSet<T>>
+where
+    T: Component,
+{
+    storages.get(&TypeId::of::<T>()).map(|storage| {
+        (**storage)
+            .as_any_mut()
+            .downcast_mut::<SparseSet<T>>()
+            .expect("[Query]: Storage downcast failed!") as *mut SparseSet<T>
+    })
+}
+
+// ---------------------------------------------------------
+//                  &T
+// ---------------------------------------------------------
+
+pub struct FetchRead<T>(*const SparseSet<T>);
+
+impl<T> Query for &T
+where
+    T: Component,
+{
+    type Fetch = FetchRead<T>;
+}
+
+impl<'w, T> Fetch<'w> for FetchRead<T>
+where
+    T: Component,
+{
+    type Item = &'w T;
+
+    fn access(access: &mut Vec<(TypeId, bool)>) {
+        access.push((TypeId::of::<T>(), false));
+    }
+
+    unsafe fn new(storages: &RawStorages) -> Option<Self> {
+        downcast_storage::<T>(storages).map(FetchRead)
+    }
+
+    fn candidates(&self) -> Option<&[Entity]> {
+        Some(unsafe { (*self.0).entities() })
+    }
+
+    unsafe fn fetch(&self, entity: Entity) -> Option<Self::Item> {
+        (*self.0).get(entity)
+    }
+}
+
+// ---------------------------------------------------------
+//                  &mut T
+// ---------------------------------------------------------
+
+pub struct FetchWrite<T>(*mut SparseSet<T>);
+
+impl<T> Query for &mut T
+where
+    T: Component,
+{
+    type Fetch = FetchWrite<T>;
+}
+
+impl<'w, T> Fetch<'w> for FetchWrite<T>
+where
+    T: Component,
+{
+    type Item = &'w mut T;
+
+    fn access(access: &mut Vec<(TypeId, bool)>) {
+        access.push((TypeId::of::<T>(), true));
+    }
+
+    unsafe fn new(storages: &RawStorages) -> Option<Self> {
+        downcast_storage_mut::<T>(storages).map(FetchWrite)
+    }
+
+    fn candidates(&self) -> Option<&[Entity]> {
+        Some(unsafe { (*self.0).entities() })
+    }
+
+    unsafe fn fetch(&self, entity: Entity) -> Option<Self::Item> {
+        (*self.0).get_mut(entity)
+    }
+}
+
+// ---------------------------------------------------------
+//                  Option<Q>
+// ---------------------------------------------------------
+
+pub struct FetchOptional<F>(Option<F>);
+
+impl<Q> Query for Option<Q>
+where
+    Q: Query,
+{
+    type Fetch = FetchOptional<Q::Fetch>;
+}
+
+impl<'w, F> Fetch<'w> for FetchOptional<F>
+where
+    F: Fetch<'w>,
+{
+    type Item = Option<F::Item>;
+
+    fn access(access: &mut Vec<(TypeId, bool)>) {
+        F::access(access);
+    }
+
+    unsafe fn new(storages: &RawStorages) -> Option<Self> {
+        Some(FetchOptional(F::new(storages)))
+    }
+
+    fn candidates(&self) -> Option<&[Entity]> {
+        None
+    }
+
+    unsafe fn fetch(&self, entity: Entity) -> Option<Self::Item> {
+        Some(self.0.as_ref().and_then(|fetch| fetch.fetch(entity)))
+    }
+}
+
+// ---------------------------------------------------------
+//                  Entity
+// ---------------------------------------------------------
+
+pub struct FetchEntity;
+
+impl Query for Entity {
+    type Fetch = FetchEntity;
+}
+
+impl<'w> Fetch<'w> for FetchEntity {
+    type Item = Entity;
+
+    fn access(_access: &mut Vec<(TypeId, bool)>) {}
+
+    unsafe fn new(_storages: &RawStorages) -> Option<Self> {
+        Some(FetchEntity)
+    }
+
+    fn candidates(&self) -> Option<&[Entity]> {
+        None
+    }
+
+    unsafe fn fetch(&self, entity: Entity) -> Option<Self::Item> {
+        Some(entity)
+    }
+}
+
+// ---------------------------------------------------------
+//                  Tuples
+// ---------------------------------------------------------
+
+macro_rules! impl_query_tuple {
+    ($($name:ident),*) => {
+        impl<$($name: Query),*> Query for ($($name,)*) {
+            type Fetch = ($($name::Fetch,)*);
+        }
+
+        #[allow(non_snake_case)]
+        impl<'w, $($name: Fetch<'w>),*> Fetch<'w> for ($($name,)*) {
+            type Item = ($($name::Item,)*);
+
+            fn access(access: &mut Vec<(TypeId, bool)>) {
+                $($name::access(access);)*
+            }
+
+            unsafe fn new(storages: &RawStorages) -> Option<Self> {
+                Some(($($name::new(storages)?,)*))
+            }
+
+            fn candidates(&self) -> Option<&[Entity]> {
+                let ($($name,)*) = self;
+                let mut smallest: Option<&[Entity]> = None;
+                $(
+                    if let Some(candidates) = $name.candidates() {
+                        if smallest.map_or(true, |s| candidates.len() < s.len()) {
+                            smallest = Some(candidates);
+                        }
+                    }
+                )*
+                smallest
+            }
+
+            unsafe fn fetch(&self, entity: Entity) -> Option<Self::Item> {
+                let ($($name,)*) = self;
+                Some(($($name.fetch(entity)?,)*))
+            }
+        }
+    };
+}
+
+impl_query_tuple!(A);
+impl_query_tuple!(A, B);
+impl_query_tuple!(A, B, C);
+impl_query_tuple!(A, B, C, D);
+impl_query_tuple!(A, B, C, D, E);
+impl_query_tuple!(A, B, C, D, E, F);
+impl_query_tuple!(A, B, C, D, E, F, G);
+impl_query_tuple!(A, B, C, D, E, F, G, H);
+
+// -------------------------------------------------------------------------------
+//                      - Filters -
+// -------------------------------------------------------------------------------
+
+/// Restricts a query to entities that match without fetching anything.
+pub trait Filter {
+    fn matches(world: &World, entity: Entity) -> bool;
+}
+
+/// Matches entities that have a component of type `T`.
+pub struct With<T>(PhantomData<T>);
+
+/// Matches entities that don't have a component of type `T`.
+pub struct Without<T>(PhantomData<T>);
+
+impl Filter for () {
+    fn matches(_world: &World, _entity: Entity) -> bool {
+        true
+    }
+}
+
+impl<T> Filter for With<T>
+where
+    T: Component,
+{
+    fn matches(world: &World, entity: Entity) -> bool {
+        world
+            .raw_storage(TypeId::of::<T>())
+            .map(|storage| storage.contains(entity))
+            .unwrap_or(false)
+    }
+}
+
+impl<T> Filter for Without<T>
+where
+    T: Component,
+{
+    fn matches(world: &World, entity: Entity) -> bool {
+        !With::<T>::matches(world, entity)
+    }
+}
+
+macro_rules! impl_filter_tuple {
+    ($($name:ident),*) => {
+        impl<$($name: Filter),*> Filter for ($($name,)*) {
+            fn matches(world: &World, entity: Entity) -> bool {
+                $($name::matches(world, entity))&&*
+            }
+        }
+    };
+}
+
+impl_filter_tuple!(A);
+impl_filter_tuple!(A, B);
+impl_filter_tuple!(A, B, C);
+impl_filter_tuple!(A, B, C, D);
+
+// -------------------------------------------------------------------------------
+//                      - Query Iter -
+// -------------------------------------------------------------------------------
+
+/// Iterates over the entities matching the query `Q` and filter `F`.
+pub struct QueryIter<'w, Q, F>
+where
+    Q: Query,
+{
+    state: Option<Q::Fetch>,
+    entities: std::vec::IntoIter<Entity>,
+    _marker: PhantomData<(&'w mut World, F)>,
+}
+
+impl<'w, Q, F> QueryIter<'w, Q, F>
+where
+    Q: Query,
+    F: Filter,
+{
+    pub(crate) fn new(world: &'w mut World) -> Self {
+        let mut access = Vec::new();
+        <Q::Fetch as Fetch<'w>>::access(&mut access);
+        for (i, (id, write)) in access.iter().enumerate() {
+            for (other_id, other_write) in access.iter().skip(i + 1) {
+                if id == other_id && (*write || *other_write) {
+                    panic!("[Query]: Conflicting access to the same component in one query!");
+                }
+            }
+        }
+
+        // The candidate entities are collected up front, so the filter can
+        // look at the world before any component is borrowed mutably.
+        let entities: Vec<Entity> = {
+            let storages = world.raw_storages_mut();
+            let state = unsafe { <Q::Fetch as Fetch<'w>>::new(&storages) };
+            match state {
+                Some(state) => match state.candidates() {
+                    Some(candidates) => candidates.to_vec(),
+                    None => world.iter_entities().collect(),
+                },
+                None => Vec::new(),
+            }
+        };
+        let entities: Vec<Entity> = entities
+            .into_iter()
+            .filter(|entity| F::matches(world, *entity))
+            .collect();
+
+        let storages = world.raw_storages_mut();
+        let state = unsafe { <Q::Fetch as Fetch<'w>>::new(&storages) };
+
+        Self {
+            state,
+            entities: entities.into_iter(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<'w, Q, F> Iterator for QueryIter<'w, Q, F>
+where
+    Q: Query,
+{
+    type Item = <Q::Fetch as Fetch<'w>>::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let state = self.state.as_ref()?;
+        for entity in &mut self.entities {
+            // Each entity is only visited once, so mutable items never alias.
+            if let Some(item) = unsafe { state.fetch(entity) } {
+                return Some(item);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::{
+        entity::Entity,
+        query::{With, Without},
+        world::World,
+    };
+
+    #[derive(Debug, PartialEq)]
+    struct Position(i32);
+
+    #[derive(Debug, PartialEq)]
+    struct Velocity(i32);
+
+    struct Frozen;
+
+    #[test]
+    fn shared_reads_of_one_component_are_allowed() {
+        let mut world = World::default();
+        world.spawn((Position(1),));
+        assert_eq!(world.query::<(&Position, &Position)>().count(), 1);
+    }
+
+    // Also meant to run under Miri, `cargo +nightly miri test --lib core::query`,
+    // which catches aliasing mutable borrows of the storage.
+    #[test]
+    fn overlapping_reads_can_be_held_together() {
+        let mut world = World::default();
+        for index in 0..3 {
+            world.spawn((Position(index),));
+        }
+        let items: Vec<_> = world.query::<(&Position, &Position, Option<&Position>)>().collect();
+        let mut sums: Vec<i32> = items.iter().map(|(a, b, c)| a.0 + b.0 + c.unwrap().0).collect();
+        sums.sort();
+        assert_eq!(sums, vec![0, 3, 6]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Conflicting access")]
+    fn two_writes_of_one_component_panic() {
+        let mut world = World::default();
+        world.spawn((Position(1),));
+        let _ = world.query::<(&mut Position, &mut Position)>();
+    }
+
+    #[test]
+    #[should_panic(expected = "Conflicting access")]
+    fn read_and_write_of_one_component_panic() {
+        let mut world = World::default();
+        world.spawn((Position(1),));
+        let _ = world.query::<(&Position, Option<&mut Position>)>();
+    }
+
+    #[test]
+    fn optional_components_match_entities_without_them() {
+        let mut world = World::default();
+        let moving = world.spawn((Position(0), Velocity(2)));
+        let still = world.spawn((Position(5),));
+
+        let mut found: Vec<_> = world
+            .query::<(Entity, &Position, Option<&Velocity>)>()
+            .map(|(entity, position, velocity)| (entity, position.0, velocity.map(|v| v.0)))
+            .collect();
+        found.sort();
+        assert_eq!(found, vec![(moving, 0, Some(2)), (still, 5, None)]);
+
+        // A storage that doesn't exist yet matches as `None`
+        assert_eq!(world.query::<(&Position, Option<&Frozen>)>().count(), 2);
+        assert_eq!(world.query::<(&Position, &Frozen)>().count(), 0);
+    }
+
+    #[test]
+    fn with_and_without_filter_entities() {
+        let mut world = World::default();
+        world.spawn((Position(0), Velocity(1)));
+        world.spawn((Position(10), Velocity(1), Frozen));
+
+        for (position, velocity) in world.query_filtered::<(&mut Position, &Velocity), Without<Frozen>>() {
+            position.0 += velocity.0;
+        }
+        let mut positions: Vec<i32> = world.query::<&Position>().map(|position| position.0).collect();
+        positions.sort();
+        assert_eq!(positions, vec![1, 10]);
+
+        let frozen: Vec<i32> = world
+            .query_filtered::<&Position, (With<Frozen>, With<Velocity>)>()
+            .map(|position| position.0)
+            .collect();
+        assert_eq!(frozen, vec![10]);
+    }
+
+    #[test]
+    fn despawns_queued_during_iteration_apply_afterwards() {
+        let mut world = World::default();
+        let mut commands = world.commands();
+        for index in 0..4 {
+            world.spawn((Position(index),));
+        }
+
+        for (entity, position) in world.query::<(Entity, &Position)>() {
+            if position.0 % 2 == 0 {
+                commands.despawn(entity);
+            }
+        }
+        assert_eq!(world.query::<&Position>().count(), 4);
+
+        world.apply_commands(&mut commands);
+        let mut positions: Vec<i32> = world.query::<&Position>().map(|position| position.0).collect();
+        positions.sort();
+        assert_eq!(positions, vec![1, 3]);
+        assert_eq!(world.len(), 2);
+    }
+
+    #[test]
+    fn stale_entities_are_not_fetched() {
+        let mut world = World::default();
+        let old = world.spawn((Position(1),));
+        assert!(world.despawn(old));
+        let new = world.spawn((Position(2),));
+
+        // The index is reused with a new generation
+        assert_eq!(old.index(), new.index());
+        assert_ne!(old.generation(), new.generation());
+        assert!(!world.is_alive(old));
+        assert_eq!(world.get::<Position>(old), None);
+        assert_eq!(world.insert(old, Velocity(3)), None);
+        assert!(!world.has::<Velocity>(new));
+        assert!(!world.despawn(old));
+
+        let found: Vec<_> = world
+            .query::<(Entity, &Position)>()
+            .map(|(entity, position)| (entity, position.0))
+            .collect();
+        assert_eq!(found, vec![(new, 2)]);
+    }
+}
@@ -0,0 +1,132 @@
+use crate::core::application::Application;
+
+// -------------------------------------------------------------------------------
+//                      - State -
+// -------------------------------------------------------------------------------
+
+/// A screen or mode of the game, e.g. the title screen, gameplay or a
+/// pause menu. States live on a stack owned by the
+/// [`Application`]; only the top state updates.
+pub trait State: Send + Sync + 'static {
+    /// Called when the state is pushed onto the stack.
+    fn on_enter(&mut self, _app: &mut Application) {}
+
+    /// Called when the state is popped off the stack.
+    fn on_exit(&mut self, _app: &mut Application) {}
+
+    /// Called when another state is pushed on top of this one.
+    fn on_pause(&mut self, _app: &mut Application) {}
+
+    /// Called when the state above this one was popped.
+    fn on_resume(&mut self, _app: &mut Application) {}
+
+    /// Called once every frame while this is the top state.
+    fn update(&mut self, _app: &mut Application) {}
+
+    /// Called once every frame before the renderer draws, while the state
+    /// is visible.
+    fn render(&mut self, _app: &mut Application) {}
+
+    /// Whether the state below this one keeps rendering while this one
+    /// is on top, e.g. for a pause menu drawn over the game.
+    fn is_transparent(&self) -> bool {
+        false
+    }
+
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+}
+
+// -------------------------------------------------------------------------------
+//                      - State Transition -
+// -------------------------------------------------------------------------------
+
+/// A change to the state stack. Send these as events, e.g. through
+/// [`Application::push_state`], and they are applied at the end of the frame.
+pub enum StateTransition {
+    /// Pauses the top state and pushes a new one on top of it.
+    Push(Box<dyn State>),
+    /// Exits the top state and resumes the one below it.
+    /// Popping the last state quits the application.
+    Pop,
+    /// Exits the top state and replaces it with a new one.
+    Switch(Box<dyn State>),
+    /// Exits every state and stops the application.
+    Quit,
+}
+
+// -------------------------------------------------------------------------------
+//                      - State Stack -
+// -------------------------------------------------------------------------------
+
+#[derive(Default)]
+pub(crate) struct StateStack {
+    states: Vec<Box<dyn State>>,
+}
+
+impl StateStack {
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn update(&mut self, app: &mut Application) {
+        if let Some(state) = self.states.last_mut() {
+            state.update(app);
+        }
+    }
+
+    /// Renders the visible states from the bottom up.
+    pub fn render(&mut self, app: &mut Application) {
+        let mut first_visible = self.states.len();
+        while first_visible > 0 {
+            first_visible -= 1;
+            if !self.states[first_visible].is_transparent() {
+                break;
+            }
+        }
+        for state in self.states.iter_mut().skip(first_visible) {
+            state.render(app);
+        }
+    }
+
+    /// Applies `transition` and returns true if the application should quit.
+    pub fn transition(&mut self, transition: StateTransition, app: &mut Application) -> bool {
+        match transition {
+            StateTransition::Push(mut state) => {
+                if let Some(top) = self.states.last_mut() {
+                    top.on_pause(app);
+                }
+                state.on_enter(app);
+                self.states.push(state);
+                false
+            }
+            StateTransition::Pop => {
+                if let Some(mut top) = self.states.pop() {
+                    top.on_exit(app);
+                }
+                match self.states.last_mut() {
+                    Some(top) => {
+                        top.on_resume(app);
+                        false
+                    }
+                    None => true,
+                }
+            }
+            StateTransition::Switch(mut state) => {
+                if let Some(mut top) = self.states.pop() {
+                    top.on_exit(app);
+                }
+                state.on_enter(app);
+                self.states.push(state);
+                false
+            }
+            StateTransition::Quit => {
+                while let Some(mut top) = self.states.pop() {
+                    top.on_exit(app);
+                }
+                true
+            }
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use std::time::{Duration, Instant};
+
+// -------------------------------------------------------------------------------
+//                      - Time -
+// -------------------------------------------------------------------------------
+
+/// Frame timing information, updated by the
+/// [`Application`](crate::core::application::Application) once per frame.
+pub struct Time {
+    last_update: Option<Instant>,
+    delta: Duration,
+    elapsed: Duration,
+    frame_count: u64,
+    paused: bool,
+}
+
+impl Default for Time {
+    fn default() -> Self {
+        Self {
+            last_update: None,
+            delta: Duration::from_secs(0),
+            elapsed: Duration::from_secs(0),
+            frame_count: 0,
+            paused: false,
+        }
+    }
+}
+
+impl Time {
+    /// Advances the clock to `now`.
+    pub fn update(&mut self, now: Instant) {
+        let delta = match self.last_update {
+            Some(last_update) => now.saturating_duration_since(last_update),
+            None => Duration::from_secs(0),
+        };
+        self.last_update = Some(now);
+        self.advance(delta);
+    }
+
+    /// Advances the clock by exactly `delta`.
+    /// While paused the delta is zero and no time elapses.
+    pub fn advance(&mut self, delta: Duration) {
+        let delta = if self.paused {
+            Duration::from_secs(0)
+        } else {
+            delta
+        };
+        self.delta = delta;
+        self.elapsed += delta;
+        self.frame_count += 1;
+    }
+
+    /// The time the last frame took.
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    /// The time the last frame took, in seconds.
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    /// The total time accumulated since the first frame.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// The number of frames run so far.
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// Stops time from passing. Frames still run, with a zero delta.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+}
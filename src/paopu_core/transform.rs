@@ -0,0 +1,359 @@
+use cgmath::{InnerSpace, Matrix4, Rad, SquareMatrix, Vector2, Vector3, Vector4};
+
+use crate::core::{
+    entity::Entity,
+    query::{With, Without},
+    world::World,
+};
+
+// -------------------------------------------------------------------------------
+//                      - Transform 2D -
+// -------------------------------------------------------------------------------
+
+/// The position, rotation and scale of an entity relative to its [`Parent`],
+/// or to the world if it has none.
+/// World units are pixels with the y axis pointing up.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Transform2D {
+    pub translation: Vector2<f32>,
+    pub rotation: Rad<f32>,
+    pub scale: Vector2<f32>,
+    /// The depth of the entity. Added to the parent's depth.
+    pub z: f32,
+}
+
+impl Default for Transform2D {
+    fn default() -> Self {
+        Self {
+            translation: Vector2::new(0.0, 0.0),
+            rotation: Rad(0.0),
+            scale: Vector2::new(1.0, 1.0),
+            z: 0.0,
+        }
+    }
+}
+
+impl Transform2D {
+    pub fn from_translation(x: f32, y: f32) -> Self {
+        Self {
+            translation: Vector2::new(x, y),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_rotation(mut self, rotation: impl Into<Rad<f32>>) -> Self {
+        self.rotation = rotation.into();
+        self
+    }
+
+    pub fn with_scale(mut self, x: f32, y: f32) -> Self {
+        self.scale = Vector2::new(x, y);
+        self
+    }
+
+    pub fn with_z(mut self, z: f32) -> Self {
+        self.z = z;
+        self
+    }
+
+    /// Builds the matrix that applies scale, then rotation, then translation.
+    pub fn compute_matrix(&self) -> Matrix4<f32> {
+        Matrix4::from_translation(Vector3::new(self.translation.x, self.translation.y, self.z))
+            * Matrix4::from_angle_z(self.rotation)
+            * Matrix4::from_nonuniform_scale(self.scale.x, self.scale.y, 1.0)
+    }
+
+    /// Decomposes a 2D affine matrix back into a transform.
+    /// Any shear in the matrix is lost.
+    pub fn from_matrix(matrix: &Matrix4<f32>) -> Self {
+        let x_axis = Vector2::new(matrix.x.x, matrix.x.y);
+        let y_axis = Vector2::new(matrix.y.x, matrix.y.y);
+        let determinant = x_axis.x * y_axis.y - x_axis.y * y_axis.x;
+        let scale_x = x_axis.magnitude();
+        let scale_y = y_axis.magnitude() * determinant.signum();
+        Self {
+            translation: Vector2::new(matrix.w.x, matrix.w.y),
+            rotation: Rad(x_axis.y.atan2(x_axis.x)),
+            scale: Vector2::new(scale_x, scale_y),
+            z: matrix.w.z,
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------
+//                      - Global Transform 2D -
+// -------------------------------------------------------------------------------
+
+/// The world space transform of an entity, computed from its [`Transform2D`]
+/// and those of its ancestors by [`propagate_transforms`].
+/// Inserted automatically for every entity with a [`Transform2D`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GlobalTransform2D {
+    matrix: Matrix4<f32>,
+    // The local transform the matrix was computed from, used to skip
+    // entities that haven't changed since the last propagation.
+    computed_from: Option<Transform2D>,
+}
+
+impl Default for GlobalTransform2D {
+    fn default() -> Self {
+        Self {
+            matrix: Matrix4::identity(),
+            computed_from: None,
+        }
+    }
+}
+
+impl GlobalTransform2D {
+    pub fn matrix(&self) -> Matrix4<f32> {
+        self.matrix
+    }
+
+    pub fn translation(&self) -> Vector2<f32> {
+        Vector2::new(self.matrix.w.x, self.matrix.w.y)
+    }
+
+    pub fn z(&self) -> f32 {
+        self.matrix.w.z
+    }
+
+    /// Transforms a point from the entity's local space into world space.
+    pub fn transform_point(&self, point: Vector2<f32>) -> Vector2<f32> {
+        let p = self.matrix * Vector4::new(point.x, point.y, 0.0, 1.0);
+        Vector2::new(p.x, p.y)
+    }
+
+    /// Decomposes the world space matrix into a transform.
+    pub fn to_transform(&self) -> Transform2D {
+        Transform2D::from_matrix(&self.matrix)
+    }
+
+    /// Forces the transform to be recomputed on the next propagation.
+    fn mark_dirty(&mut self) {
+        self.computed_from = None;
+    }
+}
+
+// -------------------------------------------------------------------------------
+//                      - Hierarchy -
+// -------------------------------------------------------------------------------
+
+/// The entity this entity's [`Transform2D`] is relative to.
+/// Use [`set_parent`] rather than inserting it directly so that the
+/// parent's [`Children`] stay in sync.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Parent(pub Entity);
+
+/// The entities that have this entity as their [`Parent`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Children(pub Vec<Entity>);
+
+/// Returns true if `ancestor` is `entity` or one of its ancestors.
+pub fn is_ancestor_of(world: &World, ancestor: Entity, entity: Entity) -> bool {
+    let mut current = Some(entity);
+    while let Some(e) = current {
+        if e == ancestor {
+            return true;
+        }
+        current = world.get::<Parent>(e).map(|parent| parent.0);
+    }
+    false
+}
+
+/// Makes `child` a child of `parent`, or a root if `parent` is `None`.
+/// The child's local transform is kept, so it moves with its new parent.
+/// Returns false if the change would create a cycle.
+pub fn set_parent(world: &mut World, child: Entity, parent: Option<Entity>) -> bool {
+    if let Some(parent) = parent {
+        if !world.is_alive(parent) || is_ancestor_of(world, child, parent) {
+            return false;
+        }
+    }
+
+    if let Some(Parent(old_parent)) = world.remove::<Parent>(child) {
+        if let Some(children) = world.get_mut::<Children>(old_parent) {
+            children.0.retain(|e| *e != child);
+        }
+    }
+
+    if let Some(parent) = parent {
+        world.insert(child, Parent(parent));
+        match world.get_mut::<Children>(parent) {
+            Some(children) => children.0.push(child),
+            None => {
+                world.insert(parent, Children(vec![child]));
+            }
+        }
+    }
+
+    if let Some(global) = world.get_mut::<GlobalTransform2D>(child) {
+        global.mark_dirty();
+    }
+    true
+}
+
+/// Like [`set_parent`], but rewrites the child's local transform so it
+/// keeps its current world position, rotation and scale.
+/// Uses the global transforms from the last propagation.
+pub fn set_parent_keep_world_transform(
+    world: &mut World,
+    child: Entity,
+    parent: Option<Entity>,
+) -> bool {
+    let child_global = match world.get::<GlobalTransform2D>(child) {
+        Some(global) => global.matrix(),
+        None => return set_parent(world, child, parent),
+    };
+    let parent_global = parent
+        .and_then(|p| world.get::<GlobalTransform2D>(p))
+        .map(|global| global.matrix())
+        .unwrap_or_else(Matrix4::identity);
+
+    if !set_parent(world, child, parent) {
+        return false;
+    }
+
+    if let Some(parent_inverse) = parent_global.invert() {
+        let local = Transform2D::from_matrix(&(parent_inverse * child_global));
+        world.insert(child, local);
+    }
+    true
+}
+
+/// Despawns `entity` along with all of its descendants.
+pub fn despawn_recursive(world: &mut World, entity: Entity) {
+    set_parent(world, entity, None);
+    despawn_with_children(world, entity);
+}
+
+fn despawn_with_children(world: &mut World, entity: Entity) {
+    if let Some(Children(children)) = world.remove::<Children>(entity) {
+        for child in children {
+            despawn_with_children(world, child);
+        }
+    }
+    world.despawn(entity);
+}
+
+// -------------------------------------------------------------------------------
+//                      - Propagation -
+// -------------------------------------------------------------------------------
+
+/// Recomputes the [`GlobalTransform2D`] of every entity whose transform,
+/// or an ancestor's transform, changed since the last call.
+/// Called by the [`Application`](crate::core::application::Application)
+/// once per frame, after the modules updated.
+pub fn propagate_transforms(world: &mut World) {
+    let missing: Vec<Entity> = world
+        .query_filtered::<Entity, (With<Transform2D>, Without<GlobalTransform2D>)>()
+        .collect();
+    for entity in missing {
+        world.insert(entity, GlobalTransform2D::default());
+    }
+
+    // Entities whose parent is gone, or has no transform, act as roots.
+    // Their global transform may still include the old parent's, so it is
+    // always recomputed, and a despawned parent's link is dropped.
+    let candidates: Vec<(Entity, Option<Entity>)> = world
+        .query_filtered::<(Entity, Option<&Parent>), With<Transform2D>>()
+        .map(|(entity, parent)| (entity, parent.map(|p| p.0)))
+        .collect();
+    for (entity, parent) in candidates {
+        match parent {
+            None => propagate_recursive(world, entity, Matrix4::identity(), false),
+            Some(parent) if !world.has::<Transform2D>(parent) => {
+                if !world.is_alive(parent) {
+                    world.remove::<Parent>(entity);
+                }
+                propagate_recursive(world, entity, Matrix4::identity(), true);
+            }
+            Some(_) => {}
+        }
+    }
+}
+
+fn propagate_recursive(
+    world: &mut World,
+    entity: Entity,
+    parent_matrix: Matrix4<f32>,
+    parent_changed: bool,
+) {
+    let local = match world.get::<Transform2D>(entity) {
+        Some(local) => *local,
+        None => return,
+    };
+    let global = match world.get_mut::<GlobalTransform2D>(entity) {
+        Some(global) => global,
+        None => return,
+    };
+
+    let changed = parent_changed || global.computed_from != Some(local);
+    if changed {
+        global.matrix = parent_matrix * local.compute_matrix();
+        global.computed_from = Some(local);
+    }
+    let matrix = global.matrix;
+
+    let children = match world.get::<Children>(entity) {
+        Some(children) => children.0.clone(),
+        None => return,
+    };
+    for child in children {
+        propagate_recursive(world, child, matrix, changed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::Vector2;
+
+    use crate::core::{
+        entity::Entity,
+        transform::{propagate_transforms, set_parent, GlobalTransform2D, Parent, Transform2D},
+        world::World,
+    };
+
+    fn global_translation(world: &World, entity: Entity) -> Vector2<f32> {
+        world.get::<GlobalTransform2D>(entity).unwrap().translation()
+    }
+
+    #[test]
+    fn orphaned_children_drop_the_parent_transform() {
+        let mut world = World::default();
+        let parent = world.spawn((Transform2D::from_translation(100.0, 0.0),));
+        let child = world.spawn((Transform2D::from_translation(1.0, 2.0),));
+        let grandchild = world.spawn((Transform2D::from_translation(0.0, 1.0),));
+        set_parent(&mut world, child, Some(parent));
+        set_parent(&mut world, grandchild, Some(child));
+        propagate_transforms(&mut world);
+        assert_eq!(global_translation(&world, child), Vector2::new(101.0, 2.0));
+        assert_eq!(global_translation(&world, grandchild), Vector2::new(101.0, 3.0));
+
+        // Despawned without unparenting the child
+        world.despawn(parent);
+        propagate_transforms(&mut world);
+        assert_eq!(global_translation(&world, child), Vector2::new(1.0, 2.0));
+        assert_eq!(global_translation(&world, grandchild), Vector2::new(1.0, 3.0));
+        assert!(!world.has::<Parent>(child));
+    }
+
+    #[test]
+    fn children_of_a_parent_without_transform_act_as_roots() {
+        let mut world = World::default();
+        let parent = world.spawn((Transform2D::from_translation(0.0, 50.0),));
+        let child = world.spawn((Transform2D::from_translation(3.0, 0.0),));
+        set_parent(&mut world, child, Some(parent));
+        propagate_transforms(&mut world);
+        assert_eq!(global_translation(&world, child), Vector2::new(3.0, 50.0));
+
+        world.remove::<Transform2D>(parent);
+        propagate_transforms(&mut world);
+        assert_eq!(global_translation(&world, child), Vector2::new(3.0, 0.0));
+        // The parent is still alive, so the link is kept
+        assert_eq!(world.get::<Parent>(child), Some(&Parent(parent)));
+
+        world.insert(parent, Transform2D::from_translation(0.0, -10.0));
+        propagate_transforms(&mut world);
+        assert_eq!(global_translation(&world, child), Vector2::new(3.0, -10.0));
+    }
+}
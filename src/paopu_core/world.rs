@@ -0,0 +1,353 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+};
+
+use crate::core::{
+    commands::{Bundle, Commands},
+    entity::{Entities, Entity},
+    query::{Filter, Query, QueryIter},
+};
+
+// -------------------------------------------------------------------------------
+//                      - Component -
+// -------------------------------------------------------------------------------
+
+/// Any type that is `Send + Sync + 'static` can be attached to an entity.
+pub trait Component: Send + Sync + 'static {}
+
+impl<T> Component for T where T: Send + Sync + 'static {}
+
+// -------------------------------------------------------------------------------
+//                      - Sparse Set -
+// -------------------------------------------------------------------------------
+
+/// Stores the components of a single type densely packed, with a sparse
+/// lookup table indexed by entity index.
+pub struct SparseSet<T> {
+    sparse: Vec<Option<u32>>,
+    dense: Vec<T>,
+    entities: Vec<Entity>,
+}
+
+impl<T> Default for SparseSet<T> {
+    fn default() -> Self {
+        Self {
+            sparse: Vec::new(),
+            dense: Vec::new(),
+            entities: Vec::new(),
+        }
+    }
+}
+
+impl<T> SparseSet<T> {
+    fn dense_index(&self, entity: Entity) -> Option<usize> {
+        let dense_index = (*self.sparse.get(entity.index() as usize)?)? as usize;
+        if self.entities[dense_index] == entity {
+            Some(dense_index)
+        } else {
+            None
+        }
+    }
+
+    /// Inserts `value` for `entity`, returning the previous value if any.
+    pub fn insert(&mut self, entity: Entity, value: T) -> Option<T> {
+        if let Some(dense_index) = self.dense_index(entity) {
+            return Some(std::mem::replace(&mut self.dense[dense_index], value));
+        }
+
+        let index = entity.index() as usize;
+        if index >= self.sparse.len() {
+            self.sparse.resize(index + 1, None);
+        }
+        self.sparse[index] = Some(self.dense.len() as u32);
+        self.dense.push(value);
+        self.entities.push(entity);
+        None
+    }
+
+    pub fn remove(&mut self, entity: Entity) -> Option<T> {
+        let dense_index = self.dense_index(entity)?;
+        self.sparse[entity.index() as usize] = None;
+        self.entities.swap_remove(dense_index);
+        let value = self.dense.swap_remove(dense_index);
+        // Patch up the lookup of the component that was moved into the hole
+        if let Some(moved) = self.entities.get(dense_index) {
+            self.sparse[moved.index() as usize] = Some(dense_index as u32);
+        }
+        Some(value)
+    }
+
+    pub fn get(&self, entity: Entity) -> Option<&T> {
+        self.dense_index(entity).map(move |i| &self.dense[i])
+    }
+
+    pub fn get_mut(&mut self, entity: Entity) -> Option<&mut T> {
+        match self.dense_index(entity) {
+            Some(i) => Some(&mut self.dense[i]),
+            None => None,
+        }
+    }
+
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.dense_index(entity).is_some()
+    }
+
+    pub fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    pub fn len(&self) -> usize {
+        self.dense.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dense.is_empty()
+    }
+}
+
+/// Type erased access to a [`SparseSet`].
+pub trait ComponentStorage: Any + Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    fn remove_entity(&mut self, entity: Entity);
+
+    fn contains(&self, entity: Entity) -> bool;
+}
+
+impl<T> ComponentStorage for SparseSet<T>
+where
+    T: Component,
+{
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn remove_entity(&mut self, entity: Entity) {
+        self.remove(entity);
+    }
+
+    fn contains(&self, entity: Entity) -> bool {
+        SparseSet::contains(self, entity)
+    }
+}
+
+// -------------------------------------------------------------------------------
+//                      - World -
+// -------------------------------------------------------------------------------
+
+/// Owns every entity and its components.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut world = World::default();
+/// let entity = world.spawn((Position(0.0), Velocity(1.0)));
+/// for (position, velocity) in world.query::<(&mut Position, &Velocity)>() {
+///     position.0 += velocity.0;
+/// }
+/// ```
+#[derive(Default)]
+pub struct World {
+    entities: Entities,
+    storages: HashMap<TypeId, Box<dyn ComponentStorage>>,
+}
+
+impl World {
+    /// Creates an entity without any components.
+    pub fn spawn_empty(&mut self) -> Entity {
+        self.entities.alloc()
+    }
+
+    /// Creates an entity with every component in `bundle`.
+    pub fn spawn<B>(&mut self, bundle: B) -> Entity
+    where
+        B: Bundle,
+    {
+        let entity = self.entities.alloc();
+        bundle.insert_into(self, entity);
+        entity
+    }
+
+    /// Destroys `entity` and all of its components.
+    /// Returns false if the entity was already despawned.
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        self.entities.flush();
+        if !self.entities.free(entity) {
+            return false;
+        }
+        for storage in self.storages.values_mut() {
+            storage.remove_entity(entity);
+        }
+        true
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.entities.is_alive(entity)
+    }
+
+    /// The number of live entities.
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.len() == 0
+    }
+
+    /// Iterates over every live entity.
+    pub fn iter_entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.entities.iter()
+    }
+
+    /// Attaches `component` to `entity`, returning the previous component
+    /// of the same type. Does nothing if the entity is dead.
+    pub fn insert<T>(&mut self, entity: Entity, component: T) -> Option<T>
+    where
+        T: Component,
+    {
+        self.entities.flush();
+        if !self.is_alive(entity) {
+            return None;
+        }
+        self.storage_mut_or_insert::<T>().insert(entity, component)
+    }
+
+    /// Attaches every component in `bundle` to `entity`.
+    pub fn insert_bundle<B>(&mut self, entity: Entity, bundle: B)
+    where
+        B: Bundle,
+    {
+        self.entities.flush();
+        if self.is_alive(entity) {
+            bundle.insert_into(self, entity);
+        }
+    }
+
+    /// Detaches the component of type `T` from `entity` and returns it.
+    pub fn remove<T>(&mut self, entity: Entity) -> Option<T>
+    where
+        T: Component,
+    {
+        self.storage_mut::<T>()?.remove(entity)
+    }
+
+    pub fn get<T>(&self, entity: Entity) -> Option<&T>
+    where
+        T: Component,
+    {
+        self.storage::<T>()?.get(entity)
+    }
+
+    pub fn get_mut<T>(&mut self, entity: Entity) -> Option<&mut T>
+    where
+        T: Component,
+    {
+        self.storage_mut::<T>()?.get_mut(entity)
+    }
+
+    pub fn has<T>(&self, entity: Entity) -> bool
+    where
+        T: Component,
+    {
+        self.storage::<T>()
+            .map(|storage| storage.contains(entity))
+            .unwrap_or(false)
+    }
+
+    /// Iterates over every entity that matches `Q`.
+    ///
+    /// `Q` is a component reference (`&T`, `&mut T`, `Option<&T>`, `Entity`)
+    /// or a tuple of them. Panics if `Q` accesses a component mutably more
+    /// than once.
+    pub fn query<Q>(&mut self) -> QueryIter<'_, Q, ()>
+    where
+        Q: Query,
+    {
+        QueryIter::new(self)
+    }
+
+    /// Iterates over every entity that matches `Q` and passes the filter `F`,
+    /// e.g. `With<T>`, `Without<T>` or a tuple of filters.
+    pub fn query_filtered<Q, F>(&mut self) -> QueryIter<'_, Q, F>
+    where
+        Q: Query,
+        F: Filter,
+    {
+        QueryIter::new(self)
+    }
+
+    /// Creates a command buffer whose spawns reserve entities of this world.
+    pub fn commands(&self) -> Commands {
+        Commands::new(self.entities.reserver())
+    }
+
+    /// Runs every command queued in `commands`.
+    pub fn apply_commands(&mut self, commands: &mut Commands) {
+        commands.apply(self);
+    }
+
+    /// Makes the entities reserved by command buffers alive.
+    pub(crate) fn flush_entities(&mut self) {
+        self.entities.flush();
+    }
+
+    // ---------------------------------------------------------
+    //                  Storage
+    // ---------------------------------------------------------
+
+    pub fn storage<T>(&self) -> Option<&SparseSet<T>>
+    where
+        T: Component,
+    {
+        self.storages.get(&TypeId::of::<T>()).map(|storage| {
+            storage
+                .as_any()
+                .downcast_ref::<SparseSet<T>>()
+                .expect("[World]: Storage downcast failed!")
+        })
+    }
+
+    pub fn storage_mut<T>(&mut self) -> Option<&mut SparseSet<T>>
+    where
+        T: Component,
+    {
+        self.storages.get_mut(&TypeId::of::<T>()).map(|storage| {
+            storage
+                .as_any_mut()
+                .downcast_mut::<SparseSet<T>>()
+                .expect("[World]: Storage downcast failed!")
+        })
+    }
+
+    fn storage_mut_or_insert<T>(&mut self) -> &mut SparseSet<T>
+    where
+        T: Component,
+    {
+        self.storages
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(SparseSet::<T>::default()))
+            .as_any_mut()
+            .downcast_mut::<SparseSet<T>>()
+            .expect("[World]: Storage downcast failed!")
+    }
+
+    pub(crate) fn raw_storage(&self, id: TypeId) -> Option<&dyn ComponentStorage> {
+        self.storages.get(&id).map(|storage| &**storage)
+    }
+
+    /// Hands out a raw pointer to every storage in a single pass so that
+    /// queries can borrow several storages at once.
+    pub(crate) fn raw_storages_mut(&mut self) -> HashMap<TypeId, *mut dyn ComponentStorage> {
+        self.storages
+            .iter_mut()
+            .map(|(id, storage)| (*id, &mut **storage as *mut dyn ComponentStorage))
+            .collect()
+    }
+}
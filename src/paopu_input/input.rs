@@ -1,17 +1,21 @@
 use std::sync::Mutex;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use lazy_static::lazy_static;
 use winit::event::WindowEvent;
 use winit::event::*;
 
+use crate::core::events::WindowId;
+use crate::window::clipboard::Clipboard;
+
 lazy_static! {
     /// The application's input singleton.
     ///
     /// # Example
     ///
     /// ```
+    /// # use paopu_rs::input::input::INPUT;
     /// let mut input = INPUT.lock().unwrap();
-    /// *input.clear_keys();
+    /// input.clear_keys();
     /// ```
     pub static ref INPUT: Mutex<Input> = Mutex::new(Input::new());
 }
@@ -19,6 +23,19 @@ lazy_static! {
 pub struct Input {
     pressed_keys: HashSet<PKeyCode>,
     released_keys: HashSet<PKeyCode>,
+    // Pressed and not released since.
+    held_keys: HashSet<PKeyCode>,
+    pressed_buttons: HashSet<PMouseButton>,
+    released_buttons: HashSet<PMouseButton>,
+    held_buttons: HashSet<PMouseButton>,
+    // In physical pixels, `None` while the cursor is outside every window.
+    cursor_position: Option<(f32, f32)>,
+    cursor_window: WindowId,
+    // The window the events passed to `update` come from.
+    event_window: WindowId,
+    focused_window: Option<WindowId>,
+    scale_factors: HashMap<WindowId, f64>,
+    clipboard: Clipboard,
 }
 
 impl Input {
@@ -26,12 +43,27 @@ impl Input {
         Self {
             pressed_keys: HashSet::new(),
             released_keys: HashSet::new(),
+            held_keys: HashSet::new(),
+            pressed_buttons: HashSet::new(),
+            released_buttons: HashSet::new(),
+            held_buttons: HashSet::new(),
+            cursor_position: None,
+            cursor_window: WindowId::PRIMARY,
+            event_window: WindowId::PRIMARY,
+            focused_window: None,
+            scale_factors: HashMap::new(),
+            clipboard: Clipboard::system(),
         }
     }
 
+    /// Replaces the clipboard, e.g. with [`Clipboard::memory`] in tests.
+    pub fn with_clipboard(mut self, clipboard: Clipboard) -> Self {
+        self.clipboard = clipboard;
+        self
+    }
+
     pub fn update(&mut self, event: &WindowEvent) -> bool{
-        self.pressed_keys.clear();
-        self.released_keys.clear();
+        self.clear_keys();
         
         let mut was_input_event = true;
         
@@ -82,6 +114,18 @@ impl Input {
                     VirtualKeyCode::Key7 => self.add_key_input(PKeyCode::Key7, state),
                     VirtualKeyCode::Key8 => self.add_key_input(PKeyCode::Key8, state),
                     VirtualKeyCode::Key9 => self.add_key_input(PKeyCode::Key9, state),
+                    VirtualKeyCode::F1 => self.add_key_input(PKeyCode::F1, state),
+                    VirtualKeyCode::F2 => self.add_key_input(PKeyCode::F2, state),
+                    VirtualKeyCode::F3 => self.add_key_input(PKeyCode::F3, state),
+                    VirtualKeyCode::F4 => self.add_key_input(PKeyCode::F4, state),
+                    VirtualKeyCode::F5 => self.add_key_input(PKeyCode::F5, state),
+                    VirtualKeyCode::F6 => self.add_key_input(PKeyCode::F6, state),
+                    VirtualKeyCode::F7 => self.add_key_input(PKeyCode::F7, state),
+                    VirtualKeyCode::F8 => self.add_key_input(PKeyCode::F8, state),
+                    VirtualKeyCode::F9 => self.add_key_input(PKeyCode::F9, state),
+                    VirtualKeyCode::F10 => self.add_key_input(PKeyCode::F10, state),
+                    VirtualKeyCode::F11 => self.add_key_input(PKeyCode::F11, state),
+                    VirtualKeyCode::F12 => self.add_key_input(PKeyCode::F12, state),
                     VirtualKeyCode::Up => self.add_key_input(PKeyCode::Up, state),
                     VirtualKeyCode::Down => self.add_key_input(PKeyCode::Down, state),
                     VirtualKeyCode::Left => self.add_key_input(PKeyCode::Left, state),
@@ -93,6 +137,23 @@ impl Input {
                     _ => was_input_event = false,
                 }
             },
+            WindowEvent::MouseInput { state, button, .. } => match button {
+                MouseButton::Left => self.add_mouse_button_input(PMouseButton::Left, state),
+                MouseButton::Right => self.add_mouse_button_input(PMouseButton::Right, state),
+                MouseButton::Middle => self.add_mouse_button_input(PMouseButton::Middle, state),
+                MouseButton::Other(_) => was_input_event = false,
+            },
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor_position = Some((position.x as f32, position.y as f32));
+                self.cursor_window = self.event_window;
+            },
+            WindowEvent::CursorLeft { .. } => {
+                // The cursor may already have moved into another window
+                if self.cursor_window == self.event_window {
+                    self.cursor_position = None;
+                }
+                was_input_event = false;
+            },
             _ => was_input_event = false,
         }
 
@@ -104,19 +165,128 @@ impl Input {
         match state {
             ElementState::Pressed => {
                 self.pressed_keys.insert(code);
+                self.held_keys.insert(code);
                 //println!("{:?}", code);
                 ()
             },
             ElementState::Released => {
                 self.released_keys.insert(code);
+                self.held_keys.remove(&code);
                 ()
             },
         }
     }
 
+    pub fn add_mouse_button_input(&mut self, button: PMouseButton, state: &ElementState) {
+        match state {
+            ElementState::Pressed => {
+                self.pressed_buttons.insert(button);
+                self.held_buttons.insert(button);
+            }
+            ElementState::Released => {
+                self.released_buttons.insert(button);
+                self.held_buttons.remove(&button);
+            }
+        }
+    }
+
+    /// Forgets which keys and mouse buttons were just pressed or released.
+    /// Held ones stay held until they are released.
+    pub fn clear_keys(&mut self) {
+        self.pressed_keys.clear();
+        self.released_keys.clear();
+        self.pressed_buttons.clear();
+        self.released_buttons.clear();
+    }
+
     pub fn get_key_pressed(&mut self, key_code: PKeyCode) -> bool {
         self.pressed_keys.contains(&key_code)
     }
+
+    pub fn get_key_released(&self, key_code: PKeyCode) -> bool {
+        self.released_keys.contains(&key_code)
+    }
+
+    /// Whether the key was pressed and not released since.
+    pub fn get_key_held(&self, key_code: PKeyCode) -> bool {
+        self.held_keys.contains(&key_code)
+    }
+
+    pub fn get_mouse_button_pressed(&self, button: PMouseButton) -> bool {
+        self.pressed_buttons.contains(&button)
+    }
+
+    pub fn get_mouse_button_released(&self, button: PMouseButton) -> bool {
+        self.released_buttons.contains(&button)
+    }
+
+    /// Whether the mouse button was pressed and not released since.
+    pub fn get_mouse_button_held(&self, button: PMouseButton) -> bool {
+        self.held_buttons.contains(&button)
+    }
+
+    /// Sets the window the events passed to [`Input::update`] come from.
+    pub fn set_event_window(&mut self, window: WindowId) {
+        self.event_window = window;
+    }
+
+    /// The cursor position in physical pixels from the top left corner of
+    /// the window it is over, or `None` if it is outside every window.
+    pub fn get_cursor_position(&self) -> Option<(f32, f32)> {
+        self.cursor_position
+    }
+
+    /// The cursor position in logical pixels.
+    pub fn get_cursor_logical_position(&self) -> Option<(f32, f32)> {
+        let scale_factor = self.get_scale_factor(self.cursor_window) as f32;
+        self.cursor_position
+            .map(|(x, y)| (x / scale_factor, y / scale_factor))
+    }
+
+    /// The window the cursor is over.
+    pub fn get_cursor_window(&self) -> Option<WindowId> {
+        self.cursor_position.map(|_| self.cursor_window)
+    }
+
+    /// Moves the cursor, in physical pixels of `window`, e.g. to simulate input.
+    pub fn set_cursor_position(&mut self, window: WindowId, position: Option<(f32, f32)>) {
+        self.cursor_window = window;
+        self.cursor_position = position;
+    }
+
+    /// The window receiving keyboard input, `None` if no window is focused.
+    pub fn get_focused_window(&self) -> Option<WindowId> {
+        self.focused_window
+    }
+
+    /// Kept in sync by the window module.
+    pub fn set_focused_window(&mut self, window: Option<WindowId>) {
+        self.focused_window = window;
+    }
+
+    /// The system clipboard, or an in-memory one in headless runs.
+    pub fn get_clipboard_mut(&mut self) -> &mut Clipboard {
+        &mut self.clipboard
+    }
+
+    pub fn set_clipboard(&mut self, clipboard: Clipboard) {
+        self.clipboard = clipboard;
+    }
+
+    pub fn get_scale_factor(&self, window: WindowId) -> f64 {
+        self.scale_factors.get(&window).copied().unwrap_or(1.0)
+    }
+
+    /// Sets a window's scale factor. Kept in sync by the window module.
+    pub fn set_scale_factor(&mut self, window: WindowId, scale_factor: f64) {
+        self.scale_factors.insert(window, scale_factor);
+    }
+}
+
+impl Default for Input {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 /// [`PKeyCode`] is a wrapper class to remove the specific
@@ -165,6 +335,19 @@ pub enum PKeyCode {
     X,
     Y,
     Z,
+    // Function keys
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
     //
     Up,
     Down,
@@ -183,3 +366,11 @@ pub enum PKeyCode {
     RAlt,
 }
 
+
+/// [`PMouseButton`] wraps the mouse buttons for the same reason as [`PKeyCode`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum PMouseButton {
+    Left,
+    Right,
+    Middle,
+}
@@ -0,0 +1,91 @@
+// -------------------------------------------------------------------------
+//              - Blend Mode -
+// -------------------------------------------------------------------------
+
+/// How a sprite's colors are combined with what's already drawn.
+///
+/// Every mode but `Opaque` blends alpha the same way as `Alpha`, so
+/// partly transparent sprites stay partly transparent in render targets.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub enum BlendMode {
+    /// Regular transparency, for textures with straight alpha.
+    #[default]
+    Alpha,
+    /// Transparency for textures whose colors are multiplied by their alpha.
+    PremultipliedAlpha,
+    /// Adds the sprite's colors, e.g. for light and fire.
+    Additive,
+    /// Multiplies by the sprite's colors, e.g. for shadows and tinting.
+    /// Transparent parts leave what's drawn as is.
+    Multiply,
+    /// The inverse of multiplying the inverted colors, brightening without
+    /// blowing out like `Additive`. Transparent parts leave what's drawn as is.
+    Screen,
+    /// Subtracts the sprite's colors.
+    Subtract,
+    /// Replaces what's drawn, ignoring alpha.
+    Opaque,
+}
+
+impl BlendMode {
+    /// Whether the mode blends colors premultiplied by their alpha, which
+    /// the built-in shaders output through their `PREMULTIPLY` variant.
+    /// Material shaders drawn with these modes must premultiply themselves.
+    pub fn premultiplies(self) -> bool {
+        matches!(self, BlendMode::Multiply | BlendMode::Screen)
+    }
+
+    pub(crate) fn color_blend(self) -> wgpu::BlendState {
+        let (src_factor, dst_factor, operation) = match self {
+            BlendMode::Alpha => (
+                wgpu::BlendFactor::SrcAlpha,
+                wgpu::BlendFactor::OneMinusSrcAlpha,
+                wgpu::BlendOperation::Add,
+            ),
+            BlendMode::PremultipliedAlpha => (
+                wgpu::BlendFactor::One,
+                wgpu::BlendFactor::OneMinusSrcAlpha,
+                wgpu::BlendOperation::Add,
+            ),
+            BlendMode::Additive => (
+                wgpu::BlendFactor::SrcAlpha,
+                wgpu::BlendFactor::One,
+                wgpu::BlendOperation::Add,
+            ),
+            // src * dst + dst * (1 - src alpha), src being premultiplied
+            BlendMode::Multiply => (
+                wgpu::BlendFactor::DstColor,
+                wgpu::BlendFactor::OneMinusSrcAlpha,
+                wgpu::BlendOperation::Add,
+            ),
+            // src * (1 - dst) + dst, src being premultiplied
+            BlendMode::Screen => (
+                wgpu::BlendFactor::OneMinusDstColor,
+                wgpu::BlendFactor::One,
+                wgpu::BlendOperation::Add,
+            ),
+            BlendMode::Subtract => (
+                wgpu::BlendFactor::SrcAlpha,
+                wgpu::BlendFactor::One,
+                wgpu::BlendOperation::ReverseSubtract,
+            ),
+            BlendMode::Opaque => return wgpu::BlendState::REPLACE,
+        };
+        wgpu::BlendState {
+            src_factor,
+            dst_factor,
+            operation,
+        }
+    }
+
+    pub(crate) fn alpha_blend(self) -> wgpu::BlendState {
+        match self {
+            BlendMode::Opaque => wgpu::BlendState::REPLACE,
+            _ => wgpu::BlendState {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                operation: wgpu::BlendOperation::Add,
+            },
+        }
+    }
+}
@@ -39,6 +39,77 @@ impl Camera {
     }
 }
 
+// -------------------------------------------------------------------------
+//              - Camera 2D -
+// -------------------------------------------------------------------------
+
+/// Which pixels the camera's viewport is measured in on HiDPI displays.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum ViewportScaling {
+    /// The viewport follows logical pixels, so the game looks the same
+    /// size on every monitor.
+    #[default]
+    Logical,
+    /// The viewport follows physical pixels, so one world unit is always
+    /// one pixel on screen.
+    Physical,
+}
+
+/// An orthographic camera where one world unit maps to one pixel at a
+/// zoom of `1.0`. The y axis points up.
+pub struct Camera2D {
+    pub position: cgmath::Vector2<f32>,
+    pub zoom: f32,
+    pub viewport_width: f32,
+    pub viewport_height: f32,
+    pub znear: f32,
+    pub zfar: f32,
+}
+
+impl Camera2D {
+    pub fn new(viewport_width: f32, viewport_height: f32) -> Self {
+        Self {
+            position: cgmath::Vector2::new(0.0, 0.0),
+            zoom: 1.0,
+            viewport_width,
+            viewport_height,
+            znear: -1000.0,
+            zfar: 1000.0,
+        }
+    }
+
+    pub fn build_view_projection_matrix(&self) -> cgmath::Matrix4<f32> {
+        let half_width = self.viewport_width / (2.0 * self.zoom);
+        let half_height = self.viewport_height / (2.0 * self.zoom);
+        let projection = cgmath::ortho(
+            self.position.x - half_width,
+            self.position.x + half_width,
+            self.position.y - half_height,
+            self.position.y + half_height,
+            self.znear,
+            self.zfar,
+        );
+        OPENGL_TO_WGPU_MATRIX * projection
+    }
+
+    /// Converts a point in viewport units, with the origin in the top left
+    /// corner and y pointing down, to world space.
+    pub fn screen_to_world(&self, x: f32, y: f32) -> cgmath::Vector2<f32> {
+        cgmath::Vector2::new(
+            self.position.x + (x - self.viewport_width / 2.0) / self.zoom,
+            self.position.y - (y - self.viewport_height / 2.0) / self.zoom,
+        )
+    }
+
+    /// Converts a point in world space to viewport units.
+    pub fn world_to_screen(&self, point: cgmath::Vector2<f32>) -> (f32, f32) {
+        (
+            (point.x - self.position.x) * self.zoom + self.viewport_width / 2.0,
+            (self.position.y - point.y) * self.zoom + self.viewport_height / 2.0,
+        )
+    }
+}
+
 pub struct CameraController {
     speed: f32,
     is_up_pressed: bool,
@@ -0,0 +1,316 @@
+use std::{
+    collections::VecDeque,
+    future::Future,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use futures::FutureExt;
+
+type MapFuture = Pin<Box<dyn Future<Output = Result<(), wgpu::BufferAsyncError>> + Send>>;
+
+// -------------------------------------------------------------------------------
+//                      - Frame Readback -
+// -------------------------------------------------------------------------------
+
+/// A frame copied into a buffer, waiting for the GPU before it can be read.
+pub struct FrameReadback {
+    buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    padded_bytes_per_row: u32,
+    // Swap chain frames are bgra and need their channels swapped.
+    bgra: bool,
+    // Behind a mutex since the future isn't Sync, which modules have to be.
+    mapping: Option<Mutex<MapFuture>>,
+}
+
+impl FrameReadback {
+    /// Records a copy of `texture` into a new buffer.
+    /// [`FrameReadback::map`] has to be called once the encoder was submitted.
+    pub fn new(
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        texture: &wgpu::Texture,
+        size: winit::dpi::PhysicalSize<u32>,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let width = size.width;
+        let height = size.height;
+        let unpadded_bytes_per_row = 4 * width;
+        // Rows in the copy buffer have to be aligned
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padding = (align - unpadded_bytes_per_row % align) % align;
+        let padded_bytes_per_row = unpadded_bytes_per_row + padding;
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Readback Buffer"),
+            size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsage::COPY_DST | wgpu::BufferUsage::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        encoder.copy_texture_to_buffer(
+            wgpu::TextureCopyView {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            wgpu::BufferCopyView {
+                buffer: &buffer,
+                layout: wgpu::TextureDataLayout {
+                    offset: 0,
+                    bytes_per_row: padded_bytes_per_row,
+                    rows_per_image: height,
+                },
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth: 1,
+            },
+        );
+
+        let bgra = matches!(
+            format,
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+        );
+
+        Self {
+            buffer,
+            width,
+            height,
+            padded_bytes_per_row,
+            bgra,
+            mapping: None,
+        }
+    }
+
+    /// Starts mapping the buffer. Call after the copy was submitted.
+    pub fn map(&mut self) {
+        let mapping = self.buffer.slice(..).map_async(wgpu::MapMode::Read);
+        self.mapping = Some(Mutex::new(Box::pin(mapping)));
+    }
+
+    /// Returns the frame once the GPU finished copying it, without blocking.
+    /// The device has to be polled for this to make progress.
+    pub fn try_read(&mut self) -> Option<anyhow::Result<image::RgbaImage>> {
+        let mapping = self.mapping.as_mut()?.get_mut().unwrap();
+        let result = mapping.now_or_never()?;
+        self.mapping = None;
+        Some(result.map_err(anyhow::Error::from).and_then(|_| self.read_mapped()))
+    }
+
+    /// Blocks until the GPU finished copying the frame.
+    pub fn read(mut self, device: &wgpu::Device) -> anyhow::Result<image::RgbaImage> {
+        if self.mapping.is_none() {
+            self.map();
+        }
+        device.poll(wgpu::Maintain::Wait);
+        match self.try_read() {
+            Some(result) => result,
+            None => anyhow::bail!("[FrameReadback]: The frame wasn't copied after waiting"),
+        }
+    }
+
+    fn read_mapped(&self) -> anyhow::Result<image::RgbaImage> {
+        let unpadded_bytes_per_row = (4 * self.width) as usize;
+        let mut pixels = Vec::with_capacity(unpadded_bytes_per_row * self.height as usize);
+        {
+            let slice = self.buffer.slice(..);
+            let data = slice.get_mapped_range();
+            for row in data.chunks(self.padded_bytes_per_row as usize) {
+                pixels.extend_from_slice(&row[..unpadded_bytes_per_row]);
+            }
+        }
+        self.buffer.unmap();
+
+        if self.bgra {
+            for pixel in pixels.chunks_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+
+        image::RgbaImage::from_raw(self.width, self.height, pixels)
+            .ok_or_else(|| anyhow::anyhow!("[FrameReadback]: Readback size mismatch"))
+    }
+}
+
+// -------------------------------------------------------------------------------
+//                      - Recording -
+// -------------------------------------------------------------------------------
+
+/// How a recording is saved.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RecordingFormat {
+    /// Numbered pngs (`frame_0000.png`, ...) in a directory.
+    PngSequence,
+    /// A single looping animated gif.
+    Gif,
+}
+
+/// The memory a recording may use for its frames by default, in bytes.
+pub const DEFAULT_RECORDING_MEMORY_LIMIT: usize = 256 * 1024 * 1024;
+
+/// Keeps the last few seconds of frames in a ring buffer.
+/// The oldest frames are dropped early once the frames would use more than
+/// `max_bytes`, so large windows record a shorter duration.
+pub(crate) struct Recorder {
+    max_frames: usize,
+    max_bytes: usize,
+    bytes: usize,
+    frame_interval: Duration,
+    since_last_frame: Duration,
+    frames: VecDeque<Arc<image::RgbaImage>>,
+}
+
+impl Recorder {
+    pub fn new(duration: Duration, frames_per_second: u32, max_bytes: usize) -> Self {
+        let frames_per_second = frames_per_second.max(1);
+        let frame_interval = Duration::from_secs(1) / frames_per_second;
+        Self {
+            max_frames: ((duration.as_secs_f32() * frames_per_second as f32).ceil() as usize).max(1),
+            max_bytes,
+            bytes: 0,
+            frame_interval,
+            // Capture the first frame right away
+            since_last_frame: frame_interval,
+            frames: VecDeque::new(),
+        }
+    }
+
+    /// Advances the recording clock. Returns true if this frame should be captured.
+    pub fn tick(&mut self, delta: Duration) -> bool {
+        self.since_last_frame += delta;
+        if self.since_last_frame < self.frame_interval {
+            return false;
+        }
+        self.since_last_frame = Duration::from_secs(0);
+        true
+    }
+
+    /// Adds a frame, dropping the oldest ones to stay within the frame
+    /// count and memory limits. The newest frame is always kept.
+    pub fn push(&mut self, frame: image::RgbaImage) {
+        let size = frame.as_raw().len();
+        while self.frames.len() >= self.max_frames || (!self.frames.is_empty() && self.bytes + size > self.max_bytes) {
+            match self.frames.pop_front() {
+                Some(oldest) => self.bytes -= oldest.as_raw().len(),
+                None => break,
+            }
+        }
+        self.bytes += size;
+        self.frames.push_back(Arc::new(frame));
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// The memory used by the buffered frames, in bytes.
+    pub fn memory_usage(&self) -> usize {
+        self.bytes
+    }
+
+    pub fn frame_interval(&self) -> Duration {
+        self.frame_interval
+    }
+
+    /// The buffered frames, oldest first. The frames are shared rather
+    /// than copied.
+    pub fn frames(&self) -> Vec<Arc<image::RgbaImage>> {
+        self.frames.iter().cloned().collect()
+    }
+}
+
+/// Saves recorded frames to `path`, a directory for png sequences or a
+/// file for gifs.
+pub(crate) fn save_recording(
+    frames: Vec<Arc<image::RgbaImage>>,
+    frame_interval: Duration,
+    path: &Path,
+    format: RecordingFormat,
+) -> anyhow::Result<()> {
+    match format {
+        RecordingFormat::PngSequence => {
+            std::fs::create_dir_all(path)?;
+            for (i, frame) in frames.iter().enumerate() {
+                let frame_path = path.join(format!("frame_{:04}.png", i));
+                frame
+                    .save(&frame_path)
+                    .with_context(|| format!("Unable to save {}", frame_path.display()))?;
+            }
+        }
+        RecordingFormat::Gif => {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let file = std::fs::File::create(path)
+                .with_context(|| format!("Unable to create {}", path.display()))?;
+            let mut encoder = image::gif::GifEncoder::new(std::io::BufWriter::new(file));
+            encoder.set_repeat(image::gif::Repeat::Infinite)?;
+            let delay = image::Delay::from_saturating_duration(frame_interval);
+            encoder.encode_frames(
+                frames
+                    .into_iter()
+                    .map(|frame| image::Frame::from_parts((*frame).clone(), 0, 0, delay)),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// A path in `dir` named after the current time, e.g. `screenshot_1617000000_042.png`.
+pub(crate) fn timestamped_path(dir: &Path, prefix: &str, extension: &str) -> PathBuf {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    dir.join(format!(
+        "{}_{}_{:03}.{}",
+        prefix,
+        now.as_secs(),
+        now.subsec_millis(),
+        extension
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::renderer::capture::Recorder;
+
+    // 4 bytes per pixel
+    fn frame(width: u32) -> image::RgbaImage {
+        image::RgbaImage::new(width, 1)
+    }
+
+    #[test]
+    fn recorder_drops_the_oldest_frames() {
+        let mut recorder = Recorder::new(Duration::from_secs(1), 3, usize::MAX);
+        for width in 1..=5 {
+            recorder.push(frame(width));
+        }
+        let widths: Vec<u32> = recorder.frames().iter().map(|frame| frame.width()).collect();
+        assert_eq!(widths, vec![3, 4, 5]);
+        assert_eq!(recorder.memory_usage(), 4 * (3 + 4 + 5));
+    }
+
+    #[test]
+    fn recorder_stays_within_the_memory_limit() {
+        let mut recorder = Recorder::new(Duration::from_secs(10), 10, 40);
+        for _ in 0..20 {
+            recorder.push(frame(4));
+        }
+        assert_eq!(recorder.frame_count(), 2);
+        assert_eq!(recorder.memory_usage(), 32);
+
+        // A frame over the limit on its own replaces every other frame
+        recorder.push(frame(20));
+        assert_eq!(recorder.frame_count(), 1);
+        assert_eq!(recorder.memory_usage(), 80);
+    }
+}
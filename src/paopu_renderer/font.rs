@@ -0,0 +1,327 @@
+use std::{
+    collections::HashMap,
+    convert::TryInto,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+
+use crate::renderer::sprite::Rect;
+
+/// A reference to a font loaded by the
+/// [`Renderer`](crate::renderer::renderer::Renderer).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FontHandle(pub(crate) usize);
+
+/// A single character of a font, in pixels.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Glyph {
+    /// The glyph's area on its page texture.
+    pub region: Rect,
+    /// Where the glyph is drawn relative to the pen position, y pointing down.
+    pub offset: (f32, f32),
+    /// How far the pen moves after the glyph.
+    pub advance: f32,
+    pub page: usize,
+}
+
+// -------------------------------------------------------------------------------
+//                      - Bitmap Font -
+// -------------------------------------------------------------------------------
+
+/// An AngelCode BMFont: glyphs packed into one or more page images.
+/// The text, XML and binary variants of the descriptor are supported.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BitmapFont {
+    /// The distance between two lines, in pixels.
+    pub line_height: f32,
+    /// The distance from the top of a line to the baseline, in pixels.
+    pub base: f32,
+    /// The page image files, relative to the descriptor.
+    pub page_files: Vec<String>,
+    pub glyphs: HashMap<char, Glyph>,
+    /// Extra advance between pairs of characters.
+    pub kernings: HashMap<(char, char), f32>,
+}
+
+impl BitmapFont {
+    /// Parses a BMFont descriptor, detecting its variant.
+    pub fn parse(bytes: &[u8]) -> anyhow::Result<Self> {
+        if bytes.starts_with(b"BMF") {
+            return parse_binary(bytes);
+        }
+        let text = std::str::from_utf8(bytes).context("BMFont descriptor isn't utf-8")?;
+        let text = text.trim_start_matches('\u{feff}').trim_start();
+        if text.starts_with('<') {
+            parse_xml(text)
+        } else {
+            parse_text(text)
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)
+            .with_context(|| format!("Unable to read font {}", path.display()))?;
+        Self::parse(&bytes).with_context(|| format!("Unable to parse font {}", path.display()))
+    }
+
+    /// The paths of the page images for a descriptor loaded from `path`.
+    pub fn page_paths(&self, path: &Path) -> Vec<PathBuf> {
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        self.page_files.iter().map(|file| dir.join(file)).collect()
+    }
+
+    /// The glyph for `c`, falling back to `?` for missing characters.
+    pub fn glyph(&self, c: char) -> Option<&Glyph> {
+        self.glyphs.get(&c).or_else(|| self.glyphs.get(&'?'))
+    }
+
+    pub fn kerning(&self, first: char, second: char) -> f32 {
+        self.kernings.get(&(first, second)).copied().unwrap_or(0.0)
+    }
+
+    fn new() -> Self {
+        Self {
+            line_height: 0.0,
+            base: 0.0,
+            page_files: Vec::new(),
+            glyphs: HashMap::new(),
+            kernings: HashMap::new(),
+        }
+    }
+
+    /// Adds an element of the text or XML variant.
+    fn add_element(&mut self, tag: &str, attributes: &HashMap<String, String>) -> anyhow::Result<()> {
+        let number = |key: &str| -> anyhow::Result<f32> {
+            match attributes.get(key) {
+                Some(value) => value
+                    .parse::<f32>()
+                    .with_context(|| format!("Invalid {} `{}` in `{}`", key, value, tag)),
+                None => Ok(0.0),
+            }
+        };
+        match tag {
+            "common" => {
+                self.line_height = number("lineHeight")?;
+                self.base = number("base")?;
+            }
+            "page" => {
+                let id = number("id")? as usize;
+                let file = attributes
+                    .get("file")
+                    .ok_or_else(|| anyhow::anyhow!("Page {} has no file", id))?;
+                if self.page_files.len() <= id {
+                    self.page_files.resize(id + 1, String::new());
+                }
+                self.page_files[id] = file.clone();
+            }
+            "char" => {
+                let c = match to_char(number("id")? as u32) {
+                    Some(c) => c,
+                    None => return Ok(()),
+                };
+                self.glyphs.insert(
+                    c,
+                    Glyph {
+                        region: Rect::new(number("x")?, number("y")?, number("width")?, number("height")?),
+                        offset: (number("xoffset")?, number("yoffset")?),
+                        advance: number("xadvance")?,
+                        page: number("page")? as usize,
+                    },
+                );
+            }
+            "kerning" => {
+                let first = to_char(number("first")? as u32);
+                let second = to_char(number("second")? as u32);
+                if let (Some(first), Some(second)) = (first, second) {
+                    self.kernings.insert((first, second), number("amount")?);
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+fn to_char(id: u32) -> Option<char> {
+    std::char::from_u32(id)
+}
+
+// ---------------------------------------------------------
+//                  Text variant
+// ---------------------------------------------------------
+
+/// Lines of `tag key=value key="quoted value"`.
+fn parse_text(text: &str) -> anyhow::Result<BitmapFont> {
+    let mut font = BitmapFont::new();
+    for line in text.lines() {
+        let line = line.trim();
+        let (tag, rest) = match line.find(char::is_whitespace) {
+            Some(index) => (&line[..index], &line[index..]),
+            None => (line, ""),
+        };
+        font.add_element(tag, &parse_attributes(rest))?;
+    }
+    Ok(font)
+}
+
+/// Parses `key=value` pairs, where values may be quoted.
+fn parse_attributes(text: &str) -> HashMap<String, String> {
+    let mut attributes = HashMap::new();
+    let mut rest = text.trim_start();
+    while let Some(equals) = rest.find('=') {
+        let key = rest[..equals].trim().to_string();
+        let after = &rest[equals + 1..];
+        let (value, remaining) = if let Some(quoted) = after.strip_prefix('"') {
+            match quoted.find('"') {
+                Some(end) => (&quoted[..end], &quoted[end + 1..]),
+                None => (quoted, ""),
+            }
+        } else {
+            match after.find(char::is_whitespace) {
+                Some(end) => (&after[..end], &after[end..]),
+                None => (after, ""),
+            }
+        };
+        attributes.insert(key, value.to_string());
+        rest = remaining.trim_start();
+    }
+    attributes
+}
+
+// ---------------------------------------------------------
+//                  XML variant
+// ---------------------------------------------------------
+
+/// Reads the elements' attributes, which is all BMFont XML uses.
+fn parse_xml(text: &str) -> anyhow::Result<BitmapFont> {
+    let mut font = BitmapFont::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('<') {
+        let end = rest[start..]
+            .find('>')
+            .map(|end| start + end)
+            .ok_or_else(|| anyhow::anyhow!("Unclosed XML tag"))?;
+        let element = &rest[start + 1..end];
+        rest = &rest[end + 1..];
+
+        // Skip declarations, comments and closing tags
+        if element.starts_with('?') || element.starts_with('!') || element.starts_with('/') {
+            continue;
+        }
+        let element = element.trim_end_matches('/');
+        let (tag, attributes) = match element.find(char::is_whitespace) {
+            Some(index) => (&element[..index], &element[index..]),
+            None => (element, ""),
+        };
+        let attributes = parse_attributes(attributes)
+            .into_iter()
+            .map(|(key, value)| (key, unescape_xml(&value)))
+            .collect();
+        font.add_element(tag, &attributes)?;
+    }
+    Ok(font)
+}
+
+fn unescape_xml(value: &str) -> String {
+    value
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+// ---------------------------------------------------------
+//                  Binary variant
+// ---------------------------------------------------------
+
+/// Reads version 3 of the binary format: blocks of a type byte and a
+/// little endian size.
+fn parse_binary(bytes: &[u8]) -> anyhow::Result<BitmapFont> {
+    if bytes.len() < 4 || bytes[3] != 3 {
+        anyhow::bail!("Only version 3 of the binary BMFont format is supported");
+    }
+    let mut font = BitmapFont::new();
+    let mut rest = &bytes[4..];
+    while !rest.is_empty() {
+        if rest.len() < 5 {
+            anyhow::bail!("Truncated block header");
+        }
+        let block_type = rest[0];
+        let size = u32::from_le_bytes(rest[1..5].try_into().unwrap()) as usize;
+        if rest.len() < 5 + size {
+            anyhow::bail!("Truncated block {}", block_type);
+        }
+        let block = &rest[5..5 + size];
+        rest = &rest[5 + size..];
+
+        match block_type {
+            // Common
+            2 => {
+                if block.len() < 4 {
+                    anyhow::bail!("Truncated common block");
+                }
+                font.line_height = read_u16(block, 0) as f32;
+                font.base = read_u16(block, 2) as f32;
+            }
+            // Pages, null terminated file names
+            3 => {
+                font.page_files = block
+                    .split(|b| *b == 0)
+                    .filter(|name| !name.is_empty())
+                    .map(|name| String::from_utf8_lossy(name).into_owned())
+                    .collect();
+            }
+            // Chars, 20 bytes each
+            4 => {
+                for char_info in block.chunks_exact(20) {
+                    let c = match to_char(read_u32(char_info, 0)) {
+                        Some(c) => c,
+                        None => continue,
+                    };
+                    font.glyphs.insert(
+                        c,
+                        Glyph {
+                            region: Rect::new(
+                                read_u16(char_info, 4) as f32,
+                                read_u16(char_info, 6) as f32,
+                                read_u16(char_info, 8) as f32,
+                                read_u16(char_info, 10) as f32,
+                            ),
+                            offset: (read_i16(char_info, 12) as f32, read_i16(char_info, 14) as f32),
+                            advance: read_i16(char_info, 16) as f32,
+                            page: char_info[18] as usize,
+                        },
+                    );
+                }
+            }
+            // Kerning pairs, 10 bytes each
+            5 => {
+                for pair in block.chunks_exact(10) {
+                    let first = to_char(read_u32(pair, 0));
+                    let second = to_char(read_u32(pair, 4));
+                    if let (Some(first), Some(second)) = (first, second) {
+                        font.kernings.insert((first, second), read_i16(pair, 8) as f32);
+                    }
+                }
+            }
+            // Info isn't needed for drawing
+            _ => {}
+        }
+    }
+    Ok(font)
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn read_i16(bytes: &[u8], offset: usize) -> i16 {
+    i16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
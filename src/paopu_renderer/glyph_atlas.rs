@@ -0,0 +1,393 @@
+use std::collections::HashMap;
+
+use crate::renderer::{
+    font::FontHandle,
+    sprite::Rect,
+    texture::TextureHandle,
+    vector_font::VectorFont,
+};
+
+/// The width and height of a glyph atlas texture, in pixels.
+pub const GLYPH_ATLAS_SIZE: u32 = 1024;
+
+/// The size signed distance field glyphs are rasterized at, in pixels.
+pub const SDF_GLYPH_SIZE: f32 = 32.0;
+
+// How far the distance field reaches outside a glyph's outline, in pixels.
+const SDF_SPREAD: i32 = 4;
+
+// Empty pixels between glyphs, so they don't bleed into each other.
+const PADDING: u32 = 1;
+
+/// A glyph of a font at a size.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct GlyphKey {
+    pub font: FontHandle,
+    pub glyph: u16,
+    /// The size in pixels, or 0 for signed distance field glyphs.
+    pub size: u32,
+}
+
+/// A glyph's coverage, rasterized but not yet packed into an atlas.
+pub struct GlyphBitmap {
+    pub width: u32,
+    pub height: u32,
+    pub alpha: Vec<u8>,
+    /// The top left corner relative to the pen on the baseline.
+    pub offset: (f32, f32),
+}
+
+/// Where a glyph sits in an atlas.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CachedGlyph {
+    pub region: Rect,
+    /// The top left corner relative to the pen on the baseline, in pixels
+    /// at the size the glyph was rasterized at.
+    pub offset: (f32, f32),
+}
+
+struct Entry {
+    glyph: CachedGlyph,
+    shelf: usize,
+    x: u32,
+    last_used: u64,
+}
+
+/// A span of a shelf, either holding a glyph or free.
+struct Slot {
+    x: u32,
+    width: u32,
+    used: bool,
+}
+
+/// A row of the atlas. Slots are kept sorted and cover the shelf up to
+/// `end`; the rest of the row is free.
+struct Shelf {
+    y: u32,
+    height: u32,
+    slots: Vec<Slot>,
+    end: u32,
+}
+
+// -------------------------------------------------------------------------------
+//                      - Glyph Atlas -
+// -------------------------------------------------------------------------------
+
+/// Packs glyphs into a single texture as they are drawn. When the atlas is
+/// full, the least recently used glyphs that weren't drawn this frame are
+/// evicted to make room.
+pub struct GlyphAtlas {
+    image: image::RgbaImage,
+    shelves: Vec<Shelf>,
+    entries: HashMap<GlyphKey, Entry>,
+    frame: u64,
+    // The area changed since the texture was last written, as x0, y0, x1, y1.
+    dirty: Option<(u32, u32, u32, u32)>,
+    texture: Option<TextureHandle>,
+}
+
+impl Default for GlyphAtlas {
+    fn default() -> Self {
+        Self::new(GLYPH_ATLAS_SIZE)
+    }
+}
+
+impl GlyphAtlas {
+    pub fn new(size: u32) -> Self {
+        Self {
+            image: image::RgbaImage::from_pixel(size, size, image::Rgba([255, 255, 255, 0])),
+            shelves: Vec::new(),
+            entries: HashMap::new(),
+            frame: 0,
+            dirty: None,
+            texture: None,
+        }
+    }
+
+    /// Returns the cached glyph, rasterizing and packing it if needed.
+    /// Returns `None` for empty glyphs and glyphs that don't fit.
+    pub fn get_or_insert(
+        &mut self,
+        key: GlyphKey,
+        rasterize: impl FnOnce() -> Option<GlyphBitmap>,
+    ) -> Option<CachedGlyph> {
+        if let Some(entry) = self.entries.get_mut(&key) {
+            entry.last_used = self.frame;
+            return Some(entry.glyph);
+        }
+        let bitmap = rasterize()?;
+        let (shelf, x) = match self.allocate(bitmap.width + PADDING, bitmap.height + PADDING) {
+            Some(position) => position,
+            None => {
+                eprintln!("[GlyphAtlas]: No room for glyph {:?}", key);
+                return None;
+            }
+        };
+        let y = self.shelves[shelf].y;
+        self.write(x, y, &bitmap);
+
+        let glyph = CachedGlyph {
+            region: Rect::new(x as f32, y as f32, bitmap.width as f32, bitmap.height as f32),
+            offset: bitmap.offset,
+        };
+        self.entries.insert(
+            key,
+            Entry {
+                glyph,
+                shelf,
+                x,
+                last_used: self.frame,
+            },
+        );
+        Some(glyph)
+    }
+
+    pub fn contains(&self, key: &GlyphKey) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Glyphs used after this are kept over the ones used before.
+    pub fn next_frame(&mut self) {
+        self.frame += 1;
+    }
+
+    pub fn get_image(&self) -> &image::RgbaImage {
+        &self.image
+    }
+
+    pub fn get_texture(&self) -> Option<TextureHandle> {
+        self.texture
+    }
+
+    pub fn set_texture(&mut self, texture: TextureHandle) {
+        self.texture = Some(texture);
+    }
+
+    /// The area changed since the last call, with its top left corner.
+    pub fn take_dirty(&mut self) -> Option<(u32, u32, image::RgbaImage)> {
+        let (x0, y0, x1, y1) = self.dirty.take()?;
+        let area = image::imageops::crop_imm(&self.image, x0, y0, x1 - x0, y1 - y0).to_image();
+        Some((x0, y0, area))
+    }
+
+    /// Finds a free area, evicting glyphs until one is found.
+    fn allocate(&mut self, width: u32, height: u32) -> Option<(usize, u32)> {
+        let size = self.image.width();
+        if width > size || height > size {
+            return None;
+        }
+        loop {
+            if let Some(position) = self.find_space(width, height) {
+                return Some(position);
+            }
+            if !self.evict_least_recently_used() {
+                return None;
+            }
+        }
+    }
+
+    fn find_space(&mut self, width: u32, height: u32) -> Option<(usize, u32)> {
+        let size = self.image.width();
+        // Shelves much taller than the glyph waste space, unless empty
+        let fits = |shelf: &Shelf| {
+            shelf.height >= height && (shelf.slots.is_empty() || shelf.height <= height + height / 4 + 2)
+        };
+
+        // A free slot left by an evicted glyph
+        for (index, shelf) in self.shelves.iter_mut().enumerate() {
+            if !fits(shelf) {
+                continue;
+            }
+            if let Some(slot) = shelf.slots.iter().position(|slot| !slot.used && slot.width >= width) {
+                let x = shelf.slots[slot].x;
+                let remaining = shelf.slots[slot].width - width;
+                shelf.slots[slot].width = width;
+                shelf.slots[slot].used = true;
+                if remaining > 0 {
+                    shelf.slots.insert(
+                        slot + 1,
+                        Slot {
+                            x: x + width,
+                            width: remaining,
+                            used: false,
+                        },
+                    );
+                }
+                return Some((index, x));
+            }
+        }
+
+        // The end of a shelf
+        for (index, shelf) in self.shelves.iter_mut().enumerate() {
+            if fits(shelf) && size - shelf.end >= width {
+                let x = shelf.end;
+                shelf.slots.push(Slot { x, width, used: true });
+                shelf.end += width;
+                return Some((index, x));
+            }
+        }
+
+        // A new shelf
+        let y = self.shelves.last().map(|shelf| shelf.y + shelf.height).unwrap_or(0);
+        if size - y >= height {
+            self.shelves.push(Shelf {
+                y,
+                height,
+                slots: vec![Slot {
+                    x: 0,
+                    width,
+                    used: true,
+                }],
+                end: width,
+            });
+            return Some((self.shelves.len() - 1, 0));
+        }
+        None
+    }
+
+    /// Frees the least recently used glyph not used this frame, as glyphs
+    /// already drawn this frame can't move. Returns false if there is none.
+    fn evict_least_recently_used(&mut self) -> bool {
+        let frame = self.frame;
+        let key = match self
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.last_used < frame)
+            .min_by_key(|(_, entry)| entry.last_used)
+        {
+            Some((key, _)) => *key,
+            None => return false,
+        };
+        let entry = self.entries.remove(&key).unwrap();
+        self.free(entry.shelf, entry.x);
+        true
+    }
+
+    fn free(&mut self, shelf_index: usize, x: u32) {
+        let shelf = &mut self.shelves[shelf_index];
+        let mut slot = match shelf.slots.iter().position(|slot| slot.x == x) {
+            Some(slot) => slot,
+            None => return,
+        };
+        shelf.slots[slot].used = false;
+
+        // Merge with the free neighbours
+        if slot + 1 < shelf.slots.len() && !shelf.slots[slot + 1].used {
+            shelf.slots[slot].width += shelf.slots.remove(slot + 1).width;
+        }
+        if slot > 0 && !shelf.slots[slot - 1].used {
+            let width = shelf.slots.remove(slot).width;
+            slot -= 1;
+            shelf.slots[slot].width += width;
+        }
+        // A free slot at the end gives its space back to the shelf
+        if slot + 1 == shelf.slots.len() {
+            shelf.end = shelf.slots.pop().unwrap().x;
+        }
+        // Empty shelves at the bottom give their rows back
+        while self.shelves.last().map(|shelf| shelf.slots.is_empty()).unwrap_or(false) {
+            self.shelves.pop();
+        }
+    }
+
+    /// Writes the glyph and clears its padding.
+    fn write(&mut self, x: u32, y: u32, bitmap: &GlyphBitmap) {
+        let size = self.image.width();
+        let x1 = (x + bitmap.width + PADDING).min(size);
+        let y1 = (y + bitmap.height + PADDING).min(size);
+        for py in y..y1 {
+            for px in x..x1 {
+                let (gx, gy) = (px - x, py - y);
+                let alpha = if gx < bitmap.width && gy < bitmap.height {
+                    bitmap.alpha[(gy * bitmap.width + gx) as usize]
+                } else {
+                    0
+                };
+                self.image.put_pixel(px, py, image::Rgba([255, 255, 255, alpha]));
+            }
+        }
+        self.dirty = Some(match self.dirty {
+            Some((dx0, dy0, dx1, dy1)) => (dx0.min(x), dy0.min(y), dx1.max(x1), dy1.max(y1)),
+            None => (x, y, x1, y1),
+        });
+    }
+}
+
+// ---------------------------------------------------------
+//                  Rasterization
+// ---------------------------------------------------------
+
+/// Rasterizes a glyph's coverage at `size` pixels.
+pub fn rasterize_glyph(font: &VectorFont, glyph: rusttype::GlyphId, size: f32) -> Option<GlyphBitmap> {
+    let positioned = font
+        .font()
+        .glyph(glyph)
+        .scaled(rusttype::Scale::uniform(size))
+        .positioned(rusttype::point(0.0, 0.0));
+    let bounds = positioned.pixel_bounding_box()?;
+    let (width, height) = (bounds.width() as u32, bounds.height() as u32);
+    let mut alpha = vec![0; (width * height) as usize];
+    positioned.draw(|x, y, coverage| {
+        alpha[(y * width + x) as usize] = (coverage * 255.0).round() as u8;
+    });
+    Some(GlyphBitmap {
+        width,
+        height,
+        alpha,
+        offset: (bounds.min.x as f32, bounds.min.y as f32),
+    })
+}
+
+/// Rasterizes a glyph as a signed distance field at [`SDF_GLYPH_SIZE`].
+/// Alpha is 0.5 on the outline, growing inside and shrinking outside.
+pub fn rasterize_sdf_glyph(font: &VectorFont, glyph: rusttype::GlyphId) -> Option<GlyphBitmap> {
+    let coverage = rasterize_glyph(font, glyph, SDF_GLYPH_SIZE)?;
+    let spread = SDF_SPREAD;
+    let width = coverage.width as i32 + spread * 2;
+    let height = coverage.height as i32 + spread * 2;
+    let inside = |x: i32, y: i32| -> bool {
+        let (gx, gy) = (x - spread, y - spread);
+        gx >= 0
+            && gy >= 0
+            && gx < coverage.width as i32
+            && gy < coverage.height as i32
+            && coverage.alpha[(gy * coverage.width as i32 + gx) as usize] >= 128
+    };
+
+    let mut alpha = Vec::with_capacity((width * height) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let is_inside = inside(x, y);
+            // The distance to the closest pixel on the other side of the outline
+            let mut closest = spread as f32;
+            for dy in -spread..=spread {
+                for dx in -spread..=spread {
+                    if inside(x + dx, y + dy) != is_inside {
+                        let distance = ((dx * dx + dy * dy) as f32).sqrt() - 0.5;
+                        closest = closest.min(distance);
+                    }
+                }
+            }
+            let signed = if is_inside { closest } else { -closest };
+            let value = 0.5 + signed / (2.0 * spread as f32);
+            alpha.push((value.clamp(0.0, 1.0) * 255.0).round() as u8);
+        }
+    }
+    Some(GlyphBitmap {
+        width: width as u32,
+        height: height as u32,
+        alpha,
+        offset: (
+            coverage.offset.0 - spread as f32,
+            coverage.offset.1 - spread as f32,
+        ),
+    })
+}
@@ -0,0 +1,290 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::Context;
+use futures::executor::block_on;
+
+use crate::{
+    core::{application::Application, headless::HeadlessApp},
+    renderer::renderer::Renderer,
+};
+
+/// Set this environment variable to overwrite the reference images with
+/// the rendered output instead of comparing against them.
+pub const UPDATE_GOLDEN_ENV: &str = "PAOPU_UPDATE_GOLDEN";
+
+// -------------------------------------------------------------------------------
+//                      - Golden Test -
+// -------------------------------------------------------------------------------
+
+/// Renders a scripted scene offscreen and compares the last frame against a
+/// checked-in reference png.
+///
+/// A missing reference fails the test. Run with [`UPDATE_GOLDEN_ENV`] set
+/// to write it from the rendered output, then review it before checking it in.
+/// On failure the actual frame and a diff image are written to the
+/// output directory.
+///
+/// # Example
+///
+/// ```ignore
+/// #[test]
+/// fn single_sprite() {
+///     GoldenTest::new("single_sprite", 64, 64)
+///         .with_frames(2)
+///         .run(|app| {
+///             let renderer = app.get_module_mut::<Renderer>().unwrap();
+///             let texture = renderer.load_texture("tests/assets/mob.png").unwrap();
+///             app.world.spawn((Transform2D::default(), Sprite::new(texture)));
+///         })
+///         .unwrap();
+/// }
+/// ```
+pub struct GoldenTest {
+    name: String,
+    width: u32,
+    height: u32,
+    frames: u64,
+    frame_delta: Duration,
+    tolerance: u8,
+    max_diff_ratio: f32,
+    reference_dir: PathBuf,
+    output_dir: PathBuf,
+}
+
+impl GoldenTest {
+    pub fn new(name: &str, width: u32, height: u32) -> Self {
+        Self {
+            name: name.to_string(),
+            width,
+            height,
+            frames: 1,
+            frame_delta: Duration::from_secs(1) / 60,
+            tolerance: 2,
+            max_diff_ratio: 0.0,
+            reference_dir: PathBuf::from("tests/golden"),
+            output_dir: PathBuf::from("target/golden"),
+        }
+    }
+
+    /// Sets the number of frames to run before the frame is captured.
+    pub fn with_frames(mut self, frames: u64) -> Self {
+        self.frames = frames.max(1);
+        self
+    }
+
+    /// Sets the simulated time between two frames.
+    pub fn with_frame_delta(mut self, frame_delta: Duration) -> Self {
+        self.frame_delta = frame_delta;
+        self
+    }
+
+    /// Sets how much a single channel of a pixel may differ from the
+    /// reference before the pixel counts as different.
+    pub fn with_tolerance(mut self, tolerance: u8) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Sets the ratio of pixels, between 0 and 1, that may differ before
+    /// the test fails.
+    pub fn with_max_diff_ratio(mut self, max_diff_ratio: f32) -> Self {
+        self.max_diff_ratio = max_diff_ratio;
+        self
+    }
+
+    /// Sets the directory the reference pngs are read from.
+    pub fn with_reference_dir<P: AsRef<Path>>(mut self, reference_dir: P) -> Self {
+        self.reference_dir = reference_dir.as_ref().to_path_buf();
+        self
+    }
+
+    /// Sets the directory the actual and diff images are written to on failure.
+    pub fn with_output_dir<P: AsRef<Path>>(mut self, output_dir: P) -> Self {
+        self.output_dir = output_dir.as_ref().to_path_buf();
+        self
+    }
+
+    pub fn reference_path(&self) -> PathBuf {
+        self.reference_dir.join(format!("{}.png", self.name))
+    }
+
+    /// Renders the scene and captures the last frame.
+    /// `setup` is called once the offscreen renderer exists, to add modules
+    /// and spawn the scene.
+    pub fn render<F>(&self, setup: F) -> anyhow::Result<image::RgbaImage>
+    where
+        F: FnOnce(&mut Application),
+    {
+        let mut app = Application::default();
+        app.add_module(Renderer::default());
+        app.create_offscreen_state(self.width, self.height)?;
+        setup(&mut app);
+
+        let mut runner = HeadlessApp::new(app).with_frame_delta(self.frame_delta);
+        runner.run_frames(self.frames);
+
+        runner
+            .app()
+            .get_module::<Renderer>()
+            .unwrap()
+            .read_frame()
+    }
+
+    /// Renders the scene and compares it against the reference image.
+    pub fn run<F>(&self, setup: F) -> anyhow::Result<()>
+    where
+        F: FnOnce(&mut Application),
+    {
+        let reference_path = self.reference_path();
+        let update = std::env::var_os(UPDATE_GOLDEN_ENV).is_some();
+        if !update && !reference_path.exists() {
+            anyhow::bail!(
+                "[GoldenTest]: missing reference {}. Run with {} set to write it",
+                reference_path.display(),
+                UPDATE_GOLDEN_ENV
+            );
+        }
+
+        let actual = self.render(setup)?;
+        if update {
+            std::fs::create_dir_all(&self.reference_dir)?;
+            actual
+                .save(&reference_path)
+                .with_context(|| format!("Unable to write {}", reference_path.display()))?;
+            println!("[GoldenTest]: Wrote reference {}", reference_path.display());
+            return Ok(());
+        }
+
+        let expected = image::open(&reference_path)
+            .with_context(|| format!("Unable to load {}", reference_path.display()))?
+            .to_rgba8();
+        let comparison = compare_images(&expected, &actual, self.tolerance);
+        if comparison.is_match(self.max_diff_ratio) {
+            return Ok(());
+        }
+
+        std::fs::create_dir_all(&self.output_dir)?;
+        let actual_path = self.output_dir.join(format!("{}.actual.png", self.name));
+        let diff_path = self.output_dir.join(format!("{}.diff.png", self.name));
+        actual.save(&actual_path)?;
+        if let Some(diff) = &comparison.diff {
+            diff.save(&diff_path)?;
+        }
+
+        anyhow::bail!(
+            "[GoldenTest]: {} differs from {}: {}. See {} and {}",
+            self.name,
+            reference_path.display(),
+            comparison,
+            actual_path.display(),
+            diff_path.display()
+        )
+    }
+}
+
+/// Whether an adapter for offscreen rendering exists, so golden tests can
+/// report a machine without any instead of failing to render.
+pub fn is_adapter_available() -> bool {
+    let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+    let adapter = block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+        power_preference: wgpu::PowerPreference::LowPower,
+        compatible_surface: None,
+    }));
+    adapter.is_some() || instance.enumerate_adapters(wgpu::BackendBit::all()).next().is_some()
+}
+
+// -------------------------------------------------------------------------------
+//                      - Image Comparison -
+// -------------------------------------------------------------------------------
+
+/// The result of comparing a rendered image against a reference.
+pub struct ImageComparison {
+    pub differing_pixels: usize,
+    pub total_pixels: usize,
+    /// The largest difference of a single channel.
+    pub max_difference: u8,
+    /// Differing pixels in red over a faded copy of the reference.
+    /// `None` if the sizes don't match.
+    pub diff: Option<image::RgbaImage>,
+}
+
+impl ImageComparison {
+    pub fn diff_ratio(&self) -> f32 {
+        if self.total_pixels == 0 {
+            return 0.0;
+        }
+        self.differing_pixels as f32 / self.total_pixels as f32
+    }
+
+    /// Whether the images match, allowing up to `max_diff_ratio` of the
+    /// pixels to differ.
+    pub fn is_match(&self, max_diff_ratio: f32) -> bool {
+        self.diff.is_some() && self.diff_ratio() <= max_diff_ratio
+    }
+}
+
+impl std::fmt::Display for ImageComparison {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.diff.is_none() {
+            return write!(f, "image sizes don't match");
+        }
+        write!(
+            f,
+            "{} of {} pixels differ ({:.2}%), max channel difference {}",
+            self.differing_pixels,
+            self.total_pixels,
+            self.diff_ratio() * 100.0,
+            self.max_difference
+        )
+    }
+}
+
+/// Compares two images pixel by pixel. A pixel differs when any of its
+/// channels differs by more than `tolerance`.
+pub fn compare_images(
+    expected: &image::RgbaImage,
+    actual: &image::RgbaImage,
+    tolerance: u8,
+) -> ImageComparison {
+    let total_pixels = (actual.width() * actual.height()) as usize;
+    if expected.dimensions() != actual.dimensions() {
+        return ImageComparison {
+            differing_pixels: total_pixels,
+            total_pixels,
+            max_difference: u8::MAX,
+            diff: None,
+        };
+    }
+
+    let mut diff = image::RgbaImage::new(actual.width(), actual.height());
+    let mut differing_pixels = 0;
+    let mut max_difference = 0;
+    for ((e, a), d) in expected.pixels().zip(actual.pixels()).zip(diff.pixels_mut()) {
+        let difference = e
+            .0
+            .iter()
+            .zip(a.0.iter())
+            .map(|(e, a)| e.max(a) - e.min(a))
+            .max()
+            .unwrap_or(0);
+        max_difference = max_difference.max(difference);
+        if difference > tolerance {
+            differing_pixels += 1;
+            *d = image::Rgba([255, 0, 0, 255]);
+        } else {
+            let luma = (e.0[0] as u16 + e.0[1] as u16 + e.0[2] as u16) / 3;
+            let faded = (luma / 4) as u8;
+            *d = image::Rgba([faded, faded, faded, 255]);
+        }
+    }
+
+    ImageComparison {
+        differing_pixels,
+        total_pixels,
+        max_difference,
+        diff: Some(diff),
+    }
+}
@@ -0,0 +1,19 @@
+// sprite_fragment.glsl
+// The interface every sprite fragment shader shares.
+
+layout(location=0) in vec2 v_tex_coords;
+layout(location=1) in vec4 v_color;
+layout(location=0) out vec4 f_color;
+
+layout(set=0, binding=0) uniform texture2D t_diffuse;
+layout(set=0, binding=1) uniform sampler s_diffuse;
+
+// The color to write. The PREMULTIPLY variant, drawn with the blend modes
+// that blend premultiplied colors, multiplies it by its alpha.
+vec4 sprite_output(vec4 color){
+#ifdef PREMULTIPLY
+	return vec4(color.rgb * color.a, color.a);
+#else
+	return color;
+#endif
+}
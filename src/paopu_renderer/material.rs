@@ -0,0 +1,361 @@
+use std::path::{Path, PathBuf};
+
+use crate::renderer::{
+    blend_mode::BlendMode,
+    shader::{Shader, ShaderStage},
+    shader_reflection::{BindingType, ReflectedBinding},
+    state_descriptor::{self, StateDescriptor},
+    texture::{Texture, TextureHandle},
+};
+
+/// A reference to a material added to the
+/// [`Renderer`](crate::renderer::renderer::Renderer).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MaterialHandle(pub(crate) usize);
+
+// -------------------------------------------------------------------------------
+//                      - Material -
+// -------------------------------------------------------------------------------
+
+/// The value of a material parameter.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MaterialValue {
+    Float(f32),
+    Vec2([f32; 2]),
+    Vec4([f32; 4]),
+}
+
+impl MaterialValue {
+    /// The value padded to a `vec4`.
+    fn as_vec4(&self) -> [f32; 4] {
+        match *self {
+            MaterialValue::Float(x) => [x, 0.0, 0.0, 0.0],
+            MaterialValue::Vec2([x, y]) => [x, y, 0.0, 0.0],
+            MaterialValue::Vec4(value) => value,
+        }
+    }
+}
+
+/// Draws sprites with a custom fragment shader, and optionally a custom
+/// vertex shader, instead of the built-in ones.
+///
+/// The shaders share the built-in sprite interface: the vertex shader gets
+/// `a_position`, `a_tex_coords` and `a_color` at locations 0 to 2 and the
+/// camera's `mat4` at set 1, binding 0, and the fragment shader gets
+/// `v_tex_coords` and `v_color` at locations 0 and 1 and the sprite's
+/// texture and sampler at set 0, bindings 0 and 1.
+///
+/// The material's own resources are in set 2:
+///
+/// - binding 0 is a uniform block with a `vec4` per parameter, in the
+///   order they were declared. Floats use `x` and `vec2`s use `xy`.
+/// - bindings 1 and 2 are the first extra texture and its sampler,
+///   bindings 3 and 4 the second one and so on.
+///
+/// WGSL shaders use the same numbers as `@group` and `@binding`.
+/// `assets/shaders` has dissolve, flash and outline examples.
+///
+/// # Example
+///
+/// ```ignore
+/// let noise = renderer.load_texture("assets/noise.png")?;
+/// let dissolve = renderer.add_material(
+///     Material::new(Shader::load("assets/shaders/dissolve.frag")?)
+///         .with_param("progress", MaterialValue::Float(0.0))
+///         .with_param("edge_color", MaterialValue::Vec4([1.0, 0.5, 0.0, 1.0]))
+///         .with_texture("noise", noise),
+/// );
+/// app.world.spawn((Transform2D::default(), Sprite::new(slime).with_material(dissolve)));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Material {
+    fragment_shader: Shader,
+    vertex_shader: Option<Shader>,
+    params: Vec<(String, MaterialValue)>,
+    textures: Vec<(String, TextureHandle)>,
+    blend_mode: BlendMode,
+}
+
+impl Material {
+    pub fn new(fragment_shader: Shader) -> Self {
+        Self {
+            fragment_shader,
+            vertex_shader: None,
+            params: Vec::new(),
+            textures: Vec::new(),
+            blend_mode: BlendMode::default(),
+        }
+    }
+
+    pub fn with_vertex_shader(mut self, vertex_shader: Shader) -> Self {
+        self.vertex_shader = Some(vertex_shader);
+        self
+    }
+
+    /// Declares a parameter with its initial value.
+    pub fn with_param(mut self, name: &str, value: MaterialValue) -> Self {
+        self.params.push((name.to_string(), value));
+        self
+    }
+
+    /// Declares an extra texture.
+    pub fn with_texture(mut self, name: &str, texture: TextureHandle) -> Self {
+        self.textures.push((name.to_string(), texture));
+        self
+    }
+
+    /// The blend mode of sprites drawn with the material, unless they
+    /// set their own.
+    pub fn with_blend_mode(mut self, blend_mode: BlendMode) -> Self {
+        self.blend_mode = blend_mode;
+        self
+    }
+
+    pub fn get_blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    pub fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+        self.blend_mode = blend_mode;
+    }
+
+    pub fn get_fragment_shader(&self) -> &Shader {
+        &self.fragment_shader
+    }
+
+    pub fn get_vertex_shader(&self) -> Option<&Shader> {
+        self.vertex_shader.as_ref()
+    }
+
+    /// Reloads the shaders compiled from any of the `changed` files, e.g.
+    /// when hot reloading. Returns true if any were.
+    pub(crate) fn reload_shaders(&mut self, changed: &[PathBuf]) -> anyhow::Result<bool> {
+        let mut reloaded = false;
+        for shader in std::iter::once(&mut self.fragment_shader).chain(self.vertex_shader.as_mut()) {
+            if shader.get_sources().iter().any(|source| changed.contains(source)) {
+                *shader = shader.reload()?;
+                reloaded = true;
+            }
+        }
+        Ok(reloaded)
+    }
+
+    /// The files the shaders were compiled from.
+    pub(crate) fn shader_sources(&self) -> impl Iterator<Item = &Path> {
+        std::iter::once(&self.fragment_shader)
+            .chain(self.vertex_shader.as_ref())
+            .flat_map(|shader| shader.get_sources().iter().map(|source| source.as_path()))
+    }
+
+    /// The bindings the shaders can use: the sprite's and the material's own.
+    fn bindings(&self) -> Vec<ReflectedBinding> {
+        let mut bindings = state_descriptor::sprite_bindings();
+        bindings.push(ReflectedBinding {
+            group: 2,
+            binding: 0,
+            ty: BindingType::UniformBuffer,
+        });
+        for index in 0..self.textures.len() as u32 {
+            bindings.push(ReflectedBinding {
+                group: 2,
+                binding: 1 + index * 2,
+                ty: BindingType::Texture,
+            });
+            bindings.push(ReflectedBinding {
+                group: 2,
+                binding: 2 + index * 2,
+                ty: BindingType::Sampler,
+            });
+        }
+        bindings
+    }
+
+    pub fn get_param(&self, name: &str) -> Option<MaterialValue> {
+        self.params
+            .iter()
+            .find(|(param, _)| param == name)
+            .map(|(_, value)| *value)
+    }
+
+    /// Sets a declared parameter.
+    pub fn set_param(&mut self, name: &str, value: MaterialValue) {
+        match self.params.iter_mut().find(|(param, _)| param == name) {
+            Some((_, current)) => *current = value,
+            None => eprintln!("[Material]: No parameter named {}", name),
+        }
+    }
+
+    pub fn get_texture(&self, name: &str) -> Option<TextureHandle> {
+        self.textures
+            .iter()
+            .find(|(texture, _)| texture == name)
+            .map(|(_, handle)| *handle)
+    }
+
+    /// Replaces a declared texture.
+    pub fn set_texture(&mut self, name: &str, texture: TextureHandle) {
+        match self.textures.iter_mut().find(|(current, _)| current == name) {
+            Some((_, current)) => *current = texture,
+            None => eprintln!("[Material]: No texture named {}", name),
+        }
+    }
+
+    pub fn get_textures(&self) -> &[(String, TextureHandle)] {
+        &self.textures
+    }
+
+    /// The parameters' uniform block. Never empty, as uniform buffers
+    /// can't be.
+    fn uniform_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = self
+            .params
+            .iter()
+            .flat_map(|(_, value)| value.as_vec4().to_vec())
+            .flat_map(|float| float.to_le_bytes().to_vec())
+            .collect();
+        if bytes.is_empty() {
+            bytes.resize(16, 0);
+        }
+        bytes
+    }
+}
+
+// ---------------------------------------------------------
+//                  GPU
+// ---------------------------------------------------------
+
+/// A material's shader modules and resources on the GPU.
+pub(crate) struct MaterialGpu {
+    pub pipeline_layout: wgpu::PipelineLayout,
+    // The built-in vertex shader is used if there is none.
+    pub vertex: Option<(wgpu::ShaderModule, String)>,
+    pub fragment: (wgpu::ShaderModule, String),
+    bind_group_layout: wgpu::BindGroupLayout,
+    uniform_buffer: wgpu::Buffer,
+    // Built once every texture is on the GPU.
+    pub bind_group: Option<wgpu::BindGroup>,
+}
+
+impl MaterialGpu {
+    /// Fails if a shader is missing the entry point of its stage, or uses
+    /// bindings or vertex inputs the material doesn't have.
+    pub fn new(state_desc: &StateDescriptor, material: &Material) -> anyhow::Result<Self> {
+        let device = &state_desc.device;
+        let bindings = material.bindings();
+        for shader in std::iter::once(&material.fragment_shader).chain(material.vertex_shader.as_ref()) {
+            state_descriptor::validate_layout(shader, &bindings)?;
+        }
+        let vertex_entry_point = match &material.vertex_shader {
+            Some(shader) => Some(entry_point(shader, ShaderStage::Vertex)?),
+            None => None,
+        };
+        let fragment_entry_point = entry_point(&material.fragment_shader, ShaderStage::Fragment)?;
+
+        let mut entries = vec![wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStage::VERTEX | wgpu::ShaderStage::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }];
+        for index in 0..material.textures.len() as u32 {
+            entries.push(wgpu::BindGroupLayoutEntry {
+                binding: 1 + index * 2,
+                visibility: wgpu::ShaderStage::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                },
+                count: None,
+            });
+            entries.push(wgpu::BindGroupLayoutEntry {
+                binding: 2 + index * 2,
+                visibility: wgpu::ShaderStage::FRAGMENT,
+                ty: wgpu::BindingType::Sampler {
+                    comparison: false,
+                    filtering: true,
+                },
+                count: None,
+            });
+        }
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &entries,
+            label: Some("Material Bind Group Layout"),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Material Pipeline Layout"),
+            bind_group_layouts: &[
+                state_desc.texture_bind_group_layout(),
+                state_desc.uniform_bind_group_layout(),
+                &bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Material Uniform Buffer"),
+            size: material.uniform_bytes().len() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Ok(Self {
+            pipeline_layout,
+            vertex: material
+                .vertex_shader
+                .as_ref()
+                .zip(vertex_entry_point)
+                .map(|(shader, entry_point)| (shader.create_module(device), entry_point)),
+            fragment: (material.fragment_shader.create_module(device), fragment_entry_point),
+            bind_group_layout,
+            uniform_buffer,
+            bind_group: None,
+        })
+    }
+
+    /// Writes the parameters and binds the textures, `textures` being the
+    /// GPU textures of the material's textures if they were uploaded.
+    pub fn update(&mut self, state_desc: &StateDescriptor, material: &Material, textures: &[Option<&Texture>]) {
+        state_desc
+            .queue
+            .write_buffer(&self.uniform_buffer, 0, &material.uniform_bytes());
+
+        let textures: Vec<&Texture> = match textures.iter().copied().collect::<Option<Vec<_>>>() {
+            Some(textures) => textures,
+            None => {
+                self.bind_group = None;
+                return;
+            }
+        };
+        let mut entries = vec![wgpu::BindGroupEntry {
+            binding: 0,
+            resource: self.uniform_buffer.as_entire_binding(),
+        }];
+        for (index, texture) in textures.iter().enumerate() {
+            let index = index as u32;
+            entries.push(wgpu::BindGroupEntry {
+                binding: 1 + index * 2,
+                resource: wgpu::BindingResource::TextureView(&texture.view),
+            });
+            entries.push(wgpu::BindGroupEntry {
+                binding: 2 + index * 2,
+                resource: wgpu::BindingResource::Sampler(&texture.sampler),
+            });
+        }
+        self.bind_group = Some(state_desc.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.bind_group_layout,
+            entries: &entries,
+            label: Some("Material Bind Group"),
+        }));
+    }
+}
+
+fn entry_point(shader: &Shader, stage: ShaderStage) -> anyhow::Result<String> {
+    shader
+        .get_entry_point(stage)
+        .map(|name| name.to_string())
+        .ok_or_else(|| anyhow::anyhow!("[Material]: {} has no {:?} entry point", shader.get_label(), stage))
+}
@@ -1,6 +1,25 @@
+pub mod blend_mode;
 pub mod camera;
+pub mod capture;
+pub mod font;
+pub mod glyph_atlas;
+pub mod golden;
+pub mod material;
+pub mod present_mode;
+pub mod render_target;
 pub mod renderer;
+pub mod rich_text;
+pub mod shader;
+pub mod shader_compiler;
+pub mod shader_preprocessor;
+pub mod shader_reflection;
+pub mod shader_watcher;
+pub mod software_cursor;
+pub mod sprite;
+pub mod sprite_batch;
 pub mod state_descriptor;
+pub mod text;
 pub mod texture;
+pub mod vector_font;
 mod uniforms;
 mod vertex_buffer_descriptor;
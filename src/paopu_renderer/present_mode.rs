@@ -0,0 +1,29 @@
+// -------------------------------------------------------------------------
+//              - Present Mode -
+// -------------------------------------------------------------------------
+
+/// [`PresentMode`] is a wrapper around [wgpu::PresentMode] so that
+/// applications don't need to depend on [wgpu] directly.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum PresentMode {
+    /// Waits for the vertical blank. Frames are capped to the display
+    /// refresh rate and no tearing is visible.
+    #[default]
+    Fifo,
+    /// Waits for the vertical blank, but newer frames replace queued ones
+    /// instead of blocking. Falls back to `Fifo` if unsupported.
+    Mailbox,
+    /// Presents immediately. Lowest latency, but tearing may be visible.
+    /// Falls back to `Fifo` if unsupported.
+    Immediate,
+}
+
+impl From<PresentMode> for wgpu::PresentMode {
+    fn from(mode: PresentMode) -> Self {
+        match mode {
+            PresentMode::Fifo => wgpu::PresentMode::Fifo,
+            PresentMode::Mailbox => wgpu::PresentMode::Mailbox,
+            PresentMode::Immediate => wgpu::PresentMode::Immediate,
+        }
+    }
+}
@@ -0,0 +1,87 @@
+// -------------------------------------------------------------------------
+//              - Render Target -
+// -------------------------------------------------------------------------
+
+/// A reference to a render target created by the
+/// [`Renderer`](crate::renderer::renderer::Renderer).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RenderTargetHandle(pub(crate) usize);
+
+/// The pixel format of a [`RenderTarget`], so applications don't need to
+/// depend on [wgpu] directly.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum RenderTargetFormat {
+    /// 8 bits per channel in sRGB, like loaded textures.
+    #[default]
+    Rgba8Srgb,
+    /// 8 bits per channel, stored as is, e.g. for data read by materials.
+    Rgba8Unorm,
+    /// 16 bit floats per channel, for values outside of 0 to 1.
+    Rgba16Float,
+}
+
+impl From<RenderTargetFormat> for wgpu::TextureFormat {
+    fn from(format: RenderTargetFormat) -> Self {
+        match format {
+            RenderTargetFormat::Rgba8Srgb => wgpu::TextureFormat::Rgba8UnormSrgb,
+            RenderTargetFormat::Rgba8Unorm => wgpu::TextureFormat::Rgba8Unorm,
+            RenderTargetFormat::Rgba16Float => wgpu::TextureFormat::Rgba16Float,
+        }
+    }
+}
+
+/// A texture that sprites are drawn into through its own camera, then
+/// drawn with like any other texture, e.g. for minimaps, security camera
+/// screens, portals and reflections.
+///
+/// Targets are drawn before the windows each frame, and before the other
+/// targets that sample them, so their texture is up to date wherever it's
+/// used. Targets that sample each other in a cycle see the last frame's
+/// contents, and sprites sampling the target they're drawn into are
+/// skipped. Targets nothing was drawn into keep their last contents.
+///
+/// # Example
+///
+/// ```ignore
+/// let minimap = renderer.create_render_target(RenderTarget::new(128, 128));
+/// if let Some(camera) = renderer.render_target_camera_mut(minimap) {
+///     camera.zoom = 0.25;
+/// }
+/// let texture = renderer.get_render_target_texture(minimap).unwrap();
+/// app.world.spawn((Transform2D::default(), Sprite::new(texture).with_layer(RenderLayer::UI)));
+/// app.world.spawn((Transform2D::default(), Sprite::new(tiles), TargetTexture(minimap)));
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RenderTarget {
+    pub width: u32,
+    pub height: u32,
+    pub format: RenderTargetFormat,
+    /// The color the target is cleared to before drawing into it.
+    pub clear_color: [f32; 4],
+}
+
+impl RenderTarget {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width: width.max(1),
+            height: height.max(1),
+            format: RenderTargetFormat::default(),
+            clear_color: [0.0, 0.0, 0.0, 0.0],
+        }
+    }
+
+    pub fn with_format(mut self, format: RenderTargetFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn with_clear_color(mut self, clear_color: [f32; 4]) -> Self {
+        self.clear_color = clear_color;
+        self
+    }
+}
+
+/// Draws the entity's sprite or text into a render target instead of a
+/// window.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TargetTexture(pub RenderTargetHandle);
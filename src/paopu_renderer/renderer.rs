@@ -1,18 +1,167 @@
-use std::any::Any;
+use std::{
+    any::Any,
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
-use winit::event::*;
+use anyhow::Context;
 use futures::executor::block_on;
+use image::GenericImageView;
 use crate::{
     core::application::Application, 
-    core::module::Module, renderer::state_descriptor::StateDescriptor,
+    core::events::{EventReader, ScaleFactorChanged, WindowId, WindowMinimized, WindowResized},
+    core::entity::Entity,
+    core::module::Module,
+    core::transform::{GlobalTransform2D, Transform2D},
+    core::world::World,
+    input::input::{PKeyCode, INPUT},
+    renderer::{
+        camera::{Camera2D, ViewportScaling},
+        capture::{self, FrameReadback, Recorder, RecordingFormat},
+        font::{BitmapFont, FontHandle},
+        glyph_atlas::{rasterize_glyph, rasterize_sdf_glyph, GlyphAtlas, GlyphKey, SDF_GLYPH_SIZE},
+        material::{Material, MaterialGpu, MaterialHandle, MaterialValue},
+        present_mode::PresentMode,
+        render_target::{RenderTarget, RenderTargetHandle, TargetTexture},
+        rich_text::{CharacterRevealed, IconLayout, IconMetrics, IconSet, RichGlyph, RichText},
+        shader_watcher::ShaderWatcher,
+        software_cursor::SoftwareCursor,
+        sprite::{RenderLayer, Sprite, SpriteAnimation, TargetWindow},
+        sprite_batch::{Batch, SpriteBatch},
+        state_descriptor::{BuiltinShader, PipelineKey, PipelineShader, StateDescriptor, SurfaceState},
+        text::{place_chars, FontMetrics, Text},
+        texture::{Texture, TextureHandle},
+        vector_font::{VectorFont, VectorMetrics},
+    },
 };
 
+struct TextureEntry {
+    label: String,
+    size: (u32, u32),
+    // Kept around until there is a device to upload it to.
+    pending: Option<image::DynamicImage>,
+    gpu: Option<(Texture, wgpu::BindGroup)>,
+    // Holds a signed distance field, drawn with the SDF pipeline.
+    sdf: bool,
+}
+
+struct MaterialEntry {
+    material: Material,
+    // Created once there is a device.
+    gpu: Option<MaterialGpu>,
+    // Set when the parameters or textures changed since the last upload.
+    dirty: bool,
+}
+
+enum FontEntry {
+    Bitmap {
+        font: BitmapFont,
+        // The texture of every page, by page id.
+        pages: Vec<TextureHandle>,
+    },
+    Vector {
+        font: VectorFont,
+        // Fonts searched in order for characters the font is missing.
+        fallbacks: Vec<FontHandle>,
+    },
+}
+
+/// A glyph placed relative to a text's origin.
+struct GlyphQuad {
+    // The index of the character, newlines excluded.
+    index: usize,
+    sprite: Sprite,
+    // The top left corner, in world units.
+    position: (f32, f32),
+    scale: f32,
+}
+
+impl GlyphQuad {
+    fn local_matrix(&self) -> cgmath::Matrix4<f32> {
+        cgmath::Matrix4::from_translation(cgmath::Vector3::new(self.position.0, self.position.1, 0.0))
+            * cgmath::Matrix4::from_scale(self.scale)
+    }
+}
+
+/// Where an icon of a rich text goes.
+struct IconPlacement {
+    icon: usize,
+    // The index of the character, newlines excluded.
+    index: usize,
+    // The top left corner of the icon's line, in world units.
+    position: (f32, f32),
+    line_height: f32,
+}
+
+/// The glyphs and icons of a text.
+#[derive(Default)]
+struct TextQuads {
+    glyphs: Vec<GlyphQuad>,
+    icons: Vec<IconPlacement>,
+    // How far apart the two copies of a bold glyph are, in world units.
+    bold_offset: f32,
+}
+
+struct RenderTargetEntry {
+    target: RenderTarget,
+    // The texture sprites draw the target with.
+    texture: TextureHandle,
+}
+
+/// Where submitted sprites are drawn into.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+enum Destination {
+    Window(WindowId),
+    Target(RenderTargetHandle),
+}
+
+/// What a captured frame is used for.
+enum CaptureTarget {
+    Screenshot(PathBuf),
+    Recording,
+}
+
 // -------------------------------------------------------------------------
 //              - Renderer -
 // -------------------------------------------------------------------------
 
 pub struct Renderer {
     state_descriptor: Option<StateDescriptor>,
+    present_mode: PresentMode,
+    // Overrides `present_mode` while the frame rate requires another mode.
+    forced_present_mode: Option<PresentMode>,
+    resize_reader: EventReader<WindowResized>,
+    scale_factor_reader: EventReader<ScaleFactorChanged>,
+    minimized_reader: EventReader<WindowMinimized>,
+    viewport_scaling: ViewportScaling,
+    scale_factors: HashMap<WindowId, f64>,
+    // Minimized windows are skipped when rendering.
+    minimized: HashSet<WindowId>,
+    textures: Vec<TextureEntry>,
+    materials: Vec<MaterialEntry>,
+    // Watches shader sources when hot reloading is enabled.
+    shader_watcher: Option<ShaderWatcher>,
+    fonts: Vec<FontEntry>,
+    glyph_atlas: GlyphAtlas,
+    sdf_glyph_atlas: GlyphAtlas,
+    icon_set: IconSet,
+    render_targets: Vec<RenderTargetEntry>,
+    sprite_batches: HashMap<Destination, SpriteBatch>,
+    // Layers whose sprites are drawn from the top of the world down.
+    y_sorted_layers: Vec<RenderLayer>,
+    // Set when the sprite batches hold submissions that haven't been drawn yet.
+    frame_pending: bool,
+    software_cursor: Option<SoftwareCursor>,
+    screenshot_key: Option<PKeyCode>,
+    screenshot_dir: PathBuf,
+    screenshot_requests: Vec<PathBuf>,
+    recorder: Option<Recorder>,
+    recording_memory_limit: usize,
+    // Set when the recorder wants the next frame.
+    record_frame: bool,
+    // Frames being copied back from the GPU, oldest first.
+    pending_captures: Vec<(FrameReadback, Vec<CaptureTarget>)>,
 }
 
 
@@ -26,10 +175,76 @@ impl Module for Renderer {
     }
 
     // Needs a window to build properly
-    fn build(&self, _app: &mut Application) {
-        // TODO: After creating get module in ApplicationBuilder
-        //let window_handle = &app.get_module::<Window>().unwrap().window;
-        //self.setup(window_handle);
+    fn build(&self, app: &mut Application) {
+        app.add_event::<WindowResized>()
+            .add_event::<ScaleFactorChanged>()
+            .add_event::<WindowMinimized>()
+            .add_event::<CharacterRevealed>();
+    }
+
+    fn update(&mut self, app: &mut Application) {
+        if let Some(events) = app.get_events::<ScaleFactorChanged>() {
+            for event in self.scale_factor_reader.iter(events) {
+                self.set_scale_factor(event.window, event.scale_factor);
+            }
+        }
+        if let Some(events) = app.get_events::<WindowResized>() {
+            // Only the latest size of each window matters
+            let mut sizes = HashMap::new();
+            for event in self.resize_reader.iter(events) {
+                sizes.insert(event.window, (event.width, event.height));
+            }
+            for (window, (width, height)) in sizes {
+                self.resize_window(window, width, height);
+            }
+        }
+        if let Some(events) = app.get_events::<WindowMinimized>() {
+            for event in self.minimized_reader.iter(events) {
+                if event.minimized {
+                    self.minimized.insert(event.window);
+                } else {
+                    self.minimized.remove(&event.window);
+                }
+            }
+        }
+
+        let delta = app.get_time().delta();
+        let changed_shaders = match self.shader_watcher.as_mut() {
+            Some(watcher) => watcher.poll(delta),
+            None => Vec::new(),
+        };
+        self.reload_shaders(&changed_shaders);
+
+        for (sprite, animation) in app.world.query::<(&mut Sprite, &mut SpriteAnimation)>() {
+            animation.advance(delta);
+            if let Some(frame) = animation.current_frame() {
+                sprite.region = Some(frame);
+            }
+        }
+        let mut revealed = Vec::new();
+        for (entity, rich_text) in app.world.query::<(Entity, &mut RichText)>() {
+            for (index, character) in rich_text.advance(delta) {
+                revealed.push(CharacterRevealed {
+                    entity,
+                    index,
+                    character,
+                });
+            }
+        }
+        for event in revealed {
+            app.send_event(event);
+        }
+
+        if let Some(recorder) = self.recorder.as_mut() {
+            self.record_frame = recorder.tick(delta);
+        }
+        self.finish_captures();
+
+        // The last frame was never drawn (e.g. every window is minimized)
+        if self.frame_pending {
+            self.clear_sprite_batches();
+        }
+        self.frame_pending = true;
     }
 }
 
@@ -37,96 +252,1729 @@ impl Default for Renderer {
     fn default() -> Self {
         Renderer {
             state_descriptor: None,
+            present_mode: PresentMode::default(),
+            forced_present_mode: None,
+            resize_reader: EventReader::default(),
+            scale_factor_reader: EventReader::default(),
+            minimized_reader: EventReader::default(),
+            viewport_scaling: ViewportScaling::default(),
+            scale_factors: HashMap::new(),
+            minimized: HashSet::new(),
+            textures: Vec::new(),
+            materials: Vec::new(),
+            shader_watcher: None,
+            fonts: Vec::new(),
+            glyph_atlas: GlyphAtlas::default(),
+            sdf_glyph_atlas: GlyphAtlas::default(),
+            icon_set: IconSet::default(),
+            render_targets: Vec::new(),
+            sprite_batches: HashMap::new(),
+            y_sorted_layers: Vec::new(),
+            frame_pending: false,
+            software_cursor: None,
+            screenshot_key: None,
+            screenshot_dir: PathBuf::from("screenshots"),
+            screenshot_requests: Vec::new(),
+            recorder: None,
+            recording_memory_limit: capture::DEFAULT_RECORDING_MEMORY_LIMIT,
+            record_frame: false,
+            pending_captures: Vec::new(),
         }
     }
 }
 
 
 impl Renderer {
+    /// Sets the present mode the swap chain will be created with.
+    pub fn with_present_mode(mut self, present_mode: PresentMode) -> Self {
+        self.present_mode = present_mode;
+        self
+    }
+
+    pub fn get_present_mode(&self) -> PresentMode {
+        self.present_mode
+    }
+
+    /// Changes the present mode at runtime.
+    /// Existing swap chains are recreated. While the window's frame rate
+    /// forces another mode, it is applied once the frame rate stops forcing it.
+    pub fn set_present_mode(&mut self, present_mode: PresentMode) {
+        let effective = self.get_effective_present_mode();
+        self.present_mode = present_mode;
+        self.apply_present_mode(effective);
+    }
+
+    /// The present mode the swap chains use: the one the frame rate forces,
+    /// if any, or the one set on the renderer.
+    pub fn get_effective_present_mode(&self) -> PresentMode {
+        self.forced_present_mode.unwrap_or(self.present_mode)
+    }
+
+    /// Overrides the present mode set on the renderer, or stops overriding
+    /// it if `None`. Used by the window to keep it in sync with the frame rate.
+    pub(crate) fn set_forced_present_mode(&mut self, present_mode: Option<PresentMode>) {
+        let effective = self.get_effective_present_mode();
+        self.forced_present_mode = present_mode;
+        self.apply_present_mode(effective);
+    }
+
+    /// Recreates the swap chains if the effective present mode changed from `previous`.
+    fn apply_present_mode(&mut self, previous: PresentMode) {
+        let effective = self.get_effective_present_mode();
+        if effective == previous {
+            return;
+        }
+        if let Some(sd) = self.state_descriptor.as_mut() {
+            sd.set_present_mode(effective);
+        }
+    }
+
     pub fn set_state(&mut self, state_descriptor: StateDescriptor) {
         self.state_descriptor = Some(state_descriptor);
     }
 
+    /// Creates the GPU state along with the primary window's swap chain.
     pub fn create_state(&mut self, window: &winit::window::Window) {
-        self.state_descriptor = Some(block_on(StateDescriptor::new(window)));
+        self.state_descriptor = Some(block_on(StateDescriptor::new(window, self.get_effective_present_mode())));
+        self.set_scale_factor(WindowId::PRIMARY, window.scale_factor());
+        self.upload_pending_textures();
+    }
+
+    /// Creates a swap chain for another window. It shares the device,
+    /// pipeline and textures with the primary window but has its own camera.
+    pub fn create_window_state(&mut self, id: WindowId, window: &winit::window::Window) {
+        let present_mode = self.get_effective_present_mode();
+        match self.state_descriptor.as_mut() {
+            Some(sd) => sd.add_window(id, window, present_mode),
+            None => {
+                eprintln!("[Renderer]: Can't add {:?} before the primary window", id);
+                return;
+            }
+        }
+        self.set_scale_factor(id, window.scale_factor());
+    }
+
+    /// Drops a closed window's swap chain.
+    pub fn remove_window_state(&mut self, id: WindowId) {
+        if let Some(sd) = self.state_descriptor.as_mut() {
+            sd.remove_window(id);
+        }
+        self.scale_factors.remove(&id);
+        self.minimized.remove(&id);
+        self.sprite_batches.remove(&Destination::Window(id));
+    }
+
+    /// The windows that are rendered to, in order of their IDs.
+    pub fn window_ids(&self) -> Vec<WindowId> {
+        match self.state_descriptor.as_ref() {
+            Some(sd) => sd.window_ids().collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Sets whether the camera's viewport follows logical or physical pixels.
+    /// Defaults to logical pixels.
+    pub fn with_viewport_scaling(mut self, viewport_scaling: ViewportScaling) -> Self {
+        self.viewport_scaling = viewport_scaling;
+        self
+    }
+
+    pub fn get_viewport_scaling(&self) -> ViewportScaling {
+        self.viewport_scaling
+    }
+
+    pub fn set_viewport_scaling(&mut self, viewport_scaling: ViewportScaling) {
+        self.viewport_scaling = viewport_scaling;
+        for window in self.window_ids() {
+            self.apply_viewport_scale(window);
+        }
+    }
+
+    /// The scale factor of a window being rendered to.
+    pub fn get_scale_factor(&self, window: WindowId) -> f64 {
+        self.scale_factors.get(&window).copied().unwrap_or(1.0)
+    }
+
+    /// Sets a window's scale factor. Kept in sync through the
+    /// [`ScaleFactorChanged`] events sent by the window module.
+    pub fn set_scale_factor(&mut self, window: WindowId, scale_factor: f64) {
+        self.scale_factors.insert(window, scale_factor);
+        self.apply_viewport_scale(window);
+    }
+
+    fn apply_viewport_scale(&mut self, window: WindowId) {
+        let viewport_scale = match self.viewport_scaling {
+            ViewportScaling::Logical => self.get_scale_factor(window) as f32,
+            ViewportScaling::Physical => 1.0,
+        };
+        if let Some(surface) = self
+            .state_descriptor
+            .as_mut()
+            .and_then(|sd| sd.surface_mut(window))
+        {
+            surface.set_viewport_scale(viewport_scale);
+        }
+    }
+
+    /// Converts a position in physical pixels of `window`, e.g. the
+    /// cursor's, to world space as seen by that window's camera.
+    pub fn screen_to_world(&self, window: WindowId, x: f32, y: f32) -> Option<cgmath::Vector2<f32>> {
+        let surface = self.state_descriptor.as_ref()?.surface(window)?;
+        let viewport_scale = surface.get_viewport_scale();
+        Some(surface.camera().screen_to_world(x / viewport_scale, y / viewport_scale))
+    }
+
+    /// Creates a state that renders into an offscreen texture of the given
+    /// size instead of a window. Frames can then be read back with
+    /// [`Renderer::read_frame`].
+    pub fn create_offscreen_state(&mut self, width: u32, height: u32) -> anyhow::Result<()> {
+        self.state_descriptor = Some(block_on(StateDescriptor::new_offscreen(width, height))?);
+        self.upload_pending_textures();
+        Ok(())
+    }
+
+    /// Copies the last rendered frame back to the CPU.
+    /// Only offscreen states can be read back.
+    pub fn read_frame(&self) -> anyhow::Result<image::RgbaImage> {
+        match self.state_descriptor.as_ref() {
+            Some(sd) => sd.read_frame(WindowId::PRIMARY),
+            None => anyhow::bail!("[Renderer]: No state to read a frame from"),
+        }
+    }
+
+    /// Reads the last rendered frame back and saves it, e.g. as a png.
+    pub fn save_frame<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        self.read_frame()?
+            .save(path)
+            .with_context(|| format!("Unable to save frame to {}", path.display()))
+    }
+
+    /// The primary window's camera.
+    pub fn camera(&self) -> Option<&Camera2D> {
+        self.window_camera(WindowId::PRIMARY)
+    }
+
+    pub fn camera_mut(&mut self) -> Option<&mut Camera2D> {
+        self.window_camera_mut(WindowId::PRIMARY)
+    }
+
+    pub fn window_camera(&self, window: WindowId) -> Option<&Camera2D> {
+        self.state_descriptor
+            .as_ref()
+            .and_then(|sd| sd.surface(window))
+            .map(|surface| surface.camera())
+    }
+
+    pub fn window_camera_mut(&mut self, window: WindowId) -> Option<&mut Camera2D> {
+        self.state_descriptor
+            .as_mut()
+            .and_then(|sd| sd.surface_mut(window))
+            .map(|surface| surface.camera_mut())
+    }
+
+    // ---------------------------------------------------------
+    //                  Textures
+    // ---------------------------------------------------------
+
+    /// Loads an image file as a texture.
+    /// The texture is uploaded once the renderer's state is created.
+    pub fn load_texture<P: AsRef<Path>>(&mut self, path: P) -> anyhow::Result<TextureHandle> {
+        let path = path.as_ref();
+        let img = image::open(path)
+            .with_context(|| format!("Unable to load texture {}", path.display()))?;
+        Ok(self.add_texture(img, &path.to_string_lossy()))
+    }
+
+    /// Loads an encoded image (e.g. a png) as a texture.
+    pub fn load_texture_from_bytes(&mut self, bytes: &[u8], label: &str) -> anyhow::Result<TextureHandle> {
+        let img = image::load_from_memory(bytes)?;
+        Ok(self.add_texture(img, label))
+    }
+
+    /// Adds a decoded image as a texture.
+    pub fn add_texture(&mut self, img: image::DynamicImage, label: &str) -> TextureHandle {
+        self.add_texture_entry(img, label, false)
+    }
+
+    fn add_texture_entry(&mut self, img: image::DynamicImage, label: &str, sdf: bool) -> TextureHandle {
+        let img = image::DynamicImage::ImageRgba8(img.to_rgba8());
+        let handle = TextureHandle(self.textures.len());
+        self.textures.push(TextureEntry {
+            label: label.to_string(),
+            size: (img.width(), img.height()),
+            pending: Some(img),
+            gpu: None,
+            sdf,
+        });
+        self.upload_pending_textures();
+        handle
+    }
+
+    /// The size of the texture in pixels.
+    pub fn texture_size(&self, texture: TextureHandle) -> Option<(u32, u32)> {
+        self.textures.get(texture.0).map(|entry| entry.size)
+    }
+
+    fn upload_pending_textures(&mut self) {
+        let sd = match self.state_descriptor.as_mut() {
+            Some(sd) => sd,
+            None => return,
+        };
+        for entry in self.textures.iter_mut() {
+            if let Some(img) = entry.pending.take() {
+                // Distance fields are interpolated between pixels
+                let filter = if entry.sdf {
+                    wgpu::FilterMode::Linear
+                } else {
+                    wgpu::FilterMode::Nearest
+                };
+                match Texture::from_image_with_filter(&sd.device, &sd.queue, &img, Some(&entry.label), filter) {
+                    Ok(texture) => {
+                        let bind_group = sd.create_texture_bind_group(&texture);
+                        entry.gpu = Some((texture, bind_group));
+                    }
+                    Err(e) => eprintln!("[Renderer]: Unable to upload {}: {:?}", entry.label, e),
+                }
+            }
+        }
+        for (index, entry) in self.render_targets.iter().enumerate() {
+            let texture_entry = &mut self.textures[entry.texture.0];
+            if texture_entry.gpu.is_some() {
+                continue;
+            }
+            let target = &entry.target;
+            let format = target.format.into();
+            let texture = Texture::new_render_target(
+                &sd.device,
+                target.width,
+                target.height,
+                format,
+                Some(&texture_entry.label),
+            );
+            let [r, g, b, a] = target.clear_color;
+            sd.add_render_target(
+                RenderTargetHandle(index),
+                &texture,
+                format,
+                winit::dpi::PhysicalSize::new(target.width, target.height),
+                wgpu::Color {
+                    r: r as f64,
+                    g: g as f64,
+                    b: b as f64,
+                    a: a as f64,
+                },
+            );
+            let bind_group = sd.create_texture_bind_group(&texture);
+            texture_entry.gpu = Some((texture, bind_group));
+        }
+    }
+
+    // ---------------------------------------------------------
+    //                  Render Targets
+    // ---------------------------------------------------------
+
+    /// Creates a texture sprites can be drawn into through its own camera,
+    /// with [`TargetTexture`] or [`Renderer::draw_sprite_to_target`].
+    /// The texture is created once the renderer's state is.
+    pub fn create_render_target(&mut self, target: RenderTarget) -> RenderTargetHandle {
+        let handle = RenderTargetHandle(self.render_targets.len());
+        let texture = TextureHandle(self.textures.len());
+        self.textures.push(TextureEntry {
+            label: format!("Render Target {}", handle.0),
+            size: (target.width, target.height),
+            pending: None,
+            gpu: None,
+            sdf: false,
+        });
+        self.render_targets.push(RenderTargetEntry { target, texture });
+        self.upload_pending_textures();
+        handle
+    }
+
+    pub fn get_render_target(&self, target: RenderTargetHandle) -> Option<&RenderTarget> {
+        self.render_targets.get(target.0).map(|entry| &entry.target)
+    }
+
+    /// The texture holding what was drawn into the target, to draw sprites
+    /// or bind materials with.
+    pub fn get_render_target_texture(&self, target: RenderTargetHandle) -> Option<TextureHandle> {
+        self.render_targets.get(target.0).map(|entry| entry.texture)
+    }
+
+    pub fn render_target_camera(&self, target: RenderTargetHandle) -> Option<&Camera2D> {
+        self.state_descriptor
+            .as_ref()
+            .and_then(|sd| sd.target(target))
+            .map(|surface| surface.camera())
+    }
+
+    pub fn render_target_camera_mut(&mut self, target: RenderTargetHandle) -> Option<&mut Camera2D> {
+        self.state_descriptor
+            .as_mut()
+            .and_then(|sd| sd.target_mut(target))
+            .map(|surface| surface.camera_mut())
+    }
+
+    /// The render target drawn into `texture`, if it is one.
+    fn texture_render_target(&self, texture: TextureHandle) -> Option<RenderTargetHandle> {
+        self.render_targets
+            .iter()
+            .position(|entry| entry.texture == texture)
+            .map(RenderTargetHandle)
+    }
+
+    /// The render targets drawn into this frame, each after the targets
+    /// its sprites sample. Targets sampling each other in a cycle are
+    /// drawn in the order the cycle is found.
+    fn render_target_order(&self) -> Vec<RenderTargetHandle> {
+        let mut visited = HashSet::new();
+        let mut order = Vec::new();
+        for index in 0..self.render_targets.len() {
+            self.visit_render_target(RenderTargetHandle(index), &mut visited, &mut order);
+        }
+        order
+    }
+
+    fn visit_render_target(
+        &self,
+        target: RenderTargetHandle,
+        visited: &mut HashSet<RenderTargetHandle>,
+        order: &mut Vec<RenderTargetHandle>,
+    ) {
+        if !visited.insert(target) {
+            return;
+        }
+        let sprite_batch = match self.sprite_batches.get(&Destination::Target(target)) {
+            Some(sprite_batch) if !sprite_batch.is_empty() => sprite_batch,
+            // Nothing to draw, so it keeps its contents
+            _ => return,
+        };
+        let mut sampled = HashSet::new();
+        for (texture, material) in sprite_batch.sources() {
+            sampled.extend(sampled_textures(texture, material, &self.materials));
+        }
+        for texture in sampled {
+            if let Some(dependency) = self.texture_render_target(texture) {
+                self.visit_render_target(dependency, visited, order);
+            }
+        }
+        order.push(target);
+    }
+
+    // ---------------------------------------------------------
+    //                  Materials
+    // ---------------------------------------------------------
+
+    /// Adds a material sprites can be drawn with through [`Sprite::with_material`].
+    pub fn add_material(&mut self, material: Material) -> MaterialHandle {
+        let handle = MaterialHandle(self.materials.len());
+        if let Some(watcher) = self.shader_watcher.as_mut() {
+            for path in material.shader_sources() {
+                watcher.watch(path);
+            }
+        }
+        self.materials.push(MaterialEntry {
+            material,
+            gpu: None,
+            dirty: true,
+        });
+        handle
+    }
+
+    pub fn get_material(&self, material: MaterialHandle) -> Option<&Material> {
+        self.materials.get(material.0).map(|entry| &entry.material)
+    }
+
+    /// The changes are uploaded before the next frame is rendered.
+    pub fn get_material_mut(&mut self, material: MaterialHandle) -> Option<&mut Material> {
+        self.materials.get_mut(material.0).map(|entry| {
+            entry.dirty = true;
+            &mut entry.material
+        })
+    }
+
+    /// Sets a parameter of a material, e.g. a dissolve's progress.
+    pub fn set_material_param(&mut self, material: MaterialHandle, name: &str, value: MaterialValue) {
+        match self.get_material_mut(material) {
+            Some(material) => material.set_param(name, value),
+            None => eprintln!("[Renderer]: No material for {:?}", material),
+        }
+    }
+
+    /// Creates the GPU state of new materials and uploads the changed ones.
+    fn update_materials(&mut self) {
+        let sd = match self.state_descriptor.as_ref() {
+            Some(sd) => sd,
+            None => return,
+        };
+        let textures = &self.textures;
+        for entry in self.materials.iter_mut().filter(|entry| entry.dirty) {
+            let material = &entry.material;
+            let mut gpu = match entry.gpu.take() {
+                Some(gpu) => gpu,
+                None => match MaterialGpu::new(sd, material) {
+                    Ok(gpu) => gpu,
+                    Err(e) => {
+                        // Sprites using it are skipped
+                        eprintln!("[Renderer]: {:#}", e);
+                        entry.dirty = false;
+                        continue;
+                    }
+                },
+            };
+            let material_textures: Vec<Option<&Texture>> = material
+                .get_textures()
+                .iter()
+                .map(|(_, texture)| {
+                    textures
+                        .get(texture.0)
+                        .and_then(|texture| texture.gpu.as_ref())
+                        .map(|(texture, _)| texture)
+                })
+                .collect();
+            gpu.update(sd, material, &material_textures);
+            // Retried until every texture is uploaded
+            entry.dirty = gpu.bind_group.is_none();
+            entry.gpu = Some(gpu);
+        }
+    }
+
+    // ---------------------------------------------------------
+    //                  Shader Hot Reload
+    // ---------------------------------------------------------
+
+    /// Recompiles shaders when their source changes, swapping them in at
+    /// the next frame. Disabled by default.
+    /// Watches the files and includes of shaders loaded from files, e.g. the
+    /// game's asset shaders. The built-in shaders' sources, includes and
+    /// manifest are watched too when they are present and writable, see
+    /// [`BuiltinShader::has_editable_sources`].
+    pub fn with_shader_hot_reload(mut self, enabled: bool) -> Self {
+        self.set_shader_hot_reload(enabled);
+        self
+    }
+
+    pub fn is_shader_hot_reload_enabled(&self) -> bool {
+        self.shader_watcher.is_some()
+    }
+
+    pub fn set_shader_hot_reload(&mut self, enabled: bool) {
+        if enabled == self.shader_watcher.is_some() {
+            return;
+        }
+        self.shader_watcher = if enabled {
+            let mut watcher = new_shader_watcher();
+            for entry in self.materials.iter() {
+                for path in entry.material.shader_sources() {
+                    watcher.watch(path);
+                }
+            }
+            Some(watcher)
+        } else {
+            None
+        };
+    }
+
+    /// Recompiles the shaders compiled from any of the `changed` files and
+    /// swaps them in. Shaders that fail to compile are logged and the old
+    /// ones kept.
+    fn reload_shaders(&mut self, changed: &[PathBuf]) {
+        if changed.is_empty() {
+            return;
+        }
+
+        let builtins_editable = BuiltinShader::has_editable_sources();
+        let manifest_changed = changed.contains(&BuiltinShader::manifest_path());
+        for builtin in BuiltinShader::ALL.iter().copied().filter(|_| builtins_editable) {
+            if !manifest_changed && !builtin.sources().iter().any(|source| changed.contains(source)) {
+                continue;
+            }
+            for &variant in builtin.variants() {
+                let result = builtin.load_variant(variant).and_then(|shader| {
+                    if let Some(watcher) = self.shader_watcher.as_mut() {
+                        shader.get_sources().iter().for_each(|source| watcher.watch(source));
+                    }
+                    match self.state_descriptor.as_mut() {
+                        Some(sd) => sd.set_builtin_shader(builtin, variant, &shader),
+                        None => Ok(()),
+                    }
+                });
+                match result {
+                    Ok(()) if variant.is_empty() => println!("[Renderer]: Reloaded {}", builtin.source_path().display()),
+                    Ok(()) => println!("[Renderer]: Reloaded {} ({})", builtin.source_path().display(), variant),
+                    Err(e) => eprintln!("[Renderer]: Keeping the old shader, {:#}", e),
+                }
+            }
+        }
+
+        for (index, entry) in self.materials.iter_mut().enumerate() {
+            let handle = MaterialHandle(index);
+            let mut material = entry.material.clone();
+            match material.reload_shaders(changed) {
+                Ok(true) => {}
+                Ok(false) => continue,
+                Err(e) => {
+                    eprintln!("[Renderer]: Keeping the old shader, {:#}", e);
+                    continue;
+                }
+            }
+            if let Some(sd) = self.state_descriptor.as_mut() {
+                match MaterialGpu::new(sd, &material) {
+                    Ok(gpu) => {
+                        entry.gpu = Some(gpu);
+                        sd.remove_material_pipelines(handle);
+                    }
+                    Err(e) => {
+                        eprintln!("[Renderer]: Keeping the old shader, {:#}", e);
+                        continue;
+                    }
+                }
+            }
+            // Includes can be added while editing
+            if let Some(watcher) = self.shader_watcher.as_mut() {
+                material.shader_sources().for_each(|source| watcher.watch(source));
+            }
+            entry.material = material;
+            entry.dirty = true;
+            println!("[Renderer]: Reloaded the shaders of {:?}", handle);
+        }
+    }
+
+    // ---------------------------------------------------------
+    //                  Fonts
+    // ---------------------------------------------------------
+
+    /// Loads an AngelCode BMFont descriptor (text, XML or binary) along
+    /// with its page images.
+    pub fn load_font<P: AsRef<Path>>(&mut self, path: P) -> anyhow::Result<FontHandle> {
+        let path = path.as_ref();
+        let font = BitmapFont::load(path)?;
+        let mut pages = Vec::new();
+        for page_path in font.page_paths(path) {
+            pages.push(self.load_texture(page_path)?);
+        }
+        Ok(self.add_font(font, pages))
+    }
+
+    /// Adds a parsed font whose pages were already loaded as textures.
+    pub fn add_font(&mut self, font: BitmapFont, pages: Vec<TextureHandle>) -> FontHandle {
+        let handle = FontHandle(self.fonts.len());
+        self.fonts.push(FontEntry::Bitmap { font, pages });
+        handle
+    }
+
+    /// Loads a TrueType or OpenType font.
+    pub fn load_vector_font<P: AsRef<Path>>(&mut self, path: P) -> anyhow::Result<FontHandle> {
+        Ok(self.add_vector_font(VectorFont::load(path)?))
+    }
+
+    pub fn add_vector_font(&mut self, font: VectorFont) -> FontHandle {
+        let handle = FontHandle(self.fonts.len());
+        self.fonts.push(FontEntry::Vector {
+            font,
+            fallbacks: Vec::new(),
+        });
+        handle
+    }
+
+    /// Sets the vector fonts searched in order for characters `font` is
+    /// missing, e.g. a font covering another script.
+    pub fn set_font_fallbacks(&mut self, font: FontHandle, fallbacks: Vec<FontHandle>) {
+        match self.fonts.get_mut(font.0) {
+            Some(FontEntry::Vector { fallbacks: current, .. }) => *current = fallbacks,
+            Some(FontEntry::Bitmap { .. }) => eprintln!("[Renderer]: Bitmap fonts can't have fallbacks"),
+            None => {}
+        }
+    }
+
+    pub fn get_font(&self, font: FontHandle) -> Option<&BitmapFont> {
+        match self.fonts.get(font.0)? {
+            FontEntry::Bitmap { font, .. } => Some(font),
+            FontEntry::Vector { .. } => None,
+        }
+    }
+
+    pub fn get_vector_font(&self, font: FontHandle) -> Option<&VectorFont> {
+        match self.fonts.get(font.0)? {
+            FontEntry::Vector { font, .. } => Some(font),
+            FontEntry::Bitmap { .. } => None,
+        }
+    }
+
+    /// The size the text covers, in world units.
+    pub fn measure_text(&self, text: &Text) -> Option<(f32, f32)> {
+        self.measure_text_with_icons(text, &IconLayout::default())
+    }
+
+    /// The size the rich text covers, in world units.
+    pub fn measure_rich_text(&self, rich_text: &RichText) -> Option<(f32, f32)> {
+        let (_, icons) = self.rich_text_icons(rich_text);
+        self.measure_text_with_icons(rich_text.get_text(), &icons)
+    }
+
+    fn measure_text_with_icons(&self, text: &Text, icons: &IconLayout) -> Option<(f32, f32)> {
+        match self.fonts.get(text.font.0)? {
+            FontEntry::Bitmap { font, .. } => {
+                let scale = if text.scale > 0.0 { text.scale } else { 1.0 };
+                let metrics = IconMetrics { font, icons };
+                let layout = place_chars(&metrics, text, text.max_width.map(|max_width| max_width / scale));
+                Some((layout.width * text.scale, layout.height * text.scale))
+            }
+            FontEntry::Vector { .. } => {
+                let metrics = vector_metrics(&self.fonts, text)?;
+                let metrics = IconMetrics {
+                    font: &metrics,
+                    icons,
+                };
+                let layout = place_chars(&metrics, text, text.max_width);
+                Some((layout.width, layout.height))
+            }
+        }
+    }
+
+    /// The sprites `[icon]` tags are drawn with.
+    pub fn with_icon_set(mut self, icon_set: IconSet) -> Self {
+        self.icon_set = icon_set;
+        self
+    }
+
+    pub fn get_icon_set(&self) -> &IconSet {
+        &self.icon_set
+    }
+
+    pub fn get_icon_set_mut(&mut self) -> &mut IconSet {
+        &mut self.icon_set
+    }
+
+    /// The sprite of every icon of the rich text, and how the icons are laid
+    /// out. Missing icons take no space.
+    fn rich_text_icons(&self, rich_text: &RichText) -> (Vec<Option<Sprite>>, IconLayout) {
+        let sprites: Vec<Option<Sprite>> = rich_text
+            .get_icons()
+            .iter()
+            .map(|name| self.icon_set.get_icon(name).cloned())
+            .collect();
+        let ratios = sprites
+            .iter()
+            .map(|sprite| {
+                sprite
+                    .as_ref()
+                    .and_then(|sprite| self.sprite_size(sprite))
+                    .map(|(width, height)| width / height)
+                    .unwrap_or(0.0)
+            })
+            .collect();
+        let icons = IconLayout {
+            chars: rich_text.get_icon_chars().to_vec(),
+            ratios,
+        };
+        (sprites, icons)
+    }
+
+    /// The size of the sprite's region in pixels, if it isn't empty.
+    fn sprite_size(&self, sprite: &Sprite) -> Option<(f32, f32)> {
+        let (width, height) = match sprite.region {
+            Some(region) => (region.width, region.height),
+            None => {
+                let (width, height) = self.texture_size(sprite.texture)?;
+                (width as f32, height as f32)
+            }
+        };
+        if width > 0.0 && height > 0.0 {
+            Some((width, height))
+        } else {
+            None
+        }
+    }
+
+    /// Creates the glyph atlas texture if it doesn't exist yet.
+    fn create_glyph_atlas_texture(&mut self, sdf: bool) {
+        let atlas = if sdf { &self.sdf_glyph_atlas } else { &self.glyph_atlas };
+        if atlas.get_texture().is_some() {
+            return;
+        }
+        let img = image::DynamicImage::ImageRgba8(atlas.get_image().clone());
+        let label = if sdf { "SDF Glyph Atlas" } else { "Glyph Atlas" };
+        let texture = self.add_texture_entry(img, label, sdf);
+        if sdf {
+            self.sdf_glyph_atlas.set_texture(texture);
+        } else {
+            self.glyph_atlas.set_texture(texture);
+        }
+    }
+
+    /// Copies the glyphs added since the last frame to the atlas textures.
+    fn flush_glyph_atlases(&mut self) {
+        let sd = self.state_descriptor.as_ref();
+        for atlas in [&mut self.glyph_atlas, &mut self.sdf_glyph_atlas].iter_mut() {
+            let texture = match atlas.get_texture() {
+                Some(texture) => texture,
+                None => continue,
+            };
+            let (x, y, area) = match atlas.take_dirty() {
+                Some(dirty) => dirty,
+                None => continue,
+            };
+            let entry = &mut self.textures[texture.0];
+            if entry.pending.is_some() {
+                entry.pending = Some(image::DynamicImage::ImageRgba8(atlas.get_image().clone()));
+            } else if let (Some(sd), Some((gpu_texture, _))) = (sd, entry.gpu.as_ref()) {
+                gpu_texture.write_region(&sd.queue, x, y, &area);
+            }
+        }
+    }
+
+    // ---------------------------------------------------------
+    //                  Capture
+    // ---------------------------------------------------------
+
+    /// Sets the key that takes a screenshot, e.g. `Some(PKeyCode::F12)`,
+    /// or `None` to disable it. Disabled by default.
+    pub fn with_screenshot_key(mut self, key_code: Option<PKeyCode>) -> Self {
+        self.screenshot_key = key_code;
+        self
+    }
+
+    pub fn get_screenshot_key(&self) -> Option<PKeyCode> {
+        self.screenshot_key
+    }
+
+    /// Sets the directory screenshots are saved to. Defaults to `screenshots`.
+    pub fn with_screenshot_dir<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.screenshot_dir = dir.as_ref().to_path_buf();
+        self
+    }
+
+    /// Captures the primary window's next frame to a timestamped png in
+    /// the screenshot directory and returns its path.
+    /// The png is written in the background once the GPU is done with the frame.
+    pub fn take_screenshot(&mut self) -> PathBuf {
+        let path = capture::timestamped_path(&self.screenshot_dir, "screenshot", "png");
+        self.take_screenshot_to(&path);
+        path
+    }
+
+    /// Captures the next frame to `path`.
+    pub fn take_screenshot_to<P: AsRef<Path>>(&mut self, path: P) {
+        self.screenshot_requests.push(path.as_ref().to_path_buf());
+    }
+
+    /// Starts keeping the primary window's last `duration` of frames, captured at
+    /// `frames_per_second`, so they can be saved with [`Renderer::save_recording`].
+    /// Fewer frames are kept if they would exceed the recording memory limit.
+    pub fn start_recording(&mut self, duration: Duration, frames_per_second: u32) {
+        self.recorder = Some(Recorder::new(duration, frames_per_second, self.recording_memory_limit));
+    }
+
+    /// Sets how much memory, in bytes, recorded frames may use.
+    /// Defaults to 256 MiB. Applies to recordings started afterwards.
+    pub fn with_recording_memory_limit(mut self, max_bytes: usize) -> Self {
+        self.recording_memory_limit = max_bytes;
+        self
+    }
+
+    pub fn get_recording_memory_limit(&self) -> usize {
+        self.recording_memory_limit
+    }
+
+    /// Stops recording and drops the recorded frames.
+    pub fn stop_recording(&mut self) {
+        self.recorder = None;
+        self.record_frame = false;
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    /// The number of frames currently held by the recording.
+    pub fn recorded_frame_count(&self) -> usize {
+        self.recorder.as_ref().map(|r| r.frame_count()).unwrap_or(0)
     }
 
+    /// The memory used by the recorded frames, in bytes.
+    pub fn recording_memory_usage(&self) -> usize {
+        self.recorder.as_ref().map(|r| r.memory_usage()).unwrap_or(0)
+    }
+
+    /// Saves the recorded frames in the background while recording continues.
+    /// `path` is a directory for png sequences and a file for gifs.
+    pub fn save_recording<P: AsRef<Path>>(&self, path: P, format: RecordingFormat) -> anyhow::Result<()> {
+        let recorder = match self.recorder.as_ref() {
+            Some(recorder) => recorder,
+            None => anyhow::bail!("[Renderer]: Not recording"),
+        };
+        let frames = recorder.frames();
+        let frame_interval = recorder.frame_interval();
+        let path = path.as_ref().to_path_buf();
+        std::thread::spawn(move || {
+            if let Err(e) = capture::save_recording(frames, frame_interval, &path, format) {
+                eprintln!("[Renderer]: Unable to save recording {}: {:?}", path.display(), e);
+            }
+        });
+        Ok(())
+    }
+
+    /// Hands the captures the GPU finished copying to their targets.
+    fn finish_captures(&mut self) {
+        let sd = match self.state_descriptor.as_ref() {
+            Some(sd) => sd,
+            None => return,
+        };
+        if self.pending_captures.is_empty() {
+            return;
+        }
+        sd.device.poll(wgpu::Maintain::Poll);
+
+        // Frames finish in order, so stop at the first one that isn't ready
+        while let Some((readback, _)) = self.pending_captures.first_mut() {
+            let result = match readback.try_read() {
+                Some(result) => result,
+                None => break,
+            };
+            let (_, targets) = self.pending_captures.remove(0);
+            let frame = match result {
+                Ok(frame) => frame,
+                Err(e) => {
+                    eprintln!("[Renderer]: Unable to capture frame: {:?}", e);
+                    continue;
+                }
+            };
+            for target in targets {
+                match target {
+                    CaptureTarget::Screenshot(path) => {
+                        let frame = frame.clone();
+                        std::thread::spawn(move || save_screenshot(frame, &path));
+                    }
+                    CaptureTarget::Recording => {
+                        if let Some(recorder) = self.recorder.as_mut() {
+                            recorder.push(frame.clone());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // ---------------------------------------------------------
+    //                  Sprites
+    // ---------------------------------------------------------
+
+    /// Submits a sprite to be drawn into the primary window this frame.
+    /// Note: The submitted sprites are cleared when the renderer updates, so
+    /// modules drawing manually should be added after the renderer.
+    pub fn draw_sprite(&mut self, transform: &Transform2D, sprite: &Sprite) {
+        self.draw_sprite_with_matrix(transform.compute_matrix(), sprite);
+    }
+
+    /// Submits a sprite transformed by `model` to be drawn into the
+    /// primary window this frame.
+    pub fn draw_sprite_with_matrix(&mut self, model: cgmath::Matrix4<f32>, sprite: &Sprite) {
+        self.draw_sprite_to_window(WindowId::PRIMARY, model, sprite);
+    }
+
+    /// Submits a sprite transformed by `model` to be drawn into `window` this frame.
+    pub fn draw_sprite_to_window(
+        &mut self,
+        window: WindowId,
+        model: cgmath::Matrix4<f32>,
+        sprite: &Sprite,
+    ) {
+        self.submit_sprite(Destination::Window(window), model, sprite, model.w.y);
+    }
+
+    /// Submits a sprite transformed by `model` to be drawn into a render
+    /// target this frame, through the target's camera.
+    pub fn draw_sprite_to_target(
+        &mut self,
+        target: RenderTargetHandle,
+        model: cgmath::Matrix4<f32>,
+        sprite: &Sprite,
+    ) {
+        self.submit_sprite(Destination::Target(target), model, sprite, model.w.y);
+    }
+
+    /// Submits a sprite sorted at `sort_y` in y-sorted layers.
+    fn submit_sprite(
+        &mut self,
+        destination: Destination,
+        model: cgmath::Matrix4<f32>,
+        sprite: &Sprite,
+        sort_y: f32,
+    ) {
+        if let Some(size) = self.texture_size(sprite.texture) {
+            let blend_mode = sprite
+                .blend_mode
+                .or_else(|| Some(self.get_material(sprite.material?)?.get_blend_mode()))
+                .unwrap_or_default();
+            self.sprite_batches
+                .entry(destination)
+                .or_default()
+                .push(model, sprite, size, blend_mode, sort_y);
+        }
+    }
+
+    /// Draws the sprites of `layer` from the top of the world down, so
+    /// sprites lower on screen are drawn in front, e.g. characters walking
+    /// past each other. Other layers draw sprites of the same z-index in
+    /// submission order, see [`Sprite`].
+    pub fn with_y_sort(mut self, layer: RenderLayer) -> Self {
+        self.set_y_sort(layer, true);
+        self
+    }
+
+    pub fn set_y_sort(&mut self, layer: RenderLayer, enabled: bool) {
+        self.y_sorted_layers.retain(|sorted| *sorted != layer);
+        if enabled {
+            self.y_sorted_layers.push(layer);
+        }
+    }
+
+    pub fn is_y_sorted(&self, layer: RenderLayer) -> bool {
+        self.y_sorted_layers.contains(&layer)
+    }
+
+    /// Submits every sprite and text in the world at its global transform,
+    /// into its [`TargetTexture`], its [`TargetWindow`] or the primary window.
+    pub fn submit_world(&mut self, world: &mut World) {
+        for (global, sprite, window, target) in world.query::<(
+            &GlobalTransform2D,
+            &Sprite,
+            Option<&TargetWindow>,
+            Option<&TargetTexture>,
+        )>() {
+            let model = global.matrix();
+            self.submit_sprite(destination(window, target), model, sprite, model.w.y);
+        }
+        for (global, text, window, target) in world.query::<(
+            &GlobalTransform2D,
+            &Text,
+            Option<&TargetWindow>,
+            Option<&TargetTexture>,
+        )>() {
+            self.submit_text(destination(window, target), global.matrix(), text);
+        }
+        for (global, rich_text, window, target) in world.query::<(
+            &GlobalTransform2D,
+            &RichText,
+            Option<&TargetWindow>,
+            Option<&TargetTexture>,
+        )>() {
+            self.submit_rich_text(destination(window, target), global.matrix(), rich_text);
+        }
+    }
+
+    /// Draws `cursor` at the mouse position on top of everything else.
+    pub fn with_software_cursor(mut self, cursor: SoftwareCursor) -> Self {
+        self.software_cursor = Some(cursor);
+        self
+    }
+
+    pub fn get_software_cursor(&self) -> Option<&SoftwareCursor> {
+        self.software_cursor.as_ref()
+    }
+
+    /// Sets the cursor drawn at the mouse position, or `None` to stop drawing it.
+    pub fn set_software_cursor(&mut self, cursor: Option<SoftwareCursor>) {
+        self.software_cursor = cursor;
+    }
+
+    /// Submits a text to be drawn into the primary window this frame.
+    pub fn draw_text(&mut self, transform: &Transform2D, text: &Text) {
+        self.draw_text_with_matrix(transform.compute_matrix(), text);
+    }
+
+    /// Submits a text transformed by `model` to be drawn into the primary
+    /// window this frame.
+    pub fn draw_text_with_matrix(&mut self, model: cgmath::Matrix4<f32>, text: &Text) {
+        self.draw_text_to_window(WindowId::PRIMARY, model, text);
+    }
+
+    /// Submits a text transformed by `model` to be drawn into `window` this
+    /// frame. Every glyph is drawn as a sprite, so text sorts with sprites.
+    pub fn draw_text_to_window(&mut self, window: WindowId, model: cgmath::Matrix4<f32>, text: &Text) {
+        self.submit_text(Destination::Window(window), model, text);
+    }
+
+    /// Submits a text transformed by `model` to be drawn into a render
+    /// target this frame.
+    pub fn draw_text_to_target(&mut self, target: RenderTargetHandle, model: cgmath::Matrix4<f32>, text: &Text) {
+        self.submit_text(Destination::Target(target), model, text);
+    }
+
+    fn submit_text(&mut self, destination: Destination, model: cgmath::Matrix4<f32>, text: &Text) {
+        for glyph in self.layout_text_quads(text, &IconLayout::default()).glyphs {
+            let glyph_model = model * glyph.local_matrix();
+            let mut sprite = glyph.sprite;
+            sprite.layer = text.layer;
+            sprite.z_index = text.z_index;
+            self.submit_sprite(destination, glyph_model, &sprite, model.w.y);
+        }
+    }
+
+    /// Submits a rich text to be drawn into the primary window this frame.
+    pub fn draw_rich_text(&mut self, transform: &Transform2D, rich_text: &RichText) {
+        self.draw_rich_text_with_matrix(transform.compute_matrix(), rich_text);
+    }
+
+    pub fn draw_rich_text_with_matrix(&mut self, model: cgmath::Matrix4<f32>, rich_text: &RichText) {
+        self.draw_rich_text_to_window(WindowId::PRIMARY, model, rich_text);
+    }
+
+    /// Submits the characters of a rich text its typewriter revealed, with
+    /// their effects, to be drawn into `window` this frame.
+    pub fn draw_rich_text_to_window(
+        &mut self,
+        window: WindowId,
+        model: cgmath::Matrix4<f32>,
+        rich_text: &RichText,
+    ) {
+        self.submit_rich_text(Destination::Window(window), model, rich_text);
+    }
+
+    /// Submits the revealed characters of a rich text to be drawn into a
+    /// render target this frame.
+    pub fn draw_rich_text_to_target(
+        &mut self,
+        target: RenderTargetHandle,
+        model: cgmath::Matrix4<f32>,
+        rich_text: &RichText,
+    ) {
+        self.submit_rich_text(Destination::Target(target), model, rich_text);
+    }
+
+    fn submit_rich_text(&mut self, destination: Destination, model: cgmath::Matrix4<f32>, rich_text: &RichText) {
+        let visible = rich_text.visible_count();
+        let elapsed = rich_text.get_elapsed();
+        let text = rich_text.get_text();
+        for glyph in self.layout_rich_text(rich_text) {
+            if glyph.index < visible {
+                let glyph_model = model * glyph.local_matrix(elapsed);
+                let mut sprite = glyph.sprite;
+                sprite.layer = text.layer;
+                sprite.z_index = text.z_index;
+                self.submit_sprite(destination, glyph_model, &sprite, model.w.y);
+            }
+        }
+    }
+
+    /// Places the glyphs and icons of a rich text with the style of their
+    /// character, ordered by character. Bold glyphs are placed twice.
+    pub fn layout_rich_text(&mut self, rich_text: &RichText) -> Vec<RichGlyph> {
+        let (icons, icon_layout) = self.rich_text_icons(rich_text);
+        let text = rich_text.get_text();
+        let quads = self.layout_text_quads(text, &icon_layout);
+        let pixel = text.scale;
+
+        let mut glyphs = Vec::new();
+        for quad in quads.glyphs {
+            let style = rich_text.get_style(quad.index).copied().unwrap_or_default();
+            let mut sprite = quad.sprite;
+            sprite.color = style.color;
+            if style.bold {
+                glyphs.push(RichGlyph {
+                    index: quad.index,
+                    sprite: sprite.clone(),
+                    position: (quad.position.0 + quads.bold_offset, quad.position.1),
+                    scale: quad.scale,
+                    style,
+                    pixel,
+                });
+            }
+            glyphs.push(RichGlyph {
+                index: quad.index,
+                sprite,
+                position: quad.position,
+                scale: quad.scale,
+                style,
+                pixel,
+            });
+        }
+        for placement in quads.icons {
+            let mut sprite = match icons[placement.icon].clone() {
+                Some(sprite) => sprite,
+                None => continue,
+            };
+            let height = match self.sprite_size(&sprite) {
+                Some((_, height)) => height,
+                None => continue,
+            };
+            // Icons fill the line, from its top left corner
+            sprite.anchor = [0.0, 1.0];
+            glyphs.push(RichGlyph {
+                index: placement.index,
+                sprite,
+                position: placement.position,
+                scale: placement.line_height / height,
+                style: rich_text.get_style(placement.index).copied().unwrap_or_default(),
+                pixel,
+            });
+        }
+        glyphs.sort_by_key(|glyph| glyph.index);
+        glyphs
+    }
+
+    /// Lays out the text and places its glyphs. Icon placeholders are as
+    /// wide as their aspect ratio in `icons` times the line height.
+    fn layout_text_quads(&mut self, text: &Text, icons: &IconLayout) -> TextQuads {
+        match self.fonts.get(text.font.0) {
+            Some(FontEntry::Bitmap { font, pages }) => bitmap_text_quads(font, pages, text, icons),
+            Some(FontEntry::Vector { .. }) => self.vector_text_quads(text, icons),
+            None => TextQuads::default(),
+        }
+    }
+
+    /// Places the glyphs of a vector font text, adding missing ones to the
+    /// glyph atlases.
+    fn vector_text_quads(&mut self, text: &Text, icons: &IconLayout) -> TextQuads {
+        // Only the atlases the text's fonts use are created
+        let (uses_atlas, uses_sdf_atlas) = match vector_metrics(&self.fonts, text) {
+            Some(metrics) => (
+                metrics.fonts.iter().any(|(_, font)| !font.is_sdf()),
+                metrics.fonts.iter().any(|(_, font)| font.is_sdf()),
+            ),
+            None => return TextQuads::default(),
+        };
+        if uses_atlas {
+            self.create_glyph_atlas_texture(false);
+        }
+        if uses_sdf_atlas {
+            self.create_glyph_atlas_texture(true);
+        }
+
+        let metrics = match vector_metrics(&self.fonts, text) {
+            Some(metrics) => metrics,
+            None => return TextQuads::default(),
+        };
+        let layout = place_chars(
+            &IconMetrics {
+                font: &metrics,
+                icons,
+            },
+            text,
+            text.max_width,
+        );
+        let ascent = metrics.fonts[0].1.ascent(metrics.size());
+        let line_height = metrics.line_height();
+
+        let mut quads = TextQuads {
+            bold_offset: (metrics.size() / 16.0).round().max(1.0),
+            ..TextQuads::default()
+        };
+        for (index, placed) in layout.chars.iter().enumerate() {
+            if let Some(icon) = icons.icon_index(placed.c) {
+                quads.icons.push(IconPlacement {
+                    icon,
+                    index,
+                    position: (placed.position.0.floor(), -placed.position.1),
+                    line_height,
+                });
+                continue;
+            }
+            let (handle, font, id) = metrics.resolve(placed.c);
+            let (cached, atlas, glyph_scale, pen_x) = if font.is_sdf() {
+                let key = GlyphKey {
+                    font: handle,
+                    glyph: id.0,
+                    size: 0,
+                };
+                let atlas = &mut self.sdf_glyph_atlas;
+                let cached = atlas.get_or_insert(key, || rasterize_sdf_glyph(font, id));
+                (cached, &*atlas, metrics.size() / SDF_GLYPH_SIZE, placed.position.0)
+            } else {
+                let key = GlyphKey {
+                    font: handle,
+                    glyph: id.0,
+                    size: metrics.pixel_size(),
+                };
+                let atlas = &mut self.glyph_atlas;
+                let cached = atlas.get_or_insert(key, || rasterize_glyph(font, id, metrics.size()));
+                // Kept on whole pixels so glyphs stay crisp
+                (cached, &*atlas, 1.0, placed.position.0.floor())
+            };
+            let (cached, texture) = match (cached, atlas.get_texture()) {
+                (Some(cached), Some(texture)) => (cached, texture),
+                _ => continue,
+            };
+            let mut sprite = Sprite::new(texture)
+                .with_region(cached.region)
+                .with_color(placed.color);
+            // Glyphs are placed by their top left corner
+            sprite.anchor = [0.0, 1.0];
+            quads.glyphs.push(GlyphQuad {
+                index,
+                sprite,
+                position: (
+                    pen_x + cached.offset.0 * glyph_scale,
+                    -(placed.position.1 + ascent + cached.offset.1 * glyph_scale),
+                ),
+                scale: glyph_scale,
+            });
+        }
+        quads
+    }
+
+    /// Submits the software cursor to the window the mouse is over, in the
+    /// top layer so it is drawn on top.
+    fn submit_software_cursor(&mut self) {
+        let cursor = match self.software_cursor.as_ref() {
+            Some(cursor) => cursor.clone(),
+            None => return,
+        };
+        let (window, (x, y)) = {
+            let input = INPUT.lock().unwrap();
+            match (input.get_cursor_window(), input.get_cursor_position()) {
+                (Some(window), Some(position)) => (window, position),
+                _ => return,
+            }
+        };
+        let (texture_width, texture_height) = match self.texture_size(cursor.sprite.texture) {
+            Some(size) => size,
+            None => return,
+        };
+        let (width, height) = match cursor.sprite.region {
+            Some(region) => (region.width, region.height),
+            None => (texture_width as f32, texture_height as f32),
+        };
+        if width == 0.0 || height == 0.0 {
+            return;
+        }
+        let (mut position, zoom) = match (
+            self.screen_to_world(window, x, y),
+            self.window_camera(window),
+        ) {
+            (Some(position), Some(camera)) => (position, camera.zoom),
+            _ => return,
+        };
+
+        let scale = if cursor.scale_with_camera {
+            // Keep the cursor's pixels on the world's pixel grid
+            position.x = position.x.round();
+            position.y = position.y.round();
+            1.0
+        } else {
+            1.0 / zoom
+        };
+        let mut sprite = cursor.sprite;
+        // The anchor is measured from the bottom left
+        sprite.anchor = [cursor.hotspot.0 / width, 1.0 - cursor.hotspot.1 / height];
+        sprite.layer = RenderLayer::TOP;
+        sprite.z_index = i32::MAX;
+        let model = cgmath::Matrix4::from_translation(cgmath::Vector3::new(position.x, position.y, 0.0))
+            * cgmath::Matrix4::from_scale(scale);
+        self.draw_sprite_to_window(window, model, &sprite);
+    }
+
+    fn clear_sprite_batches(&mut self) {
+        for sprite_batch in self.sprite_batches.values_mut() {
+            sprite_batch.clear();
+        }
+        self.glyph_atlas.next_frame();
+        self.sdf_glyph_atlas.next_frame();
+    }
+
+    /// Resizes the primary window's swap chain.
     pub fn resize(&mut self, desired_width: u32, desired_height: u32) {
+        self.resize_window(WindowId::PRIMARY, desired_width, desired_height);
+    }
+
+    /// Resizes a window's swap chain. A size of zero recreates it at its current size.
+    pub fn resize_window(&mut self, window: WindowId, desired_width: u32, desired_height: u32) {
         match self.state_descriptor.as_mut() {
             Some(sd) => {
+                let current_size = match sd.surface(window) {
+                    Some(surface) => surface.size,
+                    None => return,
+                };
                 let width;
                 let height;
                 if desired_height == 0 && desired_width == 0 {
-                    width = sd.size.width;
-                    height = sd.size.height;
+                    width = current_size.width;
+                    height = current_size.height;
                 } else {
                     width = desired_width;
                     height = desired_height;
                 }
-                sd.resize(winit::dpi::PhysicalSize::<u32>::new(width, height))
+                sd.resize(window, winit::dpi::PhysicalSize::<u32>::new(width, height))
             }
             None => (),
         }
     }
 
-    pub fn input(&mut self, event: &WindowEvent) -> bool {
-        self.state_descriptor
-            .as_mut()
-            .unwrap()
-            .camera_controller
-            .process(event)
-    }
-
-    pub fn update(&mut self) -> bool {
-        match self.render() {
-            Ok(_) => {}
-            // Recreate the swap_chain if lost
-            Err(wgpu::SwapChainError::Lost) => self.resize(0, 0),
-            // The system is out of memory, just quit.
-            Err(wgpu::SwapChainError::OutOfMemory) => return true,
-            // All other errors(Outdates, Timeout) should be resolved by the next frame.
-            Err(e) => eprintln!("{:?}", e),
+    /// Renders a frame into the render targets sprites were drawn into,
+    /// then into every window that isn't minimized, and handles swap chain
+    /// errors. Returns true if an error is fatal.
+    pub fn render_frame(&mut self) -> bool {
+        self.submit_software_cursor();
+        self.flush_glyph_atlases();
+        self.update_materials();
+        for target in self.render_target_order() {
+            if let Err(e) = self.render_destination(Destination::Target(target)) {
+                eprintln!("{:?}", e);
+            }
         }
+        let mut fatal = false;
+        for window in self.window_ids() {
+            if self.minimized.contains(&window) {
+                continue;
+            }
+            match self.render_destination(Destination::Window(window)) {
+                Ok(_) => {}
+                // Recreate the swap_chain if lost
+                Err(wgpu::SwapChainError::Lost) => self.resize_window(window, 0, 0),
+                // The system is out of memory, just quit.
+                Err(wgpu::SwapChainError::OutOfMemory) => {
+                    fatal = true;
+                    break;
+                }
+                // All other errors(Outdates, Timeout) should be resolved by the next frame.
+                Err(e) => eprintln!("{:?}", e),
+            }
+        }
+        self.clear_sprite_batches();
+        self.frame_pending = false;
 
-        return false;
+        fatal
     }
 
+    /// Renders a frame into the render targets sprites were drawn into,
+    /// then into every window that isn't minimized.
     pub fn render(&mut self) -> Result<(), wgpu::SwapChainError> {
-        let state_desc = self.state_descriptor.as_mut().unwrap();
-        state_desc.update();
-        let frame = state_desc.swap_chain.get_current_frame()?.output;
+        self.flush_glyph_atlases();
+        self.update_materials();
+        for target in self.render_target_order() {
+            self.render_destination(Destination::Target(target))?;
+        }
+        for window in self.window_ids() {
+            if !self.minimized.contains(&window) {
+                self.render_destination(Destination::Window(window))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn render_destination(&mut self, destination: Destination) -> Result<(), wgpu::SwapChainError> {
+        // Nothing to draw to, e.g. when running headless
+        let state_desc = match self.state_descriptor.as_mut() {
+            Some(sd) => sd,
+            None => return Ok(()),
+        };
+        // The sprite buffers are shared, so each destination's batch is
+        // written and submitted before the next one's
+        let sprite_batch = self.sprite_batches.entry(destination).or_default();
+        sprite_batch.build(&self.y_sorted_layers);
+        match destination {
+            Destination::Window(window) => state_desc.update_surface(window),
+            Destination::Target(target) => {
+                state_desc.update_target(target);
+                // A texture can't be sampled while it's drawn into
+                let own_texture = self.render_targets[target.0].texture;
+                let materials = &self.materials;
+                sprite_batch.batches.retain(|batch| {
+                    !sampled_textures(batch.texture, batch.material, materials).contains(&own_texture)
+                });
+            }
+        }
+        state_desc.write_sprite_batch(sprite_batch);
+        let format = match destination_surface(state_desc, destination) {
+            Some(surface) => surface.frame_format(),
+            None => return Ok(()),
+        };
+        let materials = &self.materials;
+        for batch in sprite_batch.batches.iter() {
+            let material = batch
+                .material
+                .and_then(|material| materials.get(material.0))
+                .and_then(|entry| entry.gpu.as_ref());
+            let key = match pipeline_key(batch, &self.textures, format) {
+                Some(key) => key,
+                None => continue,
+            };
+            if batch.material.is_none() || material.is_some() {
+                state_desc.prepare_pipeline(key, material);
+            }
+        }
+
+        // Screenshots and recordings capture the primary window
+        let mut capture_targets: Vec<CaptureTarget> = Vec::new();
+        if destination == Destination::Window(WindowId::PRIMARY) {
+            capture_targets.extend(self.screenshot_requests.drain(..).map(CaptureTarget::Screenshot));
+            if self.record_frame {
+                capture_targets.push(CaptureTarget::Recording);
+                self.record_frame = false;
+            }
+        }
+        if !capture_targets.is_empty() {
+            state_desc.prepare_capture(WindowId::PRIMARY);
+        }
+
+        let state_desc = &*state_desc;
+        let surface = match destination_surface(state_desc, destination) {
+            Some(surface) => surface,
+            None => return Ok(()),
+        };
+        let frame = surface.get_current_frame()?;
         let mut encoder =
             state_desc
                 .device
                 .create_command_encoder(&wgpu::CommandEncoderDescriptor {
                     label: Some("Render Encoder"),
                 });
-        {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
-                    attachment: &frame.view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(state_desc.clear_color),
-                        store: true,
-                    },
-                }],
-                depth_stencil_attachment: None,
-            });
+        draw_sprites(
+            &mut encoder,
+            state_desc,
+            surface,
+            sprite_batch,
+            &self.textures,
+            &self.materials,
+            surface.frame_view(&frame),
+        );
 
-            render_pass.set_pipeline(&state_desc.render_pipeline);
-            render_pass.set_bind_group(0, &state_desc.diffuse_bind_group, &[]);
-            render_pass.set_bind_group(1, &state_desc.uniform_bind_group, &[]);
-            render_pass.set_vertex_buffer(0, state_desc.vertex_buffer.slice(..));
-            render_pass
-                .set_index_buffer(state_desc.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-            render_pass.draw_indexed(0..state_desc.num_indicies, 0, 0..1);
+        let mut readback = None;
+        if !capture_targets.is_empty() {
+            if let Some((texture, capture_view)) = surface.capture_texture() {
+                if let Some(view) = capture_view {
+                    draw_sprites(
+                        &mut encoder,
+                        state_desc,
+                        surface,
+                        sprite_batch,
+                        &self.textures,
+                        &self.materials,
+                        view,
+                    );
+                }
+                readback = Some(FrameReadback::new(
+                    &state_desc.device,
+                    &mut encoder,
+                    texture,
+                    surface.size,
+                    surface.frame_format(),
+                ));
+            }
         }
 
         // Submit will accept anything that implments IntoIter
         state_desc.queue.submit(std::iter::once(encoder.finish()));
 
+        if let Some(mut readback) = readback {
+            readback.map();
+            self.pending_captures.push((readback, capture_targets));
+        }
+
         Ok(())
     }
 }
+
+/// Where a world entity's sprites are drawn into, render targets first.
+fn destination(window: Option<&TargetWindow>, target: Option<&TargetTexture>) -> Destination {
+    match (target, window) {
+        (Some(target), _) => Destination::Target(target.0),
+        (None, Some(window)) => Destination::Window(window.0),
+        (None, None) => Destination::Window(WindowId::PRIMARY),
+    }
+}
+
+fn destination_surface(state_desc: &StateDescriptor, destination: Destination) -> Option<&SurfaceState> {
+    match destination {
+        Destination::Window(window) => state_desc.surface(window),
+        Destination::Target(target) => state_desc.target(target),
+    }
+}
+
+/// The textures drawing a sprite with `texture` and `material` samples.
+fn sampled_textures(
+    texture: TextureHandle,
+    material: Option<MaterialHandle>,
+    materials: &[MaterialEntry],
+) -> Vec<TextureHandle> {
+    let mut textures = vec![texture];
+    if let Some(entry) = material.and_then(|material| materials.get(material.0)) {
+        textures.extend(entry.material.get_textures().iter().map(|(_, texture)| *texture));
+    }
+    textures
+}
+
+/// A watcher for the built-in shaders' sources, includes and manifest, or
+/// an empty one if they can't be edited.
+fn new_shader_watcher() -> ShaderWatcher {
+    let mut watcher = ShaderWatcher::default();
+    if !BuiltinShader::has_editable_sources() {
+        return watcher;
+    }
+    watcher.watch(BuiltinShader::manifest_path());
+    for builtin in BuiltinShader::ALL.iter() {
+        builtin.sources().iter().for_each(|source| watcher.watch(source));
+    }
+    watcher
+}
+
+/// The pipeline a batch is drawn with into a target of `format`, or `None`
+/// if its texture isn't one of `textures`, e.g. another renderer's.
+fn pipeline_key(batch: &Batch, textures: &[TextureEntry], format: wgpu::TextureFormat) -> Option<PipelineKey> {
+    let texture = textures.get(batch.texture.0)?;
+    let shader = match batch.material {
+        Some(material) => PipelineShader::Material(material),
+        None if texture.sdf => PipelineShader::Sdf,
+        None => PipelineShader::Sprite,
+    };
+    Some(PipelineKey {
+        shader,
+        format,
+        blend_mode: batch.blend_mode,
+    })
+}
+
+/// Records a render pass drawing the sprite batch into `view` through
+/// the surface's camera. Batches whose pipeline or resources aren't
+/// ready yet are skipped.
+fn draw_sprites(
+    encoder: &mut wgpu::CommandEncoder,
+    state_desc: &StateDescriptor,
+    surface: &SurfaceState,
+    sprite_batch: &SpriteBatch,
+    textures: &[TextureEntry],
+    materials: &[MaterialEntry],
+    view: &wgpu::TextureView,
+) {
+    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("Render Pass"),
+        color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+            attachment: view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(surface.clear_color.unwrap_or(state_desc.clear_color)),
+                store: true,
+            },
+        }],
+        depth_stencil_attachment: None,
+    });
+
+    render_pass.set_bind_group(1, &surface.uniform_bind_group, &[]);
+    render_pass.set_vertex_buffer(0, state_desc.vertex_buffer.slice(..));
+    render_pass.set_index_buffer(state_desc.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+    let format = surface.frame_format();
+    let mut current_key = None;
+    for batch in sprite_batch.batches.iter() {
+        let bind_group = match textures.get(batch.texture.0).and_then(|texture| texture.gpu.as_ref()) {
+            Some((_, bind_group)) => bind_group,
+            None => continue,
+        };
+        let key = match pipeline_key(batch, textures, format) {
+            Some(key) => key,
+            None => continue,
+        };
+        let pipeline = match state_desc.pipeline(&key) {
+            Some(pipeline) => pipeline,
+            None => continue,
+        };
+        if let Some(material) = batch.material {
+            let material_bind_group = materials
+                .get(material.0)
+                .and_then(|entry| entry.gpu.as_ref())
+                .and_then(|gpu| gpu.bind_group.as_ref());
+            match material_bind_group {
+                Some(material_bind_group) => render_pass.set_bind_group(2, material_bind_group, &[]),
+                None => continue,
+            }
+        }
+        if current_key != Some(key) {
+            current_key = Some(key);
+            render_pass.set_pipeline(pipeline);
+        }
+        render_pass.set_bind_group(0, bind_group, &[]);
+        render_pass.draw_indexed(batch.indices.clone(), 0, 0..1);
+    }
+}
+
+/// Places the glyphs of a bitmap font text, relative to the text's origin.
+fn bitmap_text_quads(font: &BitmapFont, pages: &[TextureHandle], text: &Text, icons: &IconLayout) -> TextQuads {
+    let scale = if text.scale > 0.0 { text.scale } else { 1.0 };
+    let metrics = IconMetrics { font, icons };
+    let layout = place_chars(&metrics, text, text.max_width.map(|max_width| max_width / scale));
+
+    let mut quads = TextQuads {
+        bold_offset: text.scale,
+        ..TextQuads::default()
+    };
+    for (index, placed) in layout.chars.iter().enumerate() {
+        if let Some(icon) = icons.icon_index(placed.c) {
+            quads.icons.push(IconPlacement {
+                icon,
+                index,
+                position: (placed.position.0 * text.scale, -placed.position.1 * text.scale),
+                line_height: font.line_height * text.scale,
+            });
+            continue;
+        }
+        let glyph = match font.glyph(placed.c) {
+            Some(glyph) if glyph.region.width > 0.0 && glyph.region.height > 0.0 => glyph,
+            _ => continue,
+        };
+        let texture = match pages.get(glyph.page) {
+            Some(texture) => *texture,
+            None => continue,
+        };
+        let mut sprite = Sprite::new(texture).with_region(glyph.region).with_color(placed.color);
+        // Glyphs are placed by their top left corner
+        sprite.anchor = [0.0, 1.0];
+        // Kept on whole pixels so pixel fonts stay crisp
+        let x = (placed.position.0 + glyph.offset.0).floor();
+        let y = (placed.position.1 + glyph.offset.1).floor();
+        quads.glyphs.push(GlyphQuad {
+            index,
+            sprite,
+            position: (x * text.scale, -y * text.scale),
+            scale: text.scale,
+        });
+    }
+    quads
+}
+
+/// The text's vector font and its fallbacks, at the size the text is drawn at.
+fn vector_metrics<'a>(fonts: &'a [FontEntry], text: &Text) -> Option<VectorMetrics<'a>> {
+    let mut handles = vec![text.font];
+    match fonts.get(text.font.0)? {
+        FontEntry::Vector { fallbacks, .. } => handles.extend(fallbacks.iter().copied()),
+        FontEntry::Bitmap { .. } => return None,
+    }
+    let fonts = handles
+        .into_iter()
+        .filter_map(|handle| match fonts.get(handle.0)? {
+            FontEntry::Vector { font, .. } => Some((handle, font)),
+            FontEntry::Bitmap { .. } => None,
+        })
+        .collect();
+    Some(VectorMetrics::new(fonts, text.font_size * text.scale))
+}
+
+fn save_screenshot(frame: image::RgbaImage, path: &Path) {
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    match frame.save(path) {
+        Ok(_) => println!("[Renderer]: Saved screenshot {}", path.display()),
+        Err(e) => eprintln!("[Renderer]: Unable to save screenshot {}: {:?}", path.display(), e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::renderer::{present_mode::PresentMode, renderer::Renderer};
+
+    #[test]
+    fn forced_present_modes_keep_the_chosen_one() {
+        let mut renderer = Renderer::default().with_present_mode(PresentMode::Mailbox);
+        renderer.set_forced_present_mode(Some(PresentMode::Fifo));
+        assert_eq!(renderer.get_effective_present_mode(), PresentMode::Fifo);
+
+        // Chosen while another mode is forced
+        renderer.set_present_mode(PresentMode::Immediate);
+        assert_eq!(renderer.get_effective_present_mode(), PresentMode::Fifo);
+        renderer.set_forced_present_mode(Some(PresentMode::Immediate));
+        renderer.set_forced_present_mode(None);
+        assert_eq!(renderer.get_present_mode(), PresentMode::Immediate);
+        assert_eq!(renderer.get_effective_present_mode(), PresentMode::Immediate);
+    }
+}
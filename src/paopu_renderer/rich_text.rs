@@ -0,0 +1,616 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use crate::{
+    core::entity::Entity,
+    input::input::PKeyCode,
+    renderer::{
+        font::FontHandle,
+        sprite::{RenderLayer, Sprite},
+        text::{layout_chars, FontMetrics, Text, TextAlignment},
+    },
+};
+
+// Icons are laid out as characters from the supplementary private use
+// areas that the text doesn't use itself, starting from this one.
+const FIRST_ICON_CHAR: u32 = 0xF0000;
+
+/// Sent when a [`RichText`]'s typewriter reveals a character, e.g. to play
+/// a sound blip. Whitespace is revealed too.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CharacterRevealed {
+    pub entity: Entity,
+    /// The index of the character, newlines excluded.
+    pub index: usize,
+    /// `None` for icons.
+    pub character: Option<char>,
+}
+
+// -------------------------------------------------------------------------
+//              - Style -
+// -------------------------------------------------------------------------
+
+/// Moves characters up and down in a wave.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Wave {
+    /// In font pixels.
+    pub amplitude: f32,
+    /// Waves per second.
+    pub frequency: f32,
+}
+
+/// How a single character of a [`RichText`] is drawn.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CharStyle {
+    pub color: [f32; 4],
+    /// Drawn twice, one pixel apart.
+    pub bold: bool,
+    pub wave: Option<Wave>,
+    /// Jitters the character up to this many font pixels.
+    pub shake: Option<f32>,
+}
+
+impl Default for CharStyle {
+    fn default() -> Self {
+        Self {
+            color: [1.0, 1.0, 1.0, 1.0],
+            bold: false,
+            wave: None,
+            shake: None,
+        }
+    }
+}
+
+// -------------------------------------------------------------------------
+//              - Icon Set -
+// -------------------------------------------------------------------------
+
+/// The sprites `[icon=name]` tags are drawn with. A name is looked up in
+/// the icons first, then in the bindings, so `[icon=jump]` can show the
+/// icon of whichever key jump is bound to.
+#[derive(Debug, Clone, Default)]
+pub struct IconSet {
+    icons: HashMap<String, Sprite>,
+    key_icons: HashMap<PKeyCode, Sprite>,
+    bindings: HashMap<String, PKeyCode>,
+}
+
+impl IconSet {
+    pub fn with_icon(mut self, name: &str, sprite: Sprite) -> Self {
+        self.set_icon(name, sprite);
+        self
+    }
+
+    pub fn with_key_icon(mut self, key: PKeyCode, sprite: Sprite) -> Self {
+        self.set_key_icon(key, sprite);
+        self
+    }
+
+    pub fn with_binding(mut self, action: &str, key: PKeyCode) -> Self {
+        self.set_binding(action, key);
+        self
+    }
+
+    pub fn set_icon(&mut self, name: &str, sprite: Sprite) {
+        self.icons.insert(name.to_string(), sprite);
+    }
+
+    pub fn set_key_icon(&mut self, key: PKeyCode, sprite: Sprite) {
+        self.key_icons.insert(key, sprite);
+    }
+
+    /// Binds an action to a key. Call again when the player rebinds it.
+    pub fn set_binding(&mut self, action: &str, key: PKeyCode) {
+        self.bindings.insert(action.to_string(), key);
+    }
+
+    pub fn get_binding(&self, action: &str) -> Option<PKeyCode> {
+        self.bindings.get(action).copied()
+    }
+
+    pub fn get_icon(&self, name: &str) -> Option<&Sprite> {
+        self.icons.get(name).or_else(|| {
+            self.bindings
+                .get(name)
+                .and_then(|key| self.key_icons.get(key))
+        })
+    }
+}
+
+// -------------------------------------------------------------------------
+//              - Rich Text -
+// -------------------------------------------------------------------------
+
+/// Reveals the characters of a [`RichText`] one at a time.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Typewriter {
+    pub chars_per_second: f32,
+    revealed: f32,
+}
+
+/// Text with inline markup:
+///
+/// - `[color=red]`, `[color=#ff8000]` or `[color=#ff800080]` ... `[/color]`
+/// - `[b]` ... `[/b]`
+/// - `[wave]`, `[wave=amplitude]` or `[wave=amplitude,frequency]` ... `[/wave]`
+/// - `[shake]` or `[shake=amplitude]` ... `[/shake]`
+/// - `[icon=name]`, drawn from the renderer's [`IconSet`] at the height of a line
+/// - `[[` for a literal `[`
+///
+/// # Example
+///
+/// ```ignore
+/// let text = RichText::parse(font, "Press [icon=jump] to [wave][color=yellow]fly[/color][/wave]!")?
+///     .with_typewriter(30.0);
+/// app.world.spawn((Transform2D::default(), text));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct RichText {
+    text: Text,
+    // The text's characters and their styles, newlines excluded.
+    chars: Vec<char>,
+    styles: Vec<CharStyle>,
+    icons: Vec<String>,
+    // The character standing in for each icon in the text.
+    icon_chars: Vec<char>,
+    typewriter: Option<Typewriter>,
+    // Drives the effects.
+    elapsed: f32,
+}
+
+impl RichText {
+    pub fn parse(font: FontHandle, markup: &str) -> anyhow::Result<Self> {
+        let mut rich_text = Self {
+            text: Text::new(font, ""),
+            chars: Vec::new(),
+            styles: Vec::new(),
+            icons: Vec::new(),
+            icon_chars: Vec::new(),
+            typewriter: None,
+            elapsed: 0.0,
+        };
+        rich_text.set_markup(markup)?;
+        Ok(rich_text)
+    }
+
+    pub fn with_alignment(mut self, alignment: TextAlignment) -> Self {
+        self.text.alignment = alignment;
+        self
+    }
+
+    pub fn with_max_width(mut self, max_width: f32) -> Self {
+        self.text.max_width = Some(max_width);
+        self
+    }
+
+    pub fn with_scale(mut self, scale: f32) -> Self {
+        self.text.scale = scale;
+        self
+    }
+
+    pub fn with_font_size(mut self, font_size: f32) -> Self {
+        self.text.font_size = font_size;
+        self
+    }
+
+    pub fn with_layer(mut self, layer: RenderLayer) -> Self {
+        self.text.layer = layer;
+        self
+    }
+
+    pub fn with_z_index(mut self, z_index: i32) -> Self {
+        self.text.z_index = z_index;
+        self
+    }
+
+    /// Hides the text and reveals `chars_per_second` characters a second.
+    pub fn with_typewriter(mut self, chars_per_second: f32) -> Self {
+        self.typewriter = Some(Typewriter {
+            chars_per_second,
+            revealed: 0.0,
+        });
+        self
+    }
+
+    /// Replaces the text, restarting the typewriter.
+    pub fn set_markup(&mut self, markup: &str) -> anyhow::Result<()> {
+        let markup = parse_markup(markup)?;
+        self.text.set_text(&markup.text);
+        self.chars = markup.text.chars().filter(|c| *c != '\n').collect();
+        self.styles = markup.styles;
+        self.icons = markup.icons;
+        self.icon_chars = markup.icon_chars;
+        if let Some(typewriter) = self.typewriter.as_mut() {
+            typewriter.revealed = 0.0;
+        }
+        Ok(())
+    }
+
+    /// The text without markup, icons being private use characters that
+    /// the markup doesn't contain.
+    pub fn get_text(&self) -> &Text {
+        &self.text
+    }
+
+    pub fn get_style(&self, index: usize) -> Option<&CharStyle> {
+        self.styles.get(index)
+    }
+
+    /// The names of the icons, in order.
+    pub fn get_icons(&self) -> &[String] {
+        &self.icons
+    }
+
+    /// The characters standing in for the icons in the text, in order.
+    pub fn get_icon_chars(&self) -> &[char] {
+        &self.icon_chars
+    }
+
+    /// The number of characters and icons, newlines excluded.
+    pub fn char_count(&self) -> usize {
+        self.styles.len()
+    }
+
+    /// The character at `index`, newlines excluded, or `None` for icons.
+    pub fn get_char(&self, index: usize) -> Option<char> {
+        let c = *self.chars.get(index)?;
+        if self.icon_chars.contains(&c) {
+            None
+        } else {
+            Some(c)
+        }
+    }
+
+    pub fn get_typewriter(&self) -> Option<&Typewriter> {
+        self.typewriter.as_ref()
+    }
+
+    /// The number of characters the typewriter revealed so far.
+    pub fn visible_count(&self) -> usize {
+        match self.typewriter {
+            Some(typewriter) => (typewriter.revealed as usize).min(self.char_count()),
+            None => self.char_count(),
+        }
+    }
+
+    pub fn is_revealed(&self) -> bool {
+        self.visible_count() == self.char_count()
+    }
+
+    /// Skips the typewriter to the end, e.g. when the player presses a key.
+    pub fn reveal_all(&mut self) {
+        let count = self.char_count();
+        if let Some(typewriter) = self.typewriter.as_mut() {
+            typewriter.revealed = count as f32;
+        }
+    }
+
+    pub fn get_elapsed(&self) -> f32 {
+        self.elapsed
+    }
+
+    /// Advances the effects and the typewriter. Returns the index of every
+    /// character revealed, and the character or `None` for icons.
+    pub fn advance(&mut self, delta: Duration) -> Vec<(usize, Option<char>)> {
+        self.elapsed += delta.as_secs_f32();
+        let before = self.visible_count();
+        let count = self.char_count();
+        if let Some(typewriter) = self.typewriter.as_mut() {
+            typewriter.revealed =
+                (typewriter.revealed + typewriter.chars_per_second * delta.as_secs_f32()).min(count as f32);
+        }
+        (before..self.visible_count())
+            .map(|index| (index, self.get_char(index)))
+            .collect()
+    }
+}
+
+/// A glyph or icon quad of a [`RichText`] placed by the renderer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RichGlyph {
+    /// The index of the character, newlines excluded.
+    pub index: usize,
+    pub sprite: Sprite,
+    /// The top left corner relative to the text's origin, in world units.
+    pub position: (f32, f32),
+    pub scale: f32,
+    pub style: CharStyle,
+    /// The size of a font pixel in world units, which effects are measured in.
+    pub pixel: f32,
+}
+
+impl RichGlyph {
+    /// How far the effects move the glyph after `elapsed` seconds.
+    pub fn effect_offset(&self, elapsed: f32) -> (f32, f32) {
+        let mut offset = (0.0, 0.0);
+        if let Some(wave) = self.style.wave {
+            // Each character trails the previous one
+            let phase = elapsed * wave.frequency * std::f32::consts::PI * 2.0 - self.index as f32 * 0.5;
+            offset.1 += phase.sin() * wave.amplitude * self.pixel;
+        }
+        if let Some(amplitude) = self.style.shake {
+            // A new position twenty times a second
+            let step = (elapsed * 20.0) as u32;
+            offset.0 += noise(self.index as u32, step, 0) * amplitude * self.pixel;
+            offset.1 += noise(self.index as u32, step, 1) * amplitude * self.pixel;
+        }
+        offset
+    }
+
+    /// The glyph's transform relative to the text's origin.
+    pub fn local_matrix(&self, elapsed: f32) -> cgmath::Matrix4<f32> {
+        let offset = self.effect_offset(elapsed);
+        cgmath::Matrix4::from_translation(cgmath::Vector3::new(
+            self.position.0 + offset.0,
+            self.position.1 + offset.1,
+            0.0,
+        )) * cgmath::Matrix4::from_scale(self.scale)
+    }
+}
+
+/// A value from -1 to 1 that looks random.
+fn noise(index: u32, step: u32, axis: u32) -> f32 {
+    let mut hash = index
+        .wrapping_mul(0x9E37_79B9)
+        ^ step.wrapping_mul(0x85EB_CA6B)
+        ^ axis.wrapping_mul(0xC2B2_AE35);
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x7FEB_352D);
+    hash ^= hash >> 15;
+    (hash & 0xFFFF) as f32 / 32767.5 - 1.0
+}
+
+// ---------------------------------------------------------
+//                  Icons
+// ---------------------------------------------------------
+
+/// The characters standing in for a text's icons, and the aspect ratio of
+/// every icon.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct IconLayout {
+    pub chars: Vec<char>,
+    pub ratios: Vec<f32>,
+}
+
+impl IconLayout {
+    /// The icon `c` stands in for.
+    pub fn icon_index(&self, c: char) -> Option<usize> {
+        self.chars.iter().position(|icon| *icon == c)
+    }
+}
+
+/// Picks characters for icons that don't appear in the markup, so literal
+/// private use characters are still drawn from the font.
+struct IconChars {
+    used: HashSet<char>,
+    next: u32,
+}
+
+impl IconChars {
+    fn new(markup: &str) -> Self {
+        Self {
+            used: markup.chars().collect(),
+            next: FIRST_ICON_CHAR,
+        }
+    }
+
+    fn next(&mut self) -> anyhow::Result<char> {
+        while let Some(c) = std::char::from_u32(self.next) {
+            self.next += 1;
+            if !self.used.contains(&c) {
+                return Ok(c);
+            }
+        }
+        anyhow::bail!("[RichText]: Too many icons")
+    }
+}
+
+/// A font's metrics with icons as wide as their aspect ratio times the
+/// line height.
+pub(crate) struct IconMetrics<'a, M> {
+    pub font: &'a M,
+    pub icons: &'a IconLayout,
+}
+
+impl<'a, M: FontMetrics> FontMetrics for IconMetrics<'a, M> {
+    fn advance(&self, c: char) -> f32 {
+        match self.icons.icon_index(c) {
+            Some(icon) => (self.icons.ratios[icon] * self.font.line_height()).round(),
+            None => self.font.advance(c),
+        }
+    }
+
+    fn kerning(&self, first: char, second: char) -> f32 {
+        if self.icons.icon_index(first).is_some() || self.icons.icon_index(second).is_some() {
+            return 0.0;
+        }
+        self.font.kerning(first, second)
+    }
+
+    fn line_height(&self) -> f32 {
+        self.font.line_height()
+    }
+}
+
+// ---------------------------------------------------------
+//                  Markup
+// ---------------------------------------------------------
+
+/// Markup split into its parts.
+struct Markup {
+    text: String,
+    // The style of every character but newlines.
+    styles: Vec<CharStyle>,
+    icons: Vec<String>,
+    icon_chars: Vec<char>,
+}
+
+fn parse_markup(markup: &str) -> anyhow::Result<Markup> {
+    let mut text = String::new();
+    let mut styles = Vec::new();
+    let mut icons = Vec::new();
+    let mut icon_chars = Vec::new();
+    let mut free_icon_chars = IconChars::new(markup);
+    let mut style = CharStyle::default();
+    // Open tags with the style from before them
+    let mut open: Vec<(String, CharStyle)> = Vec::new();
+
+    let mut rest = markup;
+    while let Some(start) = rest.find('[') {
+        // Laid out the same way as the text, so styles line up with glyphs
+        for c in rest[..start].chars().flat_map(layout_chars) {
+            text.push(c);
+            if c != '\n' {
+                styles.push(style);
+            }
+        }
+        rest = &rest[start + 1..];
+        if let Some(after) = rest.strip_prefix('[') {
+            text.push('[');
+            styles.push(style);
+            rest = after;
+            continue;
+        }
+        let end = rest
+            .find(']')
+            .ok_or_else(|| anyhow::anyhow!("[RichText]: Unclosed tag `[{}`", rest))?;
+        let tag = rest[..end].trim();
+        rest = &rest[end + 1..];
+
+        if let Some(name) = tag.strip_prefix('/') {
+            let name = name.trim();
+            let position = open
+                .iter()
+                .rposition(|(open_name, _)| open_name == name)
+                .ok_or_else(|| anyhow::anyhow!("[RichText]: `[/{}]` closes no tag", name))?;
+            // Only the tag's own attribute is restored, so tags may overlap
+            let (_, before) = open.remove(position);
+            match name {
+                "color" => style.color = before.color,
+                "b" => style.bold = before.bold,
+                "wave" => style.wave = before.wave,
+                _ => style.shake = before.shake,
+            }
+            continue;
+        }
+
+        let (name, value) = match tag.find('=') {
+            Some(equals) => (tag[..equals].trim(), Some(tag[equals + 1..].trim())),
+            None => (tag, None),
+        };
+        let previous = style;
+        match (name, value) {
+            ("icon", Some(icon)) => {
+                let c = free_icon_chars.next()?;
+                text.push(c);
+                styles.push(style);
+                icons.push(icon.to_string());
+                icon_chars.push(c);
+                continue;
+            }
+            ("color", Some(color)) => style.color = parse_color(color)?,
+            ("b", None) => style.bold = true,
+            ("wave", value) => {
+                let mut numbers = parse_numbers(tag, value)?.into_iter();
+                style.wave = Some(Wave {
+                    amplitude: numbers.next().unwrap_or(2.0),
+                    frequency: numbers.next().unwrap_or(1.0),
+                });
+            }
+            ("shake", value) => {
+                style.shake = Some(parse_numbers(tag, value)?.first().copied().unwrap_or(1.0));
+            }
+            _ => anyhow::bail!("[RichText]: Unknown tag `[{}]`", tag),
+        }
+        open.push((name.to_string(), previous));
+    }
+    for c in rest.chars().flat_map(layout_chars) {
+        text.push(c);
+        if c != '\n' {
+            styles.push(style);
+        }
+    }
+    Ok(Markup {
+        text,
+        styles,
+        icons,
+        icon_chars,
+    })
+}
+
+fn parse_numbers(tag: &str, value: Option<&str>) -> anyhow::Result<Vec<f32>> {
+    match value {
+        Some(value) => value
+            .split(',')
+            .map(|number| {
+                number
+                    .trim()
+                    .parse::<f32>()
+                    .map_err(|_| anyhow::anyhow!("[RichText]: Invalid number in `[{}]`", tag))
+            })
+            .collect(),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Parses a color name or a `#rrggbb` or `#rrggbbaa` hex color.
+fn parse_color(color: &str) -> anyhow::Result<[f32; 4]> {
+    let named = match color.to_lowercase().as_str() {
+        "white" => Some([1.0, 1.0, 1.0, 1.0]),
+        "black" => Some([0.0, 0.0, 0.0, 1.0]),
+        "gray" | "grey" => Some([0.5, 0.5, 0.5, 1.0]),
+        "red" => Some([1.0, 0.0, 0.0, 1.0]),
+        "green" => Some([0.0, 1.0, 0.0, 1.0]),
+        "blue" => Some([0.0, 0.0, 1.0, 1.0]),
+        "yellow" => Some([1.0, 1.0, 0.0, 1.0]),
+        "cyan" => Some([0.0, 1.0, 1.0, 1.0]),
+        "magenta" => Some([1.0, 0.0, 1.0, 1.0]),
+        "orange" => Some([1.0, 0.5, 0.0, 1.0]),
+        _ => None,
+    };
+    if let Some(named) = named {
+        return Ok(named);
+    }
+
+    let invalid = || anyhow::anyhow!("[RichText]: Invalid color `{}`", color);
+    let hex = color.strip_prefix('#').ok_or_else(invalid)?;
+    if (hex.len() != 6 && hex.len() != 8) || !hex.is_ascii() {
+        return Err(invalid());
+    }
+    let mut rgba = [1.0; 4];
+    for (channel, value) in rgba.iter_mut().enumerate().take(hex.len() / 2) {
+        let byte = u8::from_str_radix(&hex[channel * 2..channel * 2 + 2], 16).map_err(|_| invalid())?;
+        *value = byte as f32 / 255.0;
+    }
+    Ok(rgba)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::renderer::{font::FontHandle, rich_text::RichText};
+
+    #[test]
+    fn literal_private_use_characters_are_not_icons() {
+        let markup = "\u{F0000}[icon=jump]\u{F0001}";
+        let rich_text = RichText::parse(FontHandle(0), markup).unwrap();
+        assert_eq!(rich_text.get_icon_chars(), &['\u{F0002}']);
+        assert_eq!(rich_text.get_char(0), Some('\u{F0000}'));
+        assert_eq!(rich_text.get_char(1), None);
+        assert_eq!(rich_text.get_char(2), Some('\u{F0001}'));
+    }
+
+    #[test]
+    fn advance_returns_the_revealed_characters() {
+        let mut rich_text = RichText::parse(FontHandle(0), "ab\n[icon=jump]c")
+            .unwrap()
+            .with_typewriter(10.0);
+        assert_eq!(rich_text.advance(Duration::from_millis(250)), vec![(0, Some('a')), (1, Some('b'))]);
+        assert_eq!(rich_text.advance(Duration::from_millis(250)), vec![(2, None), (3, Some('c'))]);
+        assert_eq!(rich_text.advance(Duration::from_millis(250)), Vec::new());
+        assert!(rich_text.is_revealed());
+    }
+}
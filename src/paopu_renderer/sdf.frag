@@ -0,0 +1,13 @@
+//sdf.frag
+
+#version 450
+
+#include <sprite_fragment.glsl>
+
+// The alpha channel holds the distance to the outline, 0.5 being on it
+void main(){
+	float distance = texture(sampler2D(t_diffuse, s_diffuse), v_tex_coords).a;
+	float width = fwidth(distance);
+	float alpha = smoothstep(0.5 - width, 0.5 + width, distance);
+	f_color = sprite_output(vec4(v_color.rgb, v_color.a * alpha));
+}
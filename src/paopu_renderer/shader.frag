@@ -1,14 +1,9 @@
-
 //shader.frag
 
 #version 450
 
-layout(location=0) in vec2 v_tex_coords;
-layout(location=0) out vec4 f_color;
-
-layout(set=0, binding=0) uniform texture2D t_diffuse;
-layout(set=0, binding=1) uniform sampler s_diffuse;
+#include <sprite_fragment.glsl>
 
 void main(){
-	f_color = texture(sampler2D(t_diffuse, s_diffuse), v_tex_coords);
+	f_color = sprite_output(texture(sampler2D(t_diffuse, s_diffuse), v_tex_coords) * v_color);
 }
@@ -0,0 +1,220 @@
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+
+use crate::renderer::{
+    shader_compiler::{parse_glsl, parse_wgsl, write_spirv, DEFAULT_DEBUG_LEVEL},
+    shader_preprocessor::{PreprocessedSource, ShaderPreprocessor},
+    shader_reflection::ShaderReflection,
+};
+
+// The first word of every SPIR-V module.
+const SPIRV_MAGIC_NUMBER: u32 = 0x0723_0203;
+
+/// A stage of the sprite pipeline.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ShaderStage {
+    Vertex,
+    Fragment,
+}
+
+impl ShaderStage {
+    fn to_naga(self) -> naga::ShaderStage {
+        match self {
+            ShaderStage::Vertex => naga::ShaderStage::Vertex,
+            ShaderStage::Fragment => naga::ShaderStage::Fragment,
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------
+//                      - Shader -
+// -------------------------------------------------------------------------------
+
+/// A shader compiled to SPIR-V, along with its entry points.
+///
+/// WGSL and GLSL sources are compiled at runtime, so games can ship their
+/// own shaders as assets. Files loaded through a [`ShaderPreprocessor`] can
+/// `#include` others and use its defines. Compile errors point at the file
+/// and line, includes included.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shader {
+    label: String,
+    words: Vec<u32>,
+    // Empty for SPIR-V, whose entry points are assumed to be `main`.
+    entry_points: Vec<(ShaderStage, String)>,
+    // None for SPIR-V, which isn't reflected.
+    reflection: Option<ShaderReflection>,
+    // The file it was loaded from and how, for hot reloading.
+    path: Option<PathBuf>,
+    preprocessor: ShaderPreprocessor,
+    // The file and its includes, watched for hot reloading.
+    sources: Vec<PathBuf>,
+}
+
+impl Shader {
+    /// Wraps SPIR-V compiled ahead of time, e.g. by `glslc`.
+    /// It isn't reflected, so its entry points must be named `main`.
+    pub fn from_spirv(bytes: &[u8], label: &str) -> anyhow::Result<Self> {
+        let chunks = bytes.chunks_exact(4);
+        if !chunks.remainder().is_empty() {
+            anyhow::bail!("[Shader]: {} isn't SPIR-V, its size isn't a multiple of 4", label);
+        }
+        let words: Vec<u32> = chunks
+            .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+            .collect();
+        if words.first() != Some(&SPIRV_MAGIC_NUMBER) {
+            anyhow::bail!("[Shader]: {} isn't SPIR-V, it has no magic number", label);
+        }
+        Ok(Self {
+            label: label.to_string(),
+            words,
+            entry_points: Vec::new(),
+            reflection: None,
+            path: None,
+            preprocessor: ShaderPreprocessor::default(),
+            sources: Vec::new(),
+        })
+    }
+
+    /// Compiles WGSL. A module can hold the entry points of both stages.
+    pub fn from_wgsl(source: &str, label: &str) -> anyhow::Result<Self> {
+        let source = PreprocessedSource::unprocessed(source, label);
+        Self::compile_wgsl(&source, label)
+    }
+
+    /// Compiles a GLSL shader of the given stage.
+    pub fn from_glsl(source: &str, stage: ShaderStage, label: &str) -> anyhow::Result<Self> {
+        let source = PreprocessedSource::unprocessed(source, label);
+        Self::compile_glsl(&source, stage, &ShaderPreprocessor::default(), label)
+    }
+
+    /// Loads a shader by its extension: `.wgsl`, GLSL `.vert` and `.frag`,
+    /// or compiled `.spv`.
+    pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        Self::load_with(path, &ShaderPreprocessor::default())
+    }
+
+    /// Loads a shader, resolving its includes and defines with `preprocessor`.
+    pub fn load_with<P: AsRef<Path>>(path: P, preprocessor: &ShaderPreprocessor) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let label = path.to_string_lossy();
+        let extension = path.extension().and_then(|extension| extension.to_str());
+        let mut shader = if extension == Some("spv") {
+            let bytes = std::fs::read(path)
+                .with_context(|| format!("[Shader]: Unable to read shader {}", path.display()))?;
+            let mut shader = Self::from_spirv(&bytes, &label)?;
+            shader.sources = vec![path.to_path_buf()];
+            shader
+        } else {
+            let source = preprocessor
+                .process_file(path)
+                .map_err(|e| anyhow::anyhow!("[Shader]: {:#}", e))?;
+            let mut shader = match extension {
+                Some("wgsl") => Self::compile_wgsl(&source, &label)?,
+                Some("vert") => Self::compile_glsl(&source, ShaderStage::Vertex, preprocessor, &label)?,
+                Some("frag") => Self::compile_glsl(&source, ShaderStage::Fragment, preprocessor, &label)?,
+                _ => anyhow::bail!("[Shader]: Unsupported shader: {}", path.display()),
+            };
+            shader.sources = source.get_files().to_vec();
+            shader
+        };
+        shader.path = Some(path.to_path_buf());
+        shader.preprocessor = preprocessor.clone();
+        Ok(shader)
+    }
+
+    /// Loads the shader again from its file, e.g. once its sources changed.
+    pub fn reload(&self) -> anyhow::Result<Self> {
+        match &self.path {
+            Some(path) => Self::load_with(path, &self.preprocessor),
+            None => anyhow::bail!("[Shader]: {} wasn't loaded from a file", self.label),
+        }
+    }
+
+    pub fn get_label(&self) -> &str {
+        &self.label
+    }
+
+    /// The file the shader was loaded from.
+    pub fn get_path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// The files the shader was compiled from: its own and its includes.
+    pub fn get_sources(&self) -> &[PathBuf] {
+        &self.sources
+    }
+
+    /// The resources and vertex inputs the shader uses. SPIR-V isn't reflected.
+    pub fn get_reflection(&self) -> Option<&ShaderReflection> {
+        self.reflection.as_ref()
+    }
+
+    /// The name of the stage's entry point, if the shader has one.
+    pub fn get_entry_point(&self, stage: ShaderStage) -> Option<&str> {
+        if self.entry_points.is_empty() {
+            return Some("main");
+        }
+        self.entry_points
+            .iter()
+            .find(|(entry_stage, _)| *entry_stage == stage)
+            .map(|(_, name)| name.as_str())
+    }
+
+    pub(crate) fn create_module(&self, device: &wgpu::Device) -> wgpu::ShaderModule {
+        device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: Some(&self.label),
+            source: wgpu::ShaderSource::SpirV(Cow::Borrowed(&self.words)),
+            flags: wgpu::ShaderFlags::VALIDATION,
+        })
+    }
+
+    fn compile_wgsl(source: &PreprocessedSource, label: &str) -> anyhow::Result<Self> {
+        let module = parse_wgsl(source).map_err(|e| anyhow::anyhow!("[Shader]: {:#}", e))?;
+        Self::from_module(module, source, label)
+    }
+
+    fn compile_glsl(
+        source: &PreprocessedSource,
+        stage: ShaderStage,
+        preprocessor: &ShaderPreprocessor,
+        label: &str,
+    ) -> anyhow::Result<Self> {
+        let module = parse_glsl(source, stage.to_naga(), preprocessor.get_defines())
+            .map_err(|e| anyhow::anyhow!("[Shader]: {:#}", e))?;
+        Self::from_module(module, source, label)
+    }
+
+    /// Validates a parsed module and writes it as SPIR-V.
+    fn from_module(mut module: naga::Module, source: &PreprocessedSource, label: &str) -> anyhow::Result<Self> {
+        let words = write_spirv(&mut module, source, DEFAULT_DEBUG_LEVEL)
+            .map_err(|e| anyhow::anyhow!("[Shader]: {:#}", e))?;
+
+        let entry_points = module
+            .entry_points
+            .iter()
+            .filter_map(|entry_point| {
+                let stage = match entry_point.stage {
+                    naga::ShaderStage::Vertex => ShaderStage::Vertex,
+                    naga::ShaderStage::Fragment => ShaderStage::Fragment,
+                    naga::ShaderStage::Compute => return None,
+                };
+                Some((stage, entry_point.name.clone()))
+            })
+            .collect();
+
+        Ok(Self {
+            label: label.to_string(),
+            words,
+            entry_points,
+            reflection: Some(ShaderReflection::from_module(&module)),
+            path: None,
+            preprocessor: ShaderPreprocessor::default(),
+            sources: Vec::new(),
+        })
+    }
+}
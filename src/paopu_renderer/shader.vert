@@ -1,11 +1,12 @@
-
 // shader.vert
 #version 450
 
 layout(location=0) in vec3 a_position;
 layout(location=1) in vec2 a_tex_coords;
+layout(location=2) in vec4 a_color;
 
 layout(location=0) out vec2 v_tex_coords;
+layout(location=1) out vec4 v_color;
 
 layout(set=1, binding=0)
 uniform Uniforms {
@@ -14,5 +15,6 @@ uniform Uniforms {
 
 void main(){
 	v_tex_coords = a_tex_coords;
+	v_color = a_color;
 	gl_Position = u_view_projection * vec4(a_position, 1.0);
 }
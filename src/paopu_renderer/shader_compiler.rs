@@ -0,0 +1,98 @@
+// Shared with build.rs, so it only depends on std, anyhow, naga and the
+// preprocessor.
+use anyhow::Context;
+
+use super::shader_preprocessor::PreprocessedSource;
+
+// -------------------------------------------------------------------------------
+//                      - Shader Compiler -
+// -------------------------------------------------------------------------------
+
+/// How much debug information compiled shaders keep. No optimization is done
+/// at any level.
+/// 0 strips names and unused items, 1 keeps names and 2 also embeds the
+/// source for debuggers.
+pub const DEFAULT_DEBUG_LEVEL: u32 = 1;
+
+/// Parses WGSL, pointing errors at the file and line they are in.
+pub fn parse_wgsl(source: &PreprocessedSource) -> anyhow::Result<naga::Module> {
+    naga::front::wgsl::parse_str(&source.source)
+        .map_err(|e| anyhow::anyhow!("{}", format_error(source, e.location(&source.source), e.message())))
+}
+
+/// Parses GLSL of the given stage, pointing errors at the file and line
+/// they are in.
+pub fn parse_glsl(
+    source: &PreprocessedSource,
+    stage: naga::ShaderStage,
+    defines: &[(String, String)],
+) -> anyhow::Result<naga::Module> {
+    let options = naga::front::glsl::Options {
+        stage,
+        defines: defines.iter().cloned().collect(),
+    };
+    naga::front::glsl::Frontend::default()
+        .parse(&options, &source.source)
+        .map_err(|errors| {
+            let messages: Vec<String> = errors
+                .errors
+                .iter()
+                .map(|e| format_error(source, e.location(&source.source), &e.kind.to_string()))
+                .collect();
+            anyhow::anyhow!("{}", messages.join("\n"))
+        })
+}
+
+/// Validates a parsed module and writes it as SPIR-V, keeping as much debug
+/// information as `debug_level` asks for, see [`DEFAULT_DEBUG_LEVEL`].
+pub fn write_spirv(module: &mut naga::Module, source: &PreprocessedSource, debug_level: u32) -> anyhow::Result<Vec<u32>> {
+    if debug_level == 0 {
+        naga::compact::compact(module);
+    }
+
+    let info = naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::default(),
+    )
+    .validate(module)
+    .map_err(|e| anyhow::anyhow!("{}", format_error(source, e.location(&source.source), &error_chain(e.as_inner()))))?;
+
+    let file = &source.get_files()[0];
+    let mut options = naga::back::spv::Options::default();
+    // The shaders are written for wgpu's coordinate space already
+    options
+        .flags
+        .remove(naga::back::spv::WriterFlags::ADJUST_COORDINATE_SPACE);
+    options
+        .flags
+        .set(naga::back::spv::WriterFlags::DEBUG, debug_level >= 1);
+    if debug_level >= 2 {
+        options.debug_info = Some(naga::back::spv::DebugInfo {
+            source_code: &source.source,
+            file_name: file,
+        });
+    }
+    naga::back::spv::write_vec(module, &info, &options, None)
+        .with_context(|| format!("Unable to write {} as SPIR-V", file.display()))
+}
+
+/// Formats an error as `file:line:column: message`, pointing into the
+/// include the error is in.
+fn format_error(source: &PreprocessedSource, location: Option<naga::SourceLocation>, message: &str) -> String {
+    source.error_message(
+        location.map(|location| (location.line_number, location.line_position)),
+        message,
+    )
+}
+
+/// The error followed by its causes.
+fn error_chain(error: &dyn std::error::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    message
+}
@@ -0,0 +1,391 @@
+// Shared with build.rs, so it only depends on std and anyhow.
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+
+// -------------------------------------------------------------------------------
+//                      - Preprocessed Source -
+// -------------------------------------------------------------------------------
+
+/// A shader's source with its includes resolved, remembering which file and
+/// line every line came from so errors can point at them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PreprocessedSource {
+    pub source: String,
+    // Every file the source was read from, the shader itself first.
+    files: Vec<PathBuf>,
+    // The index of the file and the line every line came from.
+    origins: Vec<(usize, u32)>,
+}
+
+impl PreprocessedSource {
+    /// Wraps a source that wasn't read from a file, e.g. one embedded in
+    /// the game. `label` stands in for the file in errors.
+    pub fn unprocessed(source: &str, label: &str) -> Self {
+        let mut processed = Self::default();
+        processed.files.push(PathBuf::from(label));
+        for (index, line) in source.lines().enumerate() {
+            processed.push_line(line, 0, index as u32 + 1);
+        }
+        processed
+    }
+
+    /// Every file the source was read from, the shader itself first.
+    pub fn get_files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    /// The file and line a line of the source came from. Lines start at 1.
+    pub fn origin(&self, line: u32) -> Option<(&Path, u32)> {
+        let (file, line) = *self.origins.get((line as usize).checked_sub(1)?)?;
+        Some((&self.files[file], line))
+    }
+
+    /// Formats an error as `file:line:column: message`.
+    pub fn error_message(&self, location: Option<(u32, u32)>, message: &str) -> String {
+        match location.and_then(|(line, column)| Some((self.origin(line)?, column))) {
+            Some(((file, line), column)) => format!("{}:{}:{}: {}", file.display(), line, column, message),
+            None => format!("{}: {}", self.files[0].display(), message),
+        }
+    }
+
+    fn push_line(&mut self, line: &str, file: usize, line_number: u32) {
+        self.source.push_str(line);
+        self.source.push('\n');
+        self.origins.push((file, line_number));
+    }
+
+    /// Blanks the lines `#ifdef`, `#ifndef`, `#else` and `#endif` leave out,
+    /// and the directives themselves, so lines keep their numbers.
+    fn resolve_conditionals(&mut self, defines: &[(String, String)]) -> anyhow::Result<()> {
+        // Whether each open block is kept, and whether it reached its `#else`
+        let mut blocks: Vec<(bool, bool)> = Vec::new();
+        let mut source = String::with_capacity(self.source.len());
+        for (index, line) in self.source.lines().enumerate() {
+            let active = blocks.iter().all(|(kept, _)| *kept);
+            let mut words = line.split_whitespace();
+            let directive = words.next().unwrap_or("");
+            let name = words.next();
+            let location = Some((index as u32 + 1, 1));
+            match directive {
+                "#ifdef" | "#ifndef" => {
+                    let name = name.ok_or_else(|| {
+                        anyhow::anyhow!("{}", self.error_message(location, &format!("{} needs a name", directive)))
+                    })?;
+                    let defined = defines.iter().any(|(define, _)| define == name);
+                    blocks.push((defined == (directive == "#ifdef"), false));
+                }
+                "#else" => match blocks.last_mut() {
+                    Some((kept, seen_else)) if !*seen_else => {
+                        *kept = !*kept;
+                        *seen_else = true;
+                    }
+                    _ => anyhow::bail!("{}", self.error_message(location, "#else without #ifdef")),
+                },
+                "#endif" => {
+                    blocks.pop().ok_or_else(|| {
+                        anyhow::anyhow!("{}", self.error_message(location, "#endif without #ifdef"))
+                    })?;
+                }
+                _ if active => source.push_str(line),
+                _ => {}
+            }
+            source.push('\n');
+        }
+        if !blocks.is_empty() {
+            anyhow::bail!("{}", self.error_message(None, "#ifdef without #endif"));
+        }
+        self.source = source;
+        Ok(())
+    }
+}
+
+// -------------------------------------------------------------------------------
+//                      - Shader Preprocessor -
+// -------------------------------------------------------------------------------
+
+/// Resolves `#include`s and holds the defines a shader is compiled with.
+///
+/// `#include "file"` is looked up next to the including file, then in the
+/// include directories, and `#include <file>` only in the include
+/// directories. Files are included once per shader.
+///
+/// GLSL gets the defines as macros. WGSL has no preprocessor, so its
+/// `#ifdef`, `#ifndef`, `#else` and `#endif` are resolved here instead.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ShaderPreprocessor {
+    include_dirs: Vec<PathBuf>,
+    defines: Vec<(String, String)>,
+}
+
+impl ShaderPreprocessor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_include_dir<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.include_dirs.push(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Defines a macro, replacing its value if it's already defined.
+    pub fn with_define(mut self, name: &str, value: &str) -> Self {
+        self.defines.retain(|(define, _)| define != name);
+        self.defines.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn get_include_dirs(&self) -> &[PathBuf] {
+        &self.include_dirs
+    }
+
+    pub fn get_defines(&self) -> &[(String, String)] {
+        &self.defines
+    }
+
+    /// Reads a shader along with its includes.
+    pub fn process_file(&self, path: &Path) -> anyhow::Result<PreprocessedSource> {
+        let mut processed = PreprocessedSource::default();
+        let mut included = HashSet::new();
+        self.include_file(path, &mut processed, &mut included)?;
+        if path.extension().and_then(|extension| extension.to_str()) == Some("wgsl") {
+            processed.resolve_conditionals(&self.defines)?;
+        }
+        Ok(processed)
+    }
+
+    fn include_file(
+        &self,
+        path: &Path,
+        processed: &mut PreprocessedSource,
+        included: &mut HashSet<PathBuf>,
+    ) -> anyhow::Result<()> {
+        included.insert(path.canonicalize().unwrap_or_else(|_| path.to_path_buf()));
+        let source = std::fs::read_to_string(path)
+            .with_context(|| format!("Unable to read shader {}", path.display()))?;
+        let file = processed.files.len();
+        processed.files.push(path.to_path_buf());
+
+        for (index, line) in source.lines().enumerate() {
+            let line_number = index as u32 + 1;
+            let include = match line.trim_start().strip_prefix("#include") {
+                Some(include) => include.trim(),
+                None => {
+                    processed.push_line(line, file, line_number);
+                    continue;
+                }
+            };
+            let error = |message: String| anyhow::anyhow!("{}:{}: {}", path.display(), line_number, message);
+
+            let (name, relative) = if let Some(name) = include.strip_prefix('"').and_then(|name| name.strip_suffix('"')) {
+                (name, true)
+            } else if let Some(name) = include.strip_prefix('<').and_then(|name| name.strip_suffix('>')) {
+                (name, false)
+            } else {
+                return Err(error(format!("Expected \"file\" or <file> after #include, found {}", include)));
+            };
+            let relative_dir = if relative { path.parent() } else { None };
+            let resolved = relative_dir
+                .into_iter()
+                .chain(self.include_dirs.iter().map(|dir| dir.as_path()))
+                .map(|dir| dir.join(name))
+                .find(|candidate| candidate.is_file())
+                .ok_or_else(|| error(format!("Unable to find include {}", name)))?;
+
+            let canonical = resolved.canonicalize().unwrap_or_else(|_| resolved.clone());
+            if included.contains(&canonical) {
+                // Keeps the line numbers of the including file in sync
+                processed.push_line("", file, line_number);
+            } else {
+                self.include_file(&resolved, processed, included)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// -------------------------------------------------------------------------------
+//                      - Shader Manifest -
+// -------------------------------------------------------------------------------
+
+/// The most defines a shader can permute, its variants being numbered by a
+/// `u32` mask.
+pub const MAX_PERMUTE: usize = 31;
+
+/// The defines and variants of a shader listed in a [`ShaderManifest`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ShaderEntry {
+    pub file: String,
+    pub defines: Vec<(String, String)>,
+    /// Every combination of these is compiled as its own variant. At most
+    /// [`MAX_PERMUTE`].
+    pub permute: Vec<String>,
+}
+
+/// Build settings for a directory of shaders, e.g.
+///
+/// ```text
+/// include_dir = include
+/// debug_level = 1
+///
+/// [sprite.frag]
+/// define = MAX_LIGHTS=8
+/// permute = LIGHTING NORMAL_MAP
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShaderManifest {
+    /// Relative to the manifest.
+    pub include_dir: Option<PathBuf>,
+    /// How much debug information the shaders keep, no optimization is done.
+    /// 0 strips names and unused items, 1 keeps names and 2 also embeds the
+    /// source for debuggers.
+    pub debug_level: u32,
+    pub shaders: Vec<ShaderEntry>,
+}
+
+impl Default for ShaderManifest {
+    fn default() -> Self {
+        Self {
+            include_dir: None,
+            debug_level: 1,
+            shaders: Vec::new(),
+        }
+    }
+}
+
+impl ShaderManifest {
+    /// Loads a manifest, or the default settings if there is none.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        if !path.is_file() {
+            return Ok(Self::default());
+        }
+        let source = std::fs::read_to_string(path)
+            .with_context(|| format!("Unable to read shader manifest {}", path.display()))?;
+        let mut manifest = Self::parse(&source).map_err(|e| anyhow::anyhow!("{}:{}", path.display(), e))?;
+        if let (Some(include_dir), Some(parent)) = (manifest.include_dir.as_mut(), path.parent()) {
+            *include_dir = parent.join(&include_dir);
+        }
+        Ok(manifest)
+    }
+
+    /// Parses a manifest. Errors start with the line they are on.
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut manifest = Self::default();
+        for (index, line) in source.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(file) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+                manifest.shaders.push(ShaderEntry {
+                    file: file.trim().to_string(),
+                    ..ShaderEntry::default()
+                });
+                continue;
+            }
+
+            let (key, value) = match line.find('=') {
+                Some(split) => (line[..split].trim(), line[split + 1..].trim()),
+                None => return Err(format!("{}: Expected key = value, found {}", line_number, line)),
+            };
+            match (key, manifest.shaders.last_mut()) {
+                ("include_dir", None) => manifest.include_dir = Some(PathBuf::from(value)),
+                ("debug_level", None) => {
+                    manifest.debug_level = value
+                        .parse()
+                        .map_err(|_| format!("{}: Invalid debug_level {}", line_number, value))?
+                }
+                ("define", Some(shader)) => {
+                    for define in value.split_whitespace() {
+                        let (name, value) = match define.find('=') {
+                            Some(split) => (&define[..split], &define[split + 1..]),
+                            None => (define, ""),
+                        };
+                        shader.defines.push((name.to_string(), value.to_string()));
+                    }
+                }
+                ("permute", Some(shader)) => {
+                    shader.permute.extend(value.split_whitespace().map(|name| name.to_string()));
+                    if shader.permute.len() > MAX_PERMUTE {
+                        return Err(format!(
+                            "{}: {} permutes {} defines, at most {} are supported",
+                            line_number,
+                            shader.file,
+                            shader.permute.len(),
+                            MAX_PERMUTE
+                        ));
+                    }
+                }
+                _ => return Err(format!("{}: Unexpected {}", line_number, key)),
+            }
+        }
+        Ok(manifest)
+    }
+
+    /// The preprocessor for a variant of a shader, `file` being relative to
+    /// the manifest and `variant` naming its permutations, e.g.
+    /// `LIGHTING.NORMAL_MAP`, or empty for the base variant.
+    pub fn preprocessor(&self, file: &str, variant: &str) -> ShaderPreprocessor {
+        let mut preprocessor = ShaderPreprocessor::new();
+        if let Some(include_dir) = &self.include_dir {
+            preprocessor = preprocessor.with_include_dir(include_dir);
+        }
+        if let Some(shader) = self.shaders.iter().find(|shader| shader.file == file) {
+            for (name, value) in shader.defines.iter() {
+                preprocessor = preprocessor.with_define(name, value);
+            }
+        }
+        for name in variant.split('.').filter(|name| !name.is_empty()) {
+            preprocessor = preprocessor.with_define(name, "1");
+        }
+        preprocessor
+    }
+
+    /// The variants of a shader: the base one, named `""`, then every
+    /// combination of its permutations.
+    pub fn variants(&self, file: &str) -> Vec<String> {
+        let permute = match self.shaders.iter().find(|shader| shader.file == file) {
+            Some(shader) => &shader.permute[..],
+            None => &[],
+        };
+        (0..1u32 << permute.len())
+            .map(|mask| {
+                permute
+                    .iter()
+                    .enumerate()
+                    .filter(|(bit, _)| mask & (1 << bit) != 0)
+                    .map(|(_, name)| name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(".")
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ShaderManifest, MAX_PERMUTE};
+
+    fn permuting(count: usize) -> String {
+        let names: Vec<String> = (0..count).map(|index| format!("D{}", index)).collect();
+        format!("[a.frag]\npermute = {}\n", names.join(" "))
+    }
+
+    #[test]
+    fn variants_cover_every_permutation() {
+        let manifest = ShaderManifest::parse(&permuting(2)).unwrap();
+        assert_eq!(manifest.variants("a.frag"), vec!["", "D0", "D1", "D0.D1"]);
+        assert_eq!(manifest.variants("b.frag"), vec![""]);
+    }
+
+    #[test]
+    fn rejects_too_many_permutations() {
+        assert!(ShaderManifest::parse(&permuting(MAX_PERMUTE)).is_ok());
+        let error = ShaderManifest::parse(&permuting(MAX_PERMUTE + 1)).unwrap_err();
+        assert!(error.starts_with("2: a.frag permutes 32 defines"), "{}", error);
+    }
+}
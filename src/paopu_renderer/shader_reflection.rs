@@ -0,0 +1,164 @@
+// Shared with build.rs, so it only depends on std and naga.
+
+// -------------------------------------------------------------------------------
+//                      - Shader Reflection -
+// -------------------------------------------------------------------------------
+
+/// The kind of resource a binding holds.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum BindingType {
+    UniformBuffer,
+    StorageBuffer,
+    Texture,
+    Sampler,
+}
+
+/// A resource a shader binds.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ReflectedBinding {
+    pub group: u32,
+    pub binding: u32,
+    pub ty: BindingType,
+}
+
+/// The scalar type of a vertex input.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum VertexScalar {
+    Float,
+    Sint,
+    Uint,
+}
+
+/// An input of a vertex shader, e.g. a `vec3` at location 0.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct VertexInput {
+    pub location: u32,
+    pub scalar: VertexScalar,
+    pub components: u32,
+}
+
+/// The resources a shader binds and the inputs of its vertex entry point,
+/// for checking it against the layouts it's used with.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ShaderReflection {
+    /// Sorted by group then binding.
+    pub bindings: Vec<ReflectedBinding>,
+    /// Sorted by location, empty if there's no vertex entry point.
+    pub vertex_inputs: Vec<VertexInput>,
+}
+
+impl ShaderReflection {
+    pub fn from_module(module: &naga::Module) -> Self {
+        let mut bindings: Vec<ReflectedBinding> = module
+            .global_variables
+            .iter()
+            .filter_map(|(_, global)| {
+                let binding = global.binding.as_ref()?;
+                let ty = match (global.space, &module.types[global.ty].inner) {
+                    (naga::AddressSpace::Uniform, _) => BindingType::UniformBuffer,
+                    (naga::AddressSpace::Storage { .. }, _) => BindingType::StorageBuffer,
+                    (naga::AddressSpace::Handle, naga::TypeInner::Image { .. }) => BindingType::Texture,
+                    (naga::AddressSpace::Handle, naga::TypeInner::Sampler { .. }) => BindingType::Sampler,
+                    _ => return None,
+                };
+                Some(ReflectedBinding {
+                    group: binding.group,
+                    binding: binding.binding,
+                    ty,
+                })
+            })
+            .collect();
+        bindings.sort_by_key(|binding| (binding.group, binding.binding));
+
+        let mut vertex_inputs = Vec::new();
+        for entry_point in module.entry_points.iter() {
+            if entry_point.stage != naga::ShaderStage::Vertex {
+                continue;
+            }
+            for argument in entry_point.function.arguments.iter() {
+                match &module.types[argument.ty].inner {
+                    naga::TypeInner::Struct { members, .. } => {
+                        for member in members.iter() {
+                            vertex_inputs.extend(vertex_input(module, member.ty, member.binding.as_ref()));
+                        }
+                    }
+                    _ => vertex_inputs.extend(vertex_input(module, argument.ty, argument.binding.as_ref())),
+                }
+            }
+        }
+        vertex_inputs.sort_by_key(|input| input.location);
+        vertex_inputs.dedup();
+
+        Self {
+            bindings,
+            vertex_inputs,
+        }
+    }
+
+    pub fn get_binding(&self, group: u32, binding: u32) -> Option<&ReflectedBinding> {
+        self.bindings
+            .iter()
+            .find(|reflected| reflected.group == group && reflected.binding == binding)
+    }
+
+    /// Checks that every resource the shader binds is in `bindings` with the
+    /// same type, and that every vertex input is in `vertex_inputs`.
+    /// Fails with one line per mismatch.
+    pub fn validate(&self, bindings: &[ReflectedBinding], vertex_inputs: &[VertexInput]) -> Result<(), String> {
+        let mut errors = Vec::new();
+        for reflected in self.bindings.iter() {
+            match bindings
+                .iter()
+                .find(|binding| binding.group == reflected.group && binding.binding == reflected.binding)
+            {
+                Some(binding) if binding.ty == reflected.ty => {}
+                Some(binding) => errors.push(format!(
+                    "group {} binding {} is a {:?}, but the layout has a {:?}",
+                    reflected.group, reflected.binding, reflected.ty, binding.ty
+                )),
+                None => errors.push(format!(
+                    "group {} binding {} isn't in the layout",
+                    reflected.group, reflected.binding
+                )),
+            }
+        }
+        for input in self.vertex_inputs.iter() {
+            match vertex_inputs.iter().find(|vertex| vertex.location == input.location) {
+                Some(vertex) if vertex == input => {}
+                Some(vertex) => errors.push(format!(
+                    "vertex input {} is {:?}x{}, but the vertex has {:?}x{}",
+                    input.location, input.scalar, input.components, vertex.scalar, vertex.components
+                )),
+                None => errors.push(format!("vertex input {} isn't in the vertex", input.location)),
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+}
+
+fn vertex_input(module: &naga::Module, ty: naga::Handle<naga::Type>, binding: Option<&naga::Binding>) -> Option<VertexInput> {
+    let location = match binding? {
+        naga::Binding::Location { location, .. } => *location,
+        naga::Binding::BuiltIn(_) => return None,
+    };
+    let (scalar, components) = match module.types[ty].inner {
+        naga::TypeInner::Scalar(scalar) => (scalar, 1),
+        naga::TypeInner::Vector { size, scalar } => (scalar, size as u32),
+        _ => return None,
+    };
+    let scalar = match scalar.kind {
+        naga::ScalarKind::Float => VertexScalar::Float,
+        naga::ScalarKind::Sint => VertexScalar::Sint,
+        naga::ScalarKind::Uint => VertexScalar::Uint,
+        _ => return None,
+    };
+    Some(VertexInput {
+        location,
+        scalar,
+        components,
+    })
+}
@@ -0,0 +1,80 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+// -------------------------------------------------------------------------------
+//                      - Shader Watcher -
+// -------------------------------------------------------------------------------
+
+/// Watches shader sources for changes by polling their modification times,
+/// so shaders can be reloaded while the game runs.
+pub struct ShaderWatcher {
+    interval: Duration,
+    elapsed: Duration,
+    // The last modification time of every watched file, if it could be read.
+    files: HashMap<PathBuf, Option<SystemTime>>,
+}
+
+impl ShaderWatcher {
+    /// Checks the files every `interval`.
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            elapsed: Duration::from_secs(0),
+            files: HashMap::new(),
+        }
+    }
+
+    pub fn get_interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Starts watching a file. Files that are already watched are left as is.
+    pub fn watch<P: AsRef<Path>>(&mut self, path: P) {
+        let path = path.as_ref();
+        if !self.files.contains_key(path) {
+            self.files.insert(path.to_path_buf(), modified_time(path));
+        }
+    }
+
+    pub fn is_watching<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.files.contains_key(path.as_ref())
+    }
+
+    /// Advances the watcher, checking the files once the interval is up.
+    pub fn poll(&mut self, delta: Duration) -> Vec<PathBuf> {
+        self.elapsed += delta;
+        if self.elapsed < self.interval {
+            return Vec::new();
+        }
+        self.elapsed = Duration::from_secs(0);
+        self.check()
+    }
+
+    /// The files that changed since the last check.
+    pub fn check(&mut self) -> Vec<PathBuf> {
+        let mut changed = Vec::new();
+        for (path, last_modified) in self.files.iter_mut() {
+            let modified = modified_time(path);
+            // Files being rewritten can be briefly missing
+            if modified.is_some() && modified != *last_modified {
+                *last_modified = modified;
+                changed.push(path.clone());
+            }
+        }
+        changed.sort();
+        changed
+    }
+}
+
+impl Default for ShaderWatcher {
+    fn default() -> Self {
+        Self::new(Duration::from_millis(250))
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
@@ -0,0 +1,40 @@
+use crate::renderer::sprite::Sprite;
+
+// -------------------------------------------------------------------------
+//              - Software Cursor -
+// -------------------------------------------------------------------------
+
+/// A sprite the renderer draws at the mouse position on top of everything
+/// else, in place of the OS cursor. Hide the OS cursor with
+/// [`WindowState::set_cursor_visible`](crate::window::window::WindowState::set_cursor_visible).
+#[derive(Debug, Clone, PartialEq)]
+pub struct SoftwareCursor {
+    pub sprite: Sprite,
+    /// The point of the sprite that sits on the mouse position, in pixels
+    /// from the top left corner of its region.
+    pub hotspot: (f32, f32),
+    /// Whether the cursor is zoomed along with the world and snapped to
+    /// whole world pixels, so it matches pixel art sprites. Otherwise one
+    /// pixel of the cursor is one viewport unit.
+    pub scale_with_camera: bool,
+}
+
+impl SoftwareCursor {
+    pub fn new(sprite: Sprite) -> Self {
+        Self {
+            sprite,
+            hotspot: (0.0, 0.0),
+            scale_with_camera: true,
+        }
+    }
+
+    pub fn with_hotspot(mut self, x: f32, y: f32) -> Self {
+        self.hotspot = (x, y);
+        self
+    }
+
+    pub fn with_scale_with_camera(mut self, enabled: bool) -> Self {
+        self.scale_with_camera = enabled;
+        self
+    }
+}
@@ -0,0 +1,240 @@
+use std::time::Duration;
+
+use crate::{
+    core::events::WindowId,
+    renderer::{blend_mode::BlendMode, material::MaterialHandle, texture::TextureHandle},
+};
+
+// -------------------------------------------------------------------------
+//              - Rect -
+// -------------------------------------------------------------------------
+
+/// A rectangle in pixels. For texture regions the origin is the top left.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Rect {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+}
+
+// -------------------------------------------------------------------------
+//              - Render Layer -
+// -------------------------------------------------------------------------
+
+/// The layer a sprite is drawn in. Higher layers are drawn in front.
+///
+/// The named layers are spaced out so custom ones fit between them, e.g.
+/// `RenderLayer(RenderLayer::WORLD.0 + 1)` for effects above the world.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RenderLayer(pub i32);
+
+impl RenderLayer {
+    pub const BACKGROUND: RenderLayer = RenderLayer(-100);
+    pub const WORLD: RenderLayer = RenderLayer(0);
+    pub const FOREGROUND: RenderLayer = RenderLayer(100);
+    pub const UI: RenderLayer = RenderLayer(200);
+    /// In front of every other layer, e.g. for the software cursor.
+    pub const TOP: RenderLayer = RenderLayer(i32::MAX);
+}
+
+impl Default for RenderLayer {
+    fn default() -> Self {
+        RenderLayer::WORLD
+    }
+}
+
+// -------------------------------------------------------------------------
+//              - Sprite -
+// -------------------------------------------------------------------------
+
+/// Draws a texture, or a region of it, at the entity's transform.
+///
+/// Sprites are drawn by layer, then by z-index, then, in layers the
+/// [`Renderer`](crate::renderer::renderer::Renderer) y-sorts, from the top
+/// of the world down. Sprites that tie are drawn in the order they were
+/// submitted in, except that sprites sharing a texture, material and blend
+/// mode are drawn together when no sprite they'd move past overlaps them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sprite {
+    pub texture: TextureHandle,
+    /// The part of the texture to draw, or the whole texture if `None`.
+    pub region: Option<Rect>,
+    /// Multiplied with the texture color.
+    pub color: [f32; 4],
+    /// The point of the sprite that sits on the transform's origin,
+    /// from `(0.0, 0.0)` (bottom left) to `(1.0, 1.0)` (top right).
+    pub anchor: [f32; 2],
+    pub flip_x: bool,
+    pub flip_y: bool,
+    /// Draws the sprite with a material's shaders instead of the built-in ones.
+    pub material: Option<MaterialHandle>,
+    /// Overrides the material's blend mode, or [`BlendMode::Alpha`] if
+    /// there's no material.
+    pub blend_mode: Option<BlendMode>,
+    pub layer: RenderLayer,
+    /// Sprites with a higher z-index are drawn in front within a layer.
+    pub z_index: i32,
+}
+
+impl Sprite {
+    pub fn new(texture: TextureHandle) -> Self {
+        Self {
+            texture,
+            region: None,
+            color: [1.0, 1.0, 1.0, 1.0],
+            anchor: [0.5, 0.5],
+            flip_x: false,
+            flip_y: false,
+            material: None,
+            blend_mode: None,
+            layer: RenderLayer::default(),
+            z_index: 0,
+        }
+    }
+
+    pub fn with_region(mut self, region: Rect) -> Self {
+        self.region = Some(region);
+        self
+    }
+
+    pub fn with_color(mut self, color: [f32; 4]) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_material(mut self, material: MaterialHandle) -> Self {
+        self.material = Some(material);
+        self
+    }
+
+    pub fn with_blend_mode(mut self, blend_mode: BlendMode) -> Self {
+        self.blend_mode = Some(blend_mode);
+        self
+    }
+
+    pub fn with_layer(mut self, layer: RenderLayer) -> Self {
+        self.layer = layer;
+        self
+    }
+
+    pub fn with_z_index(mut self, z_index: i32) -> Self {
+        self.z_index = z_index;
+        self
+    }
+}
+
+/// Draws the entity's [`Sprite`] into another window instead of the
+/// primary one.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TargetWindow(pub WindowId);
+
+// -------------------------------------------------------------------------
+//              - Sprite Animation -
+// -------------------------------------------------------------------------
+
+/// Flips through texture regions of the entity's [`Sprite`] over time.
+/// Advanced by the renderer every frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpriteAnimation {
+    pub frames: Vec<Rect>,
+    pub frame_duration: Duration,
+    pub looping: bool,
+    pub playing: bool,
+    current_frame: usize,
+    timer: Duration,
+}
+
+impl SpriteAnimation {
+    pub fn new(frames: Vec<Rect>, frames_per_second: f32) -> Self {
+        Self {
+            frames,
+            frame_duration: Duration::from_secs_f32(1.0 / frames_per_second.max(f32::EPSILON)),
+            looping: true,
+            playing: true,
+            current_frame: 0,
+            timer: Duration::from_secs(0),
+        }
+    }
+
+    /// Builds an animation out of a sprite sheet laid out in rows of
+    /// `columns` frames of `frame_width` by `frame_height` pixels.
+    pub fn from_grid(
+        frame_width: f32,
+        frame_height: f32,
+        columns: usize,
+        frame_count: usize,
+        frames_per_second: f32,
+    ) -> Self {
+        let columns = columns.max(1);
+        let frames = (0..frame_count)
+            .map(|i| {
+                Rect::new(
+                    (i % columns) as f32 * frame_width,
+                    (i / columns) as f32 * frame_height,
+                    frame_width,
+                    frame_height,
+                )
+            })
+            .collect();
+        Self::new(frames, frames_per_second)
+    }
+
+    pub fn with_looping(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    /// Moves the animation forward by `delta`.
+    pub fn advance(&mut self, delta: Duration) {
+        if !self.playing || self.frames.is_empty() || self.frame_duration == Duration::from_secs(0)
+        {
+            return;
+        }
+
+        self.timer += delta;
+        while self.timer >= self.frame_duration {
+            self.timer -= self.frame_duration;
+            if self.current_frame + 1 < self.frames.len() {
+                self.current_frame += 1;
+            } else if self.looping {
+                self.current_frame = 0;
+            } else {
+                self.playing = false;
+                self.timer = Duration::from_secs(0);
+                break;
+            }
+        }
+    }
+
+    /// Restarts the animation from the first frame.
+    pub fn reset(&mut self) {
+        self.current_frame = 0;
+        self.timer = Duration::from_secs(0);
+        self.playing = true;
+    }
+
+    pub fn current_frame_index(&self) -> usize {
+        self.current_frame
+    }
+
+    pub fn current_frame(&self) -> Option<Rect> {
+        self.frames.get(self.current_frame).copied()
+    }
+
+    /// Returns true once a non-looping animation reached its last frame.
+    pub fn is_finished(&self) -> bool {
+        !self.looping && !self.playing && self.current_frame + 1 >= self.frames.len()
+    }
+}
@@ -0,0 +1,324 @@
+use std::{cmp::Ordering, ops::Range};
+
+use cgmath::{Matrix4, Vector4};
+
+use crate::renderer::{
+    blend_mode::BlendMode,
+    material::MaterialHandle,
+    sprite::{Rect, RenderLayer, Sprite},
+    texture::TextureHandle,
+    vertex_buffer_descriptor::Vertex,
+};
+
+// -------------------------------------------------------------------------
+//              - Sprite Batch -
+// -------------------------------------------------------------------------
+
+/// A run of consecutive sprites that share a texture, material and blend
+/// mode and can be drawn with a single draw call.
+pub struct Batch {
+    pub texture: TextureHandle,
+    pub material: Option<MaterialHandle>,
+    pub blend_mode: BlendMode,
+    pub indices: Range<u32>,
+}
+
+/// A submitted sprite's quad, waiting to be sorted.
+struct Quad {
+    layer: RenderLayer,
+    z_index: i32,
+    // The y the quad is sorted by in y-sorted layers.
+    sort_y: f32,
+    texture: TextureHandle,
+    material: Option<MaterialHandle>,
+    blend_mode: BlendMode,
+    // Top left, top right, bottom right, bottom left
+    vertices: [Vertex; 4],
+}
+
+/// Collects the sprites submitted during a frame, then sorts them into one
+/// vertex and index buffer.
+#[derive(Default)]
+pub struct SpriteBatch {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+    pub batches: Vec<Batch>,
+    quads: Vec<Quad>,
+}
+
+impl SpriteBatch {
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+        self.indices.clear();
+        self.batches.clear();
+        self.quads.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.quads.is_empty()
+    }
+
+    /// The texture and material of every submitted quad.
+    pub fn sources(&self) -> impl Iterator<Item = (TextureHandle, Option<MaterialHandle>)> + '_ {
+        self.quads.iter().map(|quad| (quad.texture, quad.material))
+    }
+
+    /// Adds a quad for `sprite` transformed by `model`, blended with
+    /// `blend_mode`. `texture_size` is the size of the sprite's texture in
+    /// pixels, and `sort_y` the height it's sorted at in y-sorted layers.
+    pub fn push(
+        &mut self,
+        model: Matrix4<f32>,
+        sprite: &Sprite,
+        texture_size: (u32, u32),
+        blend_mode: BlendMode,
+        sort_y: f32,
+    ) {
+        let (texture_width, texture_height) = (texture_size.0 as f32, texture_size.1 as f32);
+        if texture_width == 0.0 || texture_height == 0.0 {
+            return;
+        }
+        let region = sprite
+            .region
+            .unwrap_or_else(|| Rect::new(0.0, 0.0, texture_width, texture_height));
+
+        // Local corners, with the anchor sitting on the origin
+        let left = -sprite.anchor[0] * region.width;
+        let bottom = -sprite.anchor[1] * region.height;
+        let right = left + region.width;
+        let top = bottom + region.height;
+
+        let mut u0 = region.x / texture_width;
+        let mut v0 = region.y / texture_height;
+        let mut u1 = (region.x + region.width) / texture_width;
+        let mut v1 = (region.y + region.height) / texture_height;
+        if sprite.flip_x {
+            std::mem::swap(&mut u0, &mut u1);
+        }
+        if sprite.flip_y {
+            std::mem::swap(&mut v0, &mut v1);
+        }
+
+        let corner = |x: f32, y: f32, u: f32, v: f32| {
+            let position = model * Vector4::new(x, y, 0.0, 1.0);
+            Vertex {
+                position: [position.x, position.y, position.z],
+                tex_coords: [u, v],
+                color: sprite.color,
+            }
+        };
+
+        self.quads.push(Quad {
+            layer: sprite.layer,
+            z_index: sprite.z_index,
+            sort_y,
+            texture: sprite.texture,
+            material: sprite.material,
+            blend_mode,
+            vertices: [
+                corner(left, top, u0, v0),
+                corner(right, top, u1, v0),
+                corner(right, bottom, u1, v1),
+                corner(left, bottom, u0, v1),
+            ],
+        });
+    }
+
+    /// Sorts the submitted quads by layer, z-index and height in
+    /// `y_sorted_layers` (lower drawn in front), then writes them into the
+    /// vertices, indices and batches.
+    /// Quads that tie keep their submission order, except that a quad is
+    /// moved back to join the last quad sharing its texture, material and
+    /// blend mode when it overlaps none of the quads it is moved past.
+    pub fn build(&mut self, y_sorted_layers: &[RenderLayer]) {
+        self.vertices.clear();
+        self.indices.clear();
+        self.batches.clear();
+
+        let quads = &self.quads;
+        let mut sorted: Vec<usize> = (0..quads.len()).collect();
+        // Stable, so ties keep their submission order
+        sorted.sort_by(|&a, &b| draw_order(&quads[a], &quads[b], y_sorted_layers));
+        let mut order = Vec::with_capacity(sorted.len());
+        for ties in sorted.chunk_by(|&a, &b| draw_order(&quads[a], &quads[b], y_sorted_layers) == Ordering::Equal) {
+            group_ties(quads, ties, &mut order);
+        }
+
+        for quad in order.into_iter().map(|index| &quads[index]) {
+            let first = self.vertices.len() as u32;
+            self.vertices.extend_from_slice(&quad.vertices);
+
+            let start = self.indices.len() as u32;
+            self.indices
+                .extend_from_slice(&[first, first + 1, first + 2, first + 2, first + 3, first]);
+            let end = self.indices.len() as u32;
+
+            match self.batches.last_mut() {
+                Some(batch)
+                    if batch.texture == quad.texture
+                        && batch.material == quad.material
+                        && batch.blend_mode == quad.blend_mode =>
+                {
+                    batch.indices.end = end
+                }
+                _ => self.batches.push(Batch {
+                    texture: quad.texture,
+                    material: quad.material,
+                    blend_mode: quad.blend_mode,
+                    indices: start..end,
+                }),
+            }
+        }
+    }
+}
+
+/// The order quads are drawn in, regardless of how they batch.
+fn draw_order(a: &Quad, b: &Quad, y_sorted_layers: &[RenderLayer]) -> Ordering {
+    a.layer
+        .cmp(&b.layer)
+        .then(a.z_index.cmp(&b.z_index))
+        .then_with(|| {
+            if a.layer == b.layer && y_sorted_layers.contains(&a.layer) {
+                b.sort_y.total_cmp(&a.sort_y)
+            } else {
+                Ordering::Equal
+            }
+        })
+}
+
+/// Appends quads that tie in draw order to `order`, in submission order
+/// but with each moved back into the last group it batches with, unless
+/// it overlaps a group in between and would be drawn behind it.
+fn group_ties(quads: &[Quad], ties: &[usize], order: &mut Vec<usize>) {
+    // The quads of every group, and the bounds they cover
+    let mut groups: Vec<(Vec<usize>, Bounds)> = Vec::new();
+    for &index in ties {
+        let quad = &quads[index];
+        let bounds = Bounds::of(quad);
+        let mut target = None;
+        for (group, (members, group_bounds)) in groups.iter().enumerate().rev() {
+            if quads[members[0]].batches_with(quad) {
+                target = Some(group);
+                break;
+            }
+            if group_bounds.overlaps(&bounds) {
+                break;
+            }
+        }
+        match target {
+            Some(group) => {
+                let (members, group_bounds) = &mut groups[group];
+                members.push(index);
+                *group_bounds = group_bounds.union(&bounds);
+            }
+            None => groups.push((vec![index], bounds)),
+        }
+    }
+    order.extend(groups.into_iter().flat_map(|(members, _)| members));
+}
+
+impl Quad {
+    fn batches_with(&self, other: &Quad) -> bool {
+        self.texture == other.texture && self.material == other.material && self.blend_mode == other.blend_mode
+    }
+}
+
+/// The screen-aligned box around one or more quads.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Bounds {
+    min: [f32; 2],
+    max: [f32; 2],
+}
+
+impl Bounds {
+    fn of(quad: &Quad) -> Self {
+        let mut bounds = Self {
+            min: [f32::INFINITY; 2],
+            max: [f32::NEG_INFINITY; 2],
+        };
+        for vertex in quad.vertices.iter() {
+            for axis in 0..2 {
+                bounds.min[axis] = bounds.min[axis].min(vertex.position[axis]);
+                bounds.max[axis] = bounds.max[axis].max(vertex.position[axis]);
+            }
+        }
+        bounds
+    }
+
+    fn union(&self, other: &Bounds) -> Self {
+        Self {
+            min: [self.min[0].min(other.min[0]), self.min[1].min(other.min[1])],
+            max: [self.max[0].max(other.max[0]), self.max[1].max(other.max[1])],
+        }
+    }
+
+    /// Boxes that only touch, e.g. neighbouring tiles, don't overlap.
+    fn overlaps(&self, other: &Bounds) -> bool {
+        (0..2).all(|axis| self.min[axis] < other.max[axis] && other.min[axis] < self.max[axis])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{Matrix4, Vector3};
+
+    use crate::renderer::{
+        blend_mode::BlendMode,
+        sprite::{RenderLayer, Sprite},
+        sprite_batch::SpriteBatch,
+        texture::TextureHandle,
+    };
+
+    // Pushes a 4x4 sprite centered on `x`, `y`, tagged with `id` in its red channel
+    fn push(batch: &mut SpriteBatch, id: u32, texture: usize, x: f32, y: f32) {
+        let sprite = Sprite::new(TextureHandle(texture)).with_color([id as f32, 0.0, 0.0, 1.0]);
+        let model = Matrix4::from_translation(Vector3::new(x, y, 0.0));
+        batch.push(model, &sprite, (4, 4), BlendMode::Alpha, y);
+    }
+
+    fn drawn_ids(batch: &SpriteBatch) -> Vec<u32> {
+        batch.vertices.chunks(4).map(|quad| quad[0].color[0] as u32).collect()
+    }
+
+    #[test]
+    fn overlapping_ties_keep_their_submission_order() {
+        let mut batch = SpriteBatch::default();
+        push(&mut batch, 1, 1, 0.0, 0.0);
+        push(&mut batch, 2, 0, 1.0, 0.0);
+        push(&mut batch, 3, 1, 2.0, 0.0);
+        batch.build(&[]);
+        assert_eq!(drawn_ids(&batch), vec![1, 2, 3]);
+        assert_eq!(batch.batches.len(), 3);
+    }
+
+    #[test]
+    fn separate_ties_are_grouped_by_texture() {
+        let mut batch = SpriteBatch::default();
+        push(&mut batch, 1, 1, 0.0, 0.0);
+        push(&mut batch, 2, 0, 10.0, 0.0);
+        // Only touches the second's edge, so it moves past it
+        push(&mut batch, 3, 1, 6.0, 0.0);
+        // Overlaps the second, so stays after it
+        push(&mut batch, 4, 1, 11.0, 0.0);
+        batch.build(&[]);
+        assert_eq!(drawn_ids(&batch), vec![1, 3, 2, 4]);
+        assert_eq!(batch.batches.len(), 3);
+    }
+
+    #[test]
+    fn sorts_by_layer_z_index_and_y() {
+        let mut batch = SpriteBatch::default();
+        push(&mut batch, 1, 0, 0.0, 0.0);
+        batch.quads[0].layer = RenderLayer::UI;
+        push(&mut batch, 2, 0, 0.0, 5.0);
+        push(&mut batch, 3, 0, 0.0, 1.0);
+        push(&mut batch, 4, 0, 0.0, 9.0);
+        batch.quads[3].z_index = 1;
+        push(&mut batch, 5, 0, 0.0, 3.0);
+        batch.build(&[]);
+        assert_eq!(drawn_ids(&batch), vec![2, 3, 5, 4, 1]);
+        // Higher up is drawn first
+        batch.build(&[RenderLayer::WORLD]);
+        assert_eq!(drawn_ids(&batch), vec![2, 5, 3, 4, 1]);
+    }
+}
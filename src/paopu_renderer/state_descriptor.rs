@@ -1,69 +1,476 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+};
+
 use winit::window::Window;
 
 use wgpu::util::DeviceExt;
 
-use crate::renderer::{
-    camera::{Camera, CameraController},
-    texture::Texture,
-    uniforms::{UniformStaging, Uniforms},
-    vertex_buffer_descriptor::Vertex,
+use crate::{
+    core::events::WindowId,
+    renderer::{
+        blend_mode::BlendMode,
+        camera::Camera2D,
+        capture::FrameReadback,
+        material::{MaterialGpu, MaterialHandle},
+        present_mode::PresentMode,
+        render_target::RenderTargetHandle,
+        shader::{Shader, ShaderStage},
+        shader_preprocessor::{ShaderManifest, ShaderPreprocessor},
+        shader_reflection::{BindingType, ReflectedBinding, ShaderReflection},
+        sprite_batch::SpriteBatch,
+        texture::Texture,
+        uniforms::{UniformStaging, Uniforms},
+        vertex_buffer_descriptor::Vertex,
+    },
 };
 
-const VERTICES: &[Vertex] = &[
-    // Top left
-    Vertex {
-        position: [-0.5, 0.5, 0.0],
-        tex_coords: [0.0, 0.0],
-    },
-    // Top Right
-    Vertex {
-        position: [0.5, 0.5, 0.0],
-        tex_coords: [1.0, 0.0],
+// The initial number of sprites the vertex and index buffers can hold.
+const INITIAL_SPRITE_CAPACITY: usize = 256;
+
+/// The texture format window frames are rendered in.
+pub const WINDOW_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Bgra8UnormSrgb;
+
+// The reflections of the shaders build.rs compiled.
+mod builtin_reflection {
+    use crate::renderer::shader_reflection::*;
+
+    include!(concat!(env!("OUT_DIR"), "/shader_reflection.rs"));
+}
+
+/// The texture format offscreen frames are rendered in, matching
+/// [`image::RgbaImage`] so frames can be read back without conversion.
+pub const OFFSCREEN_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+// -------------------------------------------------------
+//              - Surface State -
+// -------------------------------------------------------
+
+/// What the renderer draws into.
+enum RenderSurface {
+    /// A window's swap chain.
+    Window {
+        surface: wgpu::Surface,
+        swap_chain_descriptor: wgpu::SwapChainDescriptor,
+        swap_chain: wgpu::SwapChain,
     },
-    // Bottom Right
-    Vertex {
-        position: [0.5, -0.5, 0.0],
-        tex_coords: [1.0, 1.0],
+    /// A texture that frames can be copied back from.
+    Offscreen {
+        texture: wgpu::Texture,
+        view: wgpu::TextureView,
     },
-    // Bottom Left
-    Vertex {
-        position: [-0.5, -0.5, 0.0],
-        tex_coords: [0.0, 1.0],
+    /// A render target's texture, sampled by other draws.
+    Target {
+        view: wgpu::TextureView,
+        format: wgpu::TextureFormat,
     },
-];
+}
 
-const INDICES: &[u16] = &[0, 1, 2, 2, 3, 0];
+/// The texture a single frame is drawn into.
+pub enum Frame {
+    Window(wgpu::SwapChainFrame),
+    Offscreen,
+}
+
+/// A single window's (or the offscreen texture's) swap chain and camera.
+pub struct SurfaceState {
+    render_surface: RenderSurface,
+    pub size: winit::dpi::PhysicalSize<u32>,
+    // Overrides the state's clear color, e.g. for render targets.
+    pub clear_color: Option<wgpu::Color>,
+    uniform_staging: UniformStaging,
+    uniforms: Uniforms,
+    uniform_buffer: wgpu::Buffer,
+    pub uniform_bind_group: wgpu::BindGroup,
+    // Physical pixels per viewport unit.
+    viewport_scale: f32,
+    // Window frames are drawn into this when captured.
+    capture_texture: Option<(wgpu::Texture, wgpu::TextureView, winit::dpi::PhysicalSize<u32>)>,
+}
+
+impl SurfaceState {
+    fn new(
+        device: &wgpu::Device,
+        uniform_bind_group_layout: &wgpu::BindGroupLayout,
+        render_surface: RenderSurface,
+        size: winit::dpi::PhysicalSize<u32>,
+    ) -> Self {
+        let camera = Camera2D::new(size.width as f32, size.height as f32);
+
+        let mut uniforms = Uniforms::new();
+        let uniform_staging = UniformStaging::new(camera);
+        uniform_staging.update_uniforms(&mut uniforms);
+        //uniforms.update_view_projection(&camera);
+
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Uniform Buffer"),
+            contents: bytemuck::cast_slice(&[uniforms]),
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
+
+        let uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: uniform_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer {
+                    buffer: &uniform_buffer,
+                    // Base offset of the buffer. For bindings with dynamic == true, this
+                    // offset will be added to the dynamic offset provided in
+                    // [RenderPass::set_bind_group].
+                    // The offset has to be aling to [BIND_BUFFER_ALIGNMENT].
+                    offset: 0,
+                    // Size of the binding, or [None] for using the rest of the buffer.
+                    size: None,
+                },
+            }],
+            label: Some("Uniform Bind Group"),
+        });
+
+        Self {
+            render_surface,
+            size,
+            clear_color: None,
+            uniform_staging,
+            uniforms,
+            uniform_buffer,
+            uniform_bind_group,
+            viewport_scale: 1.0,
+            capture_texture: None,
+        }
+    }
+
+    pub fn update(&mut self, queue: &wgpu::Queue) {
+        self.uniform_staging.update_uniforms(&mut self.uniforms);
+        queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[self.uniforms]),
+        );
+    }
+
+    /// Recreates the swap chain with the new present mode.
+    /// Offscreen surfaces and render targets have no swap chain and ignore it.
+    pub fn set_present_mode(&mut self, device: &wgpu::Device, present_mode: PresentMode) {
+        if let RenderSurface::Window {
+            surface,
+            swap_chain_descriptor,
+            swap_chain,
+        } = &mut self.render_surface
+        {
+            swap_chain_descriptor.present_mode = present_mode.into();
+            *swap_chain = device.create_swap_chain(surface, swap_chain_descriptor);
+        }
+    }
+
+    /// Render targets keep the size they were created with.
+    pub fn resize(&mut self, device: &wgpu::Device, new_size: winit::dpi::PhysicalSize<u32>) {
+        if let RenderSurface::Target { .. } = self.render_surface {
+            return;
+        }
+        self.size = new_size;
+        match &mut self.render_surface {
+            RenderSurface::Window {
+                surface,
+                swap_chain_descriptor,
+                swap_chain,
+            } => {
+                swap_chain_descriptor.width = new_size.width;
+                swap_chain_descriptor.height = new_size.height;
+                *swap_chain = device.create_swap_chain(surface, swap_chain_descriptor);
+            }
+            RenderSurface::Offscreen { texture, view } => {
+                let (new_texture, new_view) = create_offscreen_texture(device, new_size);
+                *texture = new_texture;
+                *view = new_view;
+            }
+            RenderSurface::Target { .. } => {}
+        }
+        self.apply_viewport();
+    }
+
+    /// Sets how many physical pixels one viewport unit covers, e.g. the
+    /// window's scale factor for a viewport in logical pixels.
+    pub fn set_viewport_scale(&mut self, viewport_scale: f32) {
+        self.viewport_scale = viewport_scale;
+        self.apply_viewport();
+    }
+
+    pub fn get_viewport_scale(&self) -> f32 {
+        self.viewport_scale
+    }
+
+    fn apply_viewport(&mut self) {
+        self.uniform_staging.set_camera_viewport(
+            self.size.width as f32 / self.viewport_scale,
+            self.size.height as f32 / self.viewport_scale,
+        );
+    }
+
+    pub fn camera(&self) -> &Camera2D {
+        &self.uniform_staging.camera
+    }
+
+    pub fn camera_mut(&mut self) -> &mut Camera2D {
+        &mut self.uniform_staging.camera
+    }
+
+    pub fn is_offscreen(&self) -> bool {
+        matches!(self.render_surface, RenderSurface::Offscreen { .. })
+    }
+
+    /// The format frames are rendered in.
+    pub fn frame_format(&self) -> wgpu::TextureFormat {
+        match &self.render_surface {
+            RenderSurface::Window {
+                swap_chain_descriptor,
+                ..
+            } => swap_chain_descriptor.format,
+            RenderSurface::Offscreen { .. } => OFFSCREEN_FORMAT,
+            RenderSurface::Target { format, .. } => *format,
+        }
+    }
+
+    /// Gets the texture the next frame is drawn into.
+    pub fn get_current_frame(&self) -> Result<Frame, wgpu::SwapChainError> {
+        match &self.render_surface {
+            RenderSurface::Window { swap_chain, .. } => {
+                Ok(Frame::Window(swap_chain.get_current_frame()?))
+            }
+            RenderSurface::Offscreen { .. } | RenderSurface::Target { .. } => Ok(Frame::Offscreen),
+        }
+    }
+
+    /// The view to render `frame` through.
+    pub fn frame_view<'a>(&'a self, frame: &'a Frame) -> &'a wgpu::TextureView {
+        match (frame, &self.render_surface) {
+            (Frame::Window(frame), _) => &frame.output.view,
+            (Frame::Offscreen, RenderSurface::Offscreen { view, .. }) => view,
+            (Frame::Offscreen, RenderSurface::Target { view, .. }) => view,
+            (Frame::Offscreen, RenderSurface::Window { .. }) => {
+                panic!("[StateDescriptor]: Offscreen frame used with a window surface")
+            }
+        }
+    }
+
+    /// Copies the last rendered offscreen frame back to the CPU.
+    /// Fails for windows, as swap chain textures can't be read.
+    pub fn read_frame(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> anyhow::Result<image::RgbaImage> {
+        let texture = match &self.render_surface {
+            RenderSurface::Offscreen { texture, .. } => texture,
+            RenderSurface::Window { .. } | RenderSurface::Target { .. } => {
+                anyhow::bail!("[StateDescriptor]: Only offscreen frames can be read back")
+            }
+        };
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Readback Encoder"),
+        });
+        let mut readback =
+            FrameReadback::new(device, &mut encoder, texture, self.size, OFFSCREEN_FORMAT);
+        queue.submit(std::iter::once(encoder.finish()));
+        readback.map();
+        readback.read(device)
+    }
+
+    /// Makes sure there is a texture a frame can be captured from.
+    /// Swap chain textures can't be copied, so windows draw the
+    /// frame a second time into a capture texture.
+    pub fn prepare_capture(&mut self, device: &wgpu::Device) {
+        if !matches!(self.render_surface, RenderSurface::Window { .. }) {
+            return;
+        }
+        let recreate = match &self.capture_texture {
+            Some((_, _, size)) => *size != self.size,
+            None => true,
+        };
+        if recreate {
+            let (texture, view) = create_render_texture(
+                device,
+                self.size,
+                self.frame_format(),
+                Some("Capture Texture"),
+            );
+            self.capture_texture = Some((texture, view, self.size));
+        }
+    }
+
+    /// The texture to capture the frame from, and the view to draw into it
+    /// through if it isn't the texture the frame was drawn into.
+    /// Call [`SurfaceState::prepare_capture`] first.
+    pub fn capture_texture(&self) -> Option<(&wgpu::Texture, Option<&wgpu::TextureView>)> {
+        match &self.render_surface {
+            RenderSurface::Offscreen { texture, .. } => Some((texture, None)),
+            RenderSurface::Window { .. } => self
+                .capture_texture
+                .as_ref()
+                .map(|(texture, view, _)| (texture, Some(view))),
+            RenderSurface::Target { .. } => None,
+        }
+    }
+}
+
+// -------------------------------------------------------
+//              - Pipelines -
+// -------------------------------------------------------
+
+/// The shaders a pipeline draws sprites with.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum PipelineShader {
+    Sprite,
+    /// Draws textures holding signed distance fields, e.g. SDF glyphs.
+    Sdf,
+    Material(MaterialHandle),
+}
+
+/// The variant of the built-in fragment shaders that outputs premultiplied
+/// colors.
+pub const PREMULTIPLY_VARIANT: &str = "PREMULTIPLY";
+
+/// The shaders built into the engine.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum BuiltinShader {
+    SpriteVertex,
+    SpriteFragment,
+    SdfFragment,
+}
+
+impl BuiltinShader {
+    pub const ALL: [BuiltinShader; 3] = [
+        BuiltinShader::SpriteVertex,
+        BuiltinShader::SpriteFragment,
+        BuiltinShader::SdfFragment,
+    ];
+
+    /// The GLSL source the shader is compiled from, for hot reloading it.
+    pub fn source_path(self) -> PathBuf {
+        Self::source_dir().join(self.file_name())
+    }
+
+    /// The directory of the built-in shaders' sources and their manifest.
+    pub fn source_dir() -> PathBuf {
+        [env!("CARGO_MANIFEST_DIR"), "src", "paopu_renderer"].iter().collect()
+    }
+
+    /// Whether the built-in shaders' sources can be edited, i.e. the engine
+    /// is built from a writable checkout rather than e.g. a registry copy.
+    pub fn has_editable_sources() -> bool {
+        std::fs::metadata(Self::manifest_path()).is_ok()
+            && std::fs::metadata(Self::source_dir())
+                .map(|metadata| metadata.is_dir() && !metadata.permissions().readonly())
+                .unwrap_or(false)
+    }
+
+    /// The build settings of the built-in shaders, see [`ShaderManifest`].
+    pub fn manifest_path() -> PathBuf {
+        Self::source_dir().join("shaders.manifest")
+    }
+
+    /// The variants of the shader the renderer draws with, see
+    /// [`ShaderManifest::variants`]. Fragment shaders have a
+    /// [`PREMULTIPLY_VARIANT`] for [`BlendMode::premultiplies`].
+    pub fn variants(self) -> &'static [&'static str] {
+        match self {
+            BuiltinShader::SpriteVertex => &[""],
+            BuiltinShader::SpriteFragment | BuiltinShader::SdfFragment => &["", PREMULTIPLY_VARIANT],
+        }
+    }
+
+    /// The preprocessor build.rs compiles a variant of the shader with.
+    pub fn preprocessor(self, variant: &str) -> anyhow::Result<ShaderPreprocessor> {
+        let manifest = ShaderManifest::load(&Self::manifest_path())?;
+        Ok(manifest.preprocessor(self.file_name(), variant))
+    }
+
+    /// Compiles the shader from its source, as build.rs does.
+    pub fn load(self) -> anyhow::Result<Shader> {
+        self.load_variant("")
+    }
+
+    /// Compiles a variant of the shader from its source, as build.rs does.
+    pub fn load_variant(self, variant: &str) -> anyhow::Result<Shader> {
+        Shader::load_with(self.source_path(), &self.preprocessor(variant)?)
+    }
+
+    /// The shader's source and its includes. Just the source if they can't
+    /// be resolved, e.g. while an include is being written.
+    pub fn sources(self) -> Vec<PathBuf> {
+        self.preprocessor("")
+            .and_then(|preprocessor| preprocessor.process_file(&self.source_path()))
+            .map(|source| source.get_files().to_vec())
+            .unwrap_or_else(|_| vec![self.source_path()])
+    }
+
+    /// The reflection build.rs emitted for the shader.
+    pub fn reflection(self) -> Option<ShaderReflection> {
+        builtin_reflection::builtin_reflections()
+            .into_iter()
+            .find(|(file, variant, _)| *file == self.file_name() && variant.is_empty())
+            .map(|(_, _, reflection)| reflection)
+    }
+
+    fn file_name(self) -> &'static str {
+        match self {
+            BuiltinShader::SpriteVertex => "shader.vert",
+            BuiltinShader::SpriteFragment => "shader.frag",
+            BuiltinShader::SdfFragment => "sdf.frag",
+        }
+    }
+
+    pub fn stage(self) -> ShaderStage {
+        match self {
+            BuiltinShader::SpriteVertex => ShaderStage::Vertex,
+            BuiltinShader::SpriteFragment | BuiltinShader::SdfFragment => ShaderStage::Fragment,
+        }
+    }
+}
+
+/// Identifies a cached pipeline.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct PipelineKey {
+    pub shader: PipelineShader,
+    pub format: wgpu::TextureFormat,
+    pub blend_mode: BlendMode,
+}
 
 // -------------------------------------------------------
 //              - State Descriptor -
 // -------------------------------------------------------
 
+/// The GPU state shared by every window: the device, pipelines, and
+/// sprite buffers, plus a [`SurfaceState`] per window.
 pub struct StateDescriptor {
-    surface: wgpu::Surface,
+    instance: wgpu::Instance,
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
-    swap_chain_descriptor: wgpu::SwapChainDescriptor,
-    pub swap_chain: wgpu::SwapChain,
-    pub size: winit::dpi::PhysicalSize<u32>,
     pub clear_color: wgpu::Color,
-    pub render_pipeline: wgpu::RenderPipeline,
+    // The built-in shader modules and their entry points.
+    sprite_vertex: (wgpu::ShaderModule, String),
+    sprite_fragment: (wgpu::ShaderModule, String),
+    sdf_fragment: (wgpu::ShaderModule, String),
+    // The variants for blend modes that blend premultiplied colors.
+    sprite_fragment_premultiplied: (wgpu::ShaderModule, String),
+    sdf_fragment_premultiplied: (wgpu::ShaderModule, String),
+    pipeline_layout: wgpu::PipelineLayout,
+    // Built on first use, one per set of shaders, target format and blend mode.
+    pipelines: HashMap<PipelineKey, wgpu::RenderPipeline>,
     pub vertex_buffer: wgpu::Buffer,
     pub index_buffer: wgpu::Buffer,
-    pub num_indicies: u32,
-    pub camera_controller: CameraController,
-    uniform_staging: UniformStaging,
-    uniforms: Uniforms,
-    uniform_buffer: wgpu::Buffer,
-    pub uniform_bind_group: wgpu::BindGroup,
-    diffuse_texture: Texture,
-    pub diffuse_bind_group: wgpu::BindGroup,
+    // The number of sprites the vertex and index buffers can hold.
+    sprite_capacity: usize,
+    uniform_bind_group_layout: wgpu::BindGroupLayout,
+    texture_bind_group_layout: wgpu::BindGroupLayout,
+    surfaces: BTreeMap<WindowId, SurfaceState>,
+    targets: BTreeMap<RenderTargetHandle, SurfaceState>,
 }
 
 impl StateDescriptor {
     // Creating some of the wgpu types requires async
-    pub async fn new(window: &Window) -> Self {
-        let size = window.inner_size();
-
+    pub async fn new(window: &Window, present_mode: PresentMode) -> Self {
         // The instance is a handle to our GPU
         // BackendBit::PRIMARY => Vulkan + Metal + DX12 + Browser WebGPU
         let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
@@ -75,32 +482,54 @@ impl StateDescriptor {
             })
             .await
             .unwrap();
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    label: Some("Device"),
-                    features: wgpu::Features::empty(),
-                    limits: wgpu::Limits::default(),
-                },
-                None, // Trace Path
-            )
-            .await
-            .unwrap();
+        let (device, queue) = request_device(&adapter).await.unwrap();
 
-        let swap_chain_descriptor = wgpu::SwapChainDescriptor {
-            usage: wgpu::TextureUsage::RENDER_ATTACHMENT,
-            format: wgpu::TextureFormat::Bgra8UnormSrgb,
-            width: size.width,
-            height: size.height,
-            present_mode: wgpu::PresentMode::Fifo,
-        };
+        let mut state = Self::from_device(instance, device, queue, WINDOW_FORMAT);
+        let render_surface =
+            create_window_surface(&state.device, surface, window.inner_size(), present_mode);
+        state.insert_surface(WindowId::PRIMARY, render_surface, window.inner_size());
+        state
+    }
 
-        let swap_chain = device.create_swap_chain(&surface, &swap_chain_descriptor);
+    /// Creates a state that renders into an offscreen texture instead of a
+    /// window, e.g. on machines without a display or GPU.
+    /// Software adapters (e.g. lavapipe) are used if no hardware one is found.
+    pub async fn new_offscreen(width: u32, height: u32) -> anyhow::Result<Self> {
+        let size = winit::dpi::PhysicalSize::new(width.max(1), height.max(1));
 
-        let diffuse_bytes = include_bytes!("../../assets/s_mob_01_idle.png");
-        let diffuse_texture =
-            Texture::from_bytes(&device, &queue, diffuse_bytes, "mob sprite").unwrap();
+        let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+        let adapter = match instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::LowPower,
+                compatible_surface: None,
+            })
+            .await
+        {
+            Some(adapter) => adapter,
+            // Software adapters aren't always returned by request_adapter
+            None => instance
+                .enumerate_adapters(wgpu::BackendBit::all())
+                .max_by_key(|adapter| adapter.get_info().device_type == wgpu::DeviceType::Cpu)
+                .ok_or_else(|| anyhow::anyhow!("[StateDescriptor]: No adapter found for offscreen rendering"))?,
+        };
+        let (device, queue) = request_device(&adapter).await?;
 
+        let mut state = Self::from_device(instance, device, queue, OFFSCREEN_FORMAT);
+        let (texture, view) = create_offscreen_texture(&state.device, size);
+        state.insert_surface(
+            WindowId::PRIMARY,
+            RenderSurface::Offscreen { texture, view },
+            size,
+        );
+        Ok(state)
+    }
+
+    fn from_device(
+        instance: wgpu::Instance,
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        format: wgpu::TextureFormat,
+    ) -> Self {
         let texture_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 entries: &[
@@ -111,7 +540,7 @@ impl StateDescriptor {
                             multisampled: false,
                             view_dimension: wgpu::TextureViewDimension::D2,
                             //sample_type: wgpu::TextureSampleType::Uint,
-                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
                         },
                         count: None,
                     },
@@ -120,7 +549,7 @@ impl StateDescriptor {
                         visibility: wgpu::ShaderStage::FRAGMENT,
                         ty: wgpu::BindingType::Sampler {
                             comparison: false,
-                            filtering: false,
+                            filtering: true,
                         },
                         count: None,
                     },
@@ -128,47 +557,6 @@ impl StateDescriptor {
                 label: Some("Texture Bind Group Layout"),
             });
 
-        let diffuse_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &texture_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&diffuse_texture.view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&diffuse_texture.sampler),
-                },
-            ],
-            label: Some("Diffuse Bind Group"),
-        });
-
-        let camera = Camera {
-            // Position the camera 1 unit up and 2 units back
-            eye: (0.0, 0.0, 10.0).into(),
-            // Look at the origin
-            target: (0.0, 0.0, 0.0).into(),
-            // Up direction
-            up: cgmath::Vector3::unit_y(),
-            aspect: swap_chain_descriptor.width as f32 / swap_chain_descriptor.height as f32,
-            fovy: 45.0,
-            znear: 0.1,
-            zfar: 100.0,
-        };
-
-        let camera_controller = CameraController::new(0.2);
-
-        let mut uniforms = Uniforms::new();
-        let uniform_staging = UniformStaging::new(camera);
-        uniform_staging.update_uniforms(&mut uniforms);
-        //uniforms.update_view_projection(&camera);
-
-        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Uniform Buffer"),
-            contents: bytemuck::cast_slice(&[uniforms]),
-            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
-        });
-
         let uniform_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 entries: &[wgpu::BindGroupLayoutEntry {
@@ -186,126 +574,476 @@ impl StateDescriptor {
                 label: Some("Uniform Bind Group Layout"),
             });
 
-        let uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &uniform_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: wgpu::BindingResource::Buffer {
-                    buffer: &uniform_buffer,
-                    // Base offset of the buffer. For bindings with dynamic == true, this
-                    // offset will be added to the dynamic offset provided in
-                    // [RenderPass::set_bind_group].
-                    // The offset has to be aling to [BIND_BUFFER_ALIGNMENT].
-                    offset: 0,
-                    // Size of the binding, or [None] for using the rest of the buffer.
-                    size: None,
-                },
-            }],
-            label: Some("Uniform Bind Group"),
-        });
-
         let clear_color = wgpu::Color::BLACK;
 
+        for builtin in BuiltinShader::ALL.iter().copied() {
+            if let Some(Err(e)) = builtin
+                .reflection()
+                .map(|reflection| reflection.validate(&sprite_bindings(), &Vertex::reflected_inputs()))
+            {
+                eprintln!("[StateDescriptor]: {:?} doesn't match the sprite layout:\n{}", builtin, e);
+            }
+        }
+
         let vs_module = device.create_shader_module(&wgpu::include_spirv!("shader.vert.spv"));
-        let fs_module = device.create_shader_module(&wgpu::include_spirv!("shader.frag.spv"));
+        let sprite_fs_module = device.create_shader_module(&wgpu::include_spirv!("shader.frag.spv"));
+        let sdf_fs_module = device.create_shader_module(&wgpu::include_spirv!("sdf.frag.spv"));
+        let sprite_premultiplied_fs_module =
+            device.create_shader_module(&wgpu::include_spirv!("shader.frag.PREMULTIPLY.spv"));
+        let sdf_premultiplied_fs_module =
+            device.create_shader_module(&wgpu::include_spirv!("sdf.frag.PREMULTIPLY.spv"));
 
-        let render_pipline_layout =
+        let pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("Render Pipeline Layout"),
                 bind_group_layouts: &[&texture_bind_group_layout, &uniform_bind_group_layout],
                 push_constant_ranges: &[],
             });
 
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Core Pipeline"),
-            layout: Some(&render_pipline_layout),
-            vertex: wgpu::VertexState {
-                module: &vs_module,
-                entry_point: "main",
-                buffers: &[Vertex::descriptor()],
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &fs_module,
-                entry_point: "main",
-                targets: &[wgpu::ColorTargetState {
-                    format: swap_chain_descriptor.format,
-                    alpha_blend: wgpu::BlendState::REPLACE,
-                    color_blend: wgpu::BlendState::REPLACE,
-                    write_mask: wgpu::ColorWrite::ALL,
-                }],
-            }),
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            depth_stencil: None,
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: Some(wgpu::IndexFormat::Uint16),
-                front_face: wgpu::FrontFace::Cw,
-                cull_mode: wgpu::CullMode::Back,
-                polygon_mode: wgpu::PolygonMode::Fill,
-            },
-        });
-
-        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Vertex Buffer"),
-            contents: bytemuck::cast_slice(VERTICES),
-            usage: wgpu::BufferUsage::VERTEX,
-        });
-
-        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Index Buffer"),
-            contents: bytemuck::cast_slice(INDICES),
-            usage: wgpu::BufferUsage::INDEX,
-        });
-
-        let num_indicies = INDICES.len() as u32;
+        let sprite_capacity = INITIAL_SPRITE_CAPACITY;
+        let (vertex_buffer, index_buffer) = create_sprite_buffers(&device, sprite_capacity);
 
-        Self {
-            surface,
+        let mut state = Self {
+            instance,
             device,
             queue,
-            swap_chain_descriptor,
-            swap_chain,
-            size,
             clear_color,
-            render_pipeline,
+            sprite_vertex: (vs_module, "main".to_string()),
+            sprite_fragment: (sprite_fs_module, "main".to_string()),
+            sdf_fragment: (sdf_fs_module, "main".to_string()),
+            sprite_fragment_premultiplied: (sprite_premultiplied_fs_module, "main".to_string()),
+            sdf_fragment_premultiplied: (sdf_premultiplied_fs_module, "main".to_string()),
+            pipeline_layout,
+            pipelines: HashMap::new(),
             vertex_buffer,
             index_buffer,
-            num_indicies,
-            camera_controller,
-            uniform_staging,
-            uniforms,
-            uniform_buffer,
-            uniform_bind_group,
-            diffuse_texture,
-            diffuse_bind_group,
+            sprite_capacity,
+            uniform_bind_group_layout,
+            texture_bind_group_layout,
+            surfaces: BTreeMap::new(),
+            targets: BTreeMap::new(),
+        };
+        for &shader in [PipelineShader::Sprite, PipelineShader::Sdf].iter() {
+            let blend_mode = BlendMode::default();
+            state.prepare_pipeline(PipelineKey { shader, format, blend_mode }, None);
         }
+        state
     }
 
-    pub fn update(&mut self) {
-        self.camera_controller
-            .update_camera(&mut self.uniform_staging.camera);
-        //self.uniform_staging.model_rotation += cgmath::Deg(2.0);
-        self.uniform_staging.update_uniforms(&mut self.uniforms);
-        self.queue.write_buffer(
-            &self.uniform_buffer,
-            0,
-            bytemuck::cast_slice(&[self.uniforms]),
+    fn insert_surface(
+        &mut self,
+        id: WindowId,
+        render_surface: RenderSurface,
+        size: winit::dpi::PhysicalSize<u32>,
+    ) {
+        let surface = SurfaceState::new(
+            &self.device,
+            &self.uniform_bind_group_layout,
+            render_surface,
+            size,
         );
+        self.surfaces.insert(id, surface);
     }
 
-    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
-        self.size = new_size;
-        self.swap_chain_descriptor.width = new_size.width;
-        self.swap_chain_descriptor.height = new_size.height;
-        self.swap_chain = self
-            .device
-            .create_swap_chain(&self.surface, &self.swap_chain_descriptor);
-        self.uniform_staging.set_camera_aspect(
-            self.swap_chain_descriptor.width as f32 / self.swap_chain_descriptor.height as f32,
+    /// Creates a swap chain for another window, sharing the device,
+    /// pipeline and textures with the existing ones.
+    pub fn add_window(&mut self, id: WindowId, window: &Window, present_mode: PresentMode) {
+        let surface = unsafe { self.instance.create_surface(window) };
+        let render_surface =
+            create_window_surface(&self.device, surface, window.inner_size(), present_mode);
+        self.insert_surface(id, render_surface, window.inner_size());
+    }
+
+    pub fn remove_window(&mut self, id: WindowId) {
+        self.surfaces.remove(&id);
+    }
+
+    pub fn surface(&self, id: WindowId) -> Option<&SurfaceState> {
+        self.surfaces.get(&id)
+    }
+
+    pub fn surface_mut(&mut self, id: WindowId) -> Option<&mut SurfaceState> {
+        self.surfaces.get_mut(&id)
+    }
+
+    /// The windows that have a surface, in order of their IDs.
+    pub fn window_ids(&self) -> impl Iterator<Item = WindowId> + '_ {
+        self.surfaces.keys().copied()
+    }
+
+    /// Writes the window's camera to its uniform buffer.
+    pub fn update_surface(&mut self, id: WindowId) {
+        if let Some(surface) = self.surfaces.get_mut(&id) {
+            surface.update(&self.queue);
+        }
+    }
+
+    /// Adds a surface drawing into a render target's `texture`, with its
+    /// own camera.
+    pub fn add_render_target(
+        &mut self,
+        handle: RenderTargetHandle,
+        texture: &Texture,
+        format: wgpu::TextureFormat,
+        size: winit::dpi::PhysicalSize<u32>,
+        clear_color: wgpu::Color,
+    ) {
+        let view = texture.texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut target = SurfaceState::new(
+            &self.device,
+            &self.uniform_bind_group_layout,
+            RenderSurface::Target { view, format },
+            size,
         );
+        target.clear_color = Some(clear_color);
+        self.targets.insert(handle, target);
+    }
+
+    pub fn target(&self, handle: RenderTargetHandle) -> Option<&SurfaceState> {
+        self.targets.get(&handle)
+    }
+
+    pub fn target_mut(&mut self, handle: RenderTargetHandle) -> Option<&mut SurfaceState> {
+        self.targets.get_mut(&handle)
+    }
+
+    /// Writes the render target's camera to its uniform buffer.
+    pub fn update_target(&mut self, handle: RenderTargetHandle) {
+        if let Some(target) = self.targets.get_mut(&handle) {
+            target.update(&self.queue);
+        }
+    }
+
+    /// Makes sure the window's next frame can be captured.
+    pub fn prepare_capture(&mut self, id: WindowId) {
+        if let Some(surface) = self.surfaces.get_mut(&id) {
+            surface.prepare_capture(&self.device);
+        }
+    }
+
+    /// Recreates every swap chain with the new present mode.
+    pub fn set_present_mode(&mut self, present_mode: PresentMode) {
+        for surface in self.surfaces.values_mut() {
+            surface.set_present_mode(&self.device, present_mode);
+        }
+    }
+
+    pub fn resize(&mut self, id: WindowId, new_size: winit::dpi::PhysicalSize<u32>) {
+        if let Some(surface) = self.surfaces.get_mut(&id) {
+            surface.resize(&self.device, new_size);
+        }
+    }
+
+    /// Copies the last rendered frame of an offscreen surface back to the CPU.
+    pub fn read_frame(&self, id: WindowId) -> anyhow::Result<image::RgbaImage> {
+        match self.surfaces.get(&id) {
+            Some(surface) => surface.read_frame(&self.device, &self.queue),
+            None => anyhow::bail!("[StateDescriptor]: No surface for {:?}", id),
+        }
+    }
+
+    /// Builds the pipeline for `key` if it isn't cached yet. Material
+    /// pipelines need the material's GPU state.
+    pub(crate) fn prepare_pipeline(&mut self, key: PipelineKey, material: Option<&MaterialGpu>) {
+        if self.pipelines.contains_key(&key) {
+            return;
+        }
+        let sprite_vertex = (&self.sprite_vertex.0, self.sprite_vertex.1.as_str());
+        let (sprite_fragment, sdf_fragment) = if key.blend_mode.premultiplies() {
+            (&self.sprite_fragment_premultiplied, &self.sdf_fragment_premultiplied)
+        } else {
+            (&self.sprite_fragment, &self.sdf_fragment)
+        };
+        let (layout, vertex, fragment, label) = match (key.shader, material) {
+            (PipelineShader::Sprite, _) => (
+                &self.pipeline_layout,
+                sprite_vertex,
+                (&sprite_fragment.0, sprite_fragment.1.as_str()),
+                "Core Pipeline",
+            ),
+            (PipelineShader::Sdf, _) => (
+                &self.pipeline_layout,
+                sprite_vertex,
+                (&sdf_fragment.0, sdf_fragment.1.as_str()),
+                "SDF Pipeline",
+            ),
+            (PipelineShader::Material(_), Some(material)) => (
+                &material.pipeline_layout,
+                match &material.vertex {
+                    Some((module, entry_point)) => (module, entry_point.as_str()),
+                    None => sprite_vertex,
+                },
+                (&material.fragment.0, material.fragment.1.as_str()),
+                "Material Pipeline",
+            ),
+            (PipelineShader::Material(handle), None) => {
+                eprintln!("[StateDescriptor]: {:?} has no GPU state to build a pipeline with", handle);
+                return;
+            }
+        };
+        let pipeline = create_sprite_pipeline(&self.device, layout, vertex, fragment, key, label);
+        self.pipelines.insert(key, pipeline);
+    }
+
+    /// The cached pipeline for `key`, see [`StateDescriptor::prepare_pipeline`].
+    pub fn pipeline(&self, key: &PipelineKey) -> Option<&wgpu::RenderPipeline> {
+        self.pipelines.get(key)
+    }
+
+    /// Drops the cached pipelines of a material, e.g. after its shaders
+    /// were reloaded. They are rebuilt the next time it is drawn.
+    pub fn remove_material_pipelines(&mut self, material: MaterialHandle) {
+        self.pipelines
+            .retain(|key, _| key.shader != PipelineShader::Material(material));
+    }
+
+    /// Replaces a variant of a built-in shader, see [`BuiltinShader::variants`],
+    /// rebuilding the pipelines using it the next time they are drawn with.
+    /// Fails, keeping the current shader, if it has no entry point for the
+    /// built-in's stage or doesn't match the sprite layout.
+    pub fn set_builtin_shader(&mut self, builtin: BuiltinShader, variant: &str, shader: &Shader) -> anyhow::Result<()> {
+        if !builtin.variants().contains(&variant) {
+            anyhow::bail!("[StateDescriptor]: {:?} has no variant {:?}", builtin, variant);
+        }
+        let entry_point = shader.get_entry_point(builtin.stage()).ok_or_else(|| {
+            anyhow::anyhow!("[StateDescriptor]: {} has no {:?} entry point", shader.get_label(), builtin.stage())
+        })?;
+        validate_layout(shader, &sprite_bindings())?;
+        let module = (shader.create_module(&self.device), entry_point.to_string());
+        let premultiplied = variant == PREMULTIPLY_VARIANT;
+        match builtin {
+            BuiltinShader::SpriteVertex => {
+                self.sprite_vertex = module;
+                // Materials without their own vertex shader use it too
+                self.pipelines.clear();
+            }
+            BuiltinShader::SpriteFragment => {
+                if premultiplied {
+                    self.sprite_fragment_premultiplied = module;
+                } else {
+                    self.sprite_fragment = module;
+                }
+                self.pipelines.retain(|key, _| {
+                    key.shader != PipelineShader::Sprite || key.blend_mode.premultiplies() != premultiplied
+                });
+            }
+            BuiltinShader::SdfFragment => {
+                if premultiplied {
+                    self.sdf_fragment_premultiplied = module;
+                } else {
+                    self.sdf_fragment = module;
+                }
+                self.pipelines.retain(|key, _| {
+                    key.shader != PipelineShader::Sdf || key.blend_mode.premultiplies() != premultiplied
+                });
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn texture_bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.texture_bind_group_layout
+    }
+
+    pub(crate) fn uniform_bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.uniform_bind_group_layout
+    }
+
+    /// Creates the bind group a texture is sampled through.
+    pub fn create_texture_bind_group(&self, texture: &Texture) -> wgpu::BindGroup {
+        self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.texture_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&texture.sampler),
+                },
+            ],
+            label: Some("Texture Bind Group"),
+        })
+    }
+
+    /// Uploads the sprite batch's geometry, growing the buffers if needed.
+    /// The buffers are shared between windows, so each window's batch has
+    /// to be submitted before the next one is written.
+    pub fn write_sprite_batch(&mut self, batch: &SpriteBatch) {
+        let sprite_count = batch.vertices.len() / 4;
+        if sprite_count > self.sprite_capacity {
+            self.sprite_capacity = sprite_count.next_power_of_two();
+            let (vertex_buffer, index_buffer) =
+                create_sprite_buffers(&self.device, self.sprite_capacity);
+            self.vertex_buffer = vertex_buffer;
+            self.index_buffer = index_buffer;
+        }
+
+        self.queue
+            .write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&batch.vertices));
+        self.queue
+            .write_buffer(&self.index_buffer, 0, bytemuck::cast_slice(&batch.indices));
+    }
+}
+
+/// The bindings of the sprite pipeline: the texture and sampler at group 0
+/// and the camera uniform at group 1.
+pub(crate) fn sprite_bindings() -> Vec<ReflectedBinding> {
+    vec![
+        ReflectedBinding {
+            group: 0,
+            binding: 0,
+            ty: BindingType::Texture,
+        },
+        ReflectedBinding {
+            group: 0,
+            binding: 1,
+            ty: BindingType::Sampler,
+        },
+        ReflectedBinding {
+            group: 1,
+            binding: 0,
+            ty: BindingType::UniformBuffer,
+        },
+    ]
+}
+
+/// Checks a shader only uses `bindings` and the sprite vertex's attributes.
+/// Shaders that weren't reflected, e.g. SPIR-V, pass.
+pub(crate) fn validate_layout(shader: &Shader, bindings: &[ReflectedBinding]) -> anyhow::Result<()> {
+    match shader.get_reflection() {
+        Some(reflection) => reflection
+            .validate(bindings, &Vertex::reflected_inputs())
+            .map_err(|e| anyhow::anyhow!("[StateDescriptor]: {} doesn't match its layout:\n{}", shader.get_label(), e)),
+        None => Ok(()),
+    }
+}
+
+async fn request_device(adapter: &wgpu::Adapter) -> Result<(wgpu::Device, wgpu::Queue), wgpu::RequestDeviceError> {
+    adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                label: Some("Device"),
+                features: wgpu::Features::empty(),
+                limits: wgpu::Limits::default(),
+            },
+            None, // Trace Path
+        )
+        .await
+}
+
+fn create_window_surface(
+    device: &wgpu::Device,
+    surface: wgpu::Surface,
+    size: winit::dpi::PhysicalSize<u32>,
+    present_mode: PresentMode,
+) -> RenderSurface {
+    let swap_chain_descriptor = wgpu::SwapChainDescriptor {
+        usage: wgpu::TextureUsage::RENDER_ATTACHMENT,
+        format: WINDOW_FORMAT,
+        width: size.width,
+        height: size.height,
+        present_mode: present_mode.into(),
+    };
+
+    let swap_chain = device.create_swap_chain(&surface, &swap_chain_descriptor);
+
+    RenderSurface::Window {
+        surface,
+        swap_chain_descriptor,
+        swap_chain,
     }
 }
+
+fn create_offscreen_texture(
+    device: &wgpu::Device,
+    size: winit::dpi::PhysicalSize<u32>,
+) -> (wgpu::Texture, wgpu::TextureView) {
+    create_render_texture(device, size, OFFSCREEN_FORMAT, Some("Offscreen Texture"))
+}
+
+/// Creates a texture that can be rendered into and copied from.
+fn create_render_texture(
+    device: &wgpu::Device,
+    size: winit::dpi::PhysicalSize<u32>,
+    format: wgpu::TextureFormat,
+    label: Option<&str>,
+) -> (wgpu::Texture, wgpu::TextureView) {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label,
+        size: wgpu::Extent3d {
+            width: size.width,
+            height: size.height,
+            depth: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsage::RENDER_ATTACHMENT | wgpu::TextureUsage::COPY_SRC,
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    (texture, view)
+}
+
+fn create_sprite_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    vertex: (&wgpu::ShaderModule, &str),
+    fragment: (&wgpu::ShaderModule, &str),
+    key: PipelineKey,
+    label: &str,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: vertex.0,
+            entry_point: vertex.1,
+            buffers: &[Vertex::descriptor()],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: fragment.0,
+            entry_point: fragment.1,
+            targets: &[wgpu::ColorTargetState {
+                format: key.format,
+                alpha_blend: key.blend_mode.alpha_blend(),
+                color_blend: key.blend_mode.color_blend(),
+                write_mask: wgpu::ColorWrite::ALL,
+            }],
+        }),
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        depth_stencil: None,
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Cw,
+            // Flipped sprites have their winding reversed, so nothing is culled
+            cull_mode: wgpu::CullMode::None,
+            polygon_mode: wgpu::PolygonMode::Fill,
+        },
+    })
+}
+
+fn create_sprite_buffers(device: &wgpu::Device, sprite_capacity: usize) -> (wgpu::Buffer, wgpu::Buffer) {
+    let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Vertex Buffer"),
+        size: (sprite_capacity * 4 * std::mem::size_of::<Vertex>()) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    let index_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Index Buffer"),
+        size: (sprite_capacity * 6 * std::mem::size_of::<u32>()) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsage::INDEX | wgpu::BufferUsage::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    (vertex_buffer, index_buffer)
+}
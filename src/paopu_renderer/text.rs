@@ -0,0 +1,366 @@
+use crate::renderer::{
+    font::{BitmapFont, FontHandle},
+    sprite::{Rect, RenderLayer},
+};
+
+// -------------------------------------------------------------------------
+//              - Text -
+// -------------------------------------------------------------------------
+
+/// How the lines of a [`Text`] are aligned.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum TextAlignment {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+/// A run of text drawn in one color.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextSection {
+    pub text: String,
+    pub color: [f32; 4],
+}
+
+/// Draws a string with a font at the entity's transform. The top left
+/// corner of the first line sits on the transform's origin.
+///
+/// Each section can have its own color, down to single glyphs. The font
+/// can be a bitmap font or a vector font, drawn at `font_size`.
+///
+/// # Example
+///
+/// ```ignore
+/// let font = renderer.load_font("assets/fonts/pixel.fnt")?;
+/// app.world.spawn((
+///     Transform2D::default(),
+///     Text::new(font, "Score: ")
+///         .with_section("100", [1.0, 0.8, 0.0, 1.0])
+///         .with_scale(2.0),
+/// ));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Text {
+    pub font: FontHandle,
+    pub sections: Vec<TextSection>,
+    pub alignment: TextAlignment,
+    /// Lines are wrapped at word boundaries to fit this width, in world units.
+    /// Lines are aligned within this width if set, or around the origin otherwise.
+    pub max_width: Option<f32>,
+    /// Integer scales keep pixel fonts crisp.
+    pub scale: f32,
+    /// The size vector fonts are drawn at, in pixels before scaling.
+    /// Bitmap fonts are drawn at the size they were generated at.
+    pub font_size: f32,
+    /// The glyphs are drawn in this layer, sorted as a whole at the text's origin.
+    pub layer: RenderLayer,
+    pub z_index: i32,
+}
+
+impl Text {
+    pub fn new(font: FontHandle, text: &str) -> Self {
+        Self {
+            font,
+            sections: vec![TextSection {
+                text: text.to_string(),
+                color: [1.0, 1.0, 1.0, 1.0],
+            }],
+            alignment: TextAlignment::Left,
+            max_width: None,
+            scale: 1.0,
+            font_size: 16.0,
+            layer: RenderLayer::default(),
+            z_index: 0,
+        }
+    }
+
+    /// Sets the color of every section.
+    pub fn with_color(mut self, color: [f32; 4]) -> Self {
+        for section in self.sections.iter_mut() {
+            section.color = color;
+        }
+        self
+    }
+
+    /// Appends text in another color.
+    pub fn with_section(mut self, text: &str, color: [f32; 4]) -> Self {
+        self.sections.push(TextSection {
+            text: text.to_string(),
+            color,
+        });
+        self
+    }
+
+    pub fn with_alignment(mut self, alignment: TextAlignment) -> Self {
+        self.alignment = alignment;
+        self
+    }
+
+    pub fn with_max_width(mut self, max_width: f32) -> Self {
+        self.max_width = Some(max_width);
+        self
+    }
+
+    pub fn with_scale(mut self, scale: f32) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn with_font_size(mut self, font_size: f32) -> Self {
+        self.font_size = font_size;
+        self
+    }
+
+    pub fn with_layer(mut self, layer: RenderLayer) -> Self {
+        self.layer = layer;
+        self
+    }
+
+    pub fn with_z_index(mut self, z_index: i32) -> Self {
+        self.z_index = z_index;
+        self
+    }
+
+    /// Replaces the text with a single section in the first section's color.
+    pub fn set_text(&mut self, text: &str) {
+        let color = self
+            .sections
+            .first()
+            .map(|section| section.color)
+            .unwrap_or([1.0, 1.0, 1.0, 1.0]);
+        self.sections = vec![TextSection {
+            text: text.to_string(),
+            color,
+        }];
+    }
+
+    /// The text of every section joined together.
+    pub fn get_text(&self) -> String {
+        self.sections.iter().map(|section| section.text.as_str()).collect()
+    }
+}
+
+// -------------------------------------------------------------------------
+//              - Layout -
+// -------------------------------------------------------------------------
+
+/// A glyph placed by [`layout_text`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PositionedGlyph {
+    pub page: usize,
+    /// The glyph's area on its page texture.
+    pub region: Rect,
+    /// The glyph's top left corner relative to the text's origin, in
+    /// unscaled font pixels with y pointing down.
+    pub position: (f32, f32),
+    pub color: [f32; 4],
+}
+
+/// The glyphs of a text and the size they cover.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TextLayout {
+    pub glyphs: Vec<PositionedGlyph>,
+    /// The widest line's width, in unscaled font pixels.
+    pub width: f32,
+    pub height: f32,
+}
+
+/// What line breaking needs to know about a font.
+pub(crate) trait FontMetrics {
+    fn advance(&self, c: char) -> f32;
+    fn kerning(&self, first: char, second: char) -> f32;
+    fn line_height(&self) -> f32;
+}
+
+impl FontMetrics for BitmapFont {
+    fn advance(&self, c: char) -> f32 {
+        self.glyph(c).map(|glyph| glyph.advance).unwrap_or(0.0)
+    }
+
+    fn kerning(&self, first: char, second: char) -> f32 {
+        BitmapFont::kerning(self, first, second)
+    }
+
+    fn line_height(&self) -> f32 {
+        self.line_height
+    }
+}
+
+/// A character placed by [`place_chars`].
+pub(crate) struct PlacedChar {
+    pub c: char,
+    pub color: [f32; 4],
+    /// The pen position at the top of the character's line.
+    pub position: (f32, f32),
+}
+
+/// The characters of a text broken into aligned lines.
+pub(crate) struct CharLayout {
+    pub chars: Vec<PlacedChar>,
+    pub width: f32,
+    pub height: f32,
+}
+
+/// Breaks the text into lines and places its glyphs.
+/// Positions are kept on whole pixels so pixel fonts stay crisp.
+pub fn layout_text(font: &BitmapFont, text: &Text) -> TextLayout {
+    let scale = if text.scale > 0.0 { text.scale } else { 1.0 };
+    let chars = place_chars(font, text, text.max_width.map(|max_width| max_width / scale));
+
+    let glyphs = chars
+        .chars
+        .iter()
+        .filter_map(|placed| {
+            let glyph = font.glyph(placed.c)?;
+            if glyph.region.width <= 0.0 || glyph.region.height <= 0.0 {
+                return None;
+            }
+            Some(PositionedGlyph {
+                page: glyph.page,
+                region: glyph.region,
+                position: (
+                    (placed.position.0 + glyph.offset.0).floor(),
+                    (placed.position.1 + glyph.offset.1).floor(),
+                ),
+                color: placed.color,
+            })
+        })
+        .collect();
+    TextLayout {
+        glyphs,
+        width: chars.width,
+        height: chars.height,
+    }
+}
+
+/// Breaks the text into lines of at most `max_width`, in the font's units,
+/// and places every character's pen position.
+pub(crate) fn place_chars<M: FontMetrics>(font: &M, text: &Text, max_width: Option<f32>) -> CharLayout {
+    let chars: Vec<(char, [f32; 4])> = text
+        .sections
+        .iter()
+        .flat_map(|section| section.text.chars().map(move |c| (c, section.color)))
+        .collect();
+    let lines = break_lines(font, &chars, max_width);
+
+    let mut layout = CharLayout {
+        chars: Vec::new(),
+        width: 0.0,
+        height: lines.len() as f32 * font.line_height(),
+    };
+    for (line_index, line) in lines.iter().enumerate() {
+        let width = line_width(font, trim_end(line));
+        layout.width = layout.width.max(width);
+
+        let x = match (text.alignment, max_width) {
+            (TextAlignment::Left, _) => 0.0,
+            (TextAlignment::Center, Some(max_width)) => ((max_width - width) / 2.0).floor(),
+            (TextAlignment::Center, None) => -(width / 2.0).floor(),
+            (TextAlignment::Right, Some(max_width)) => (max_width - width).floor(),
+            (TextAlignment::Right, None) => -width,
+        };
+        let y = line_index as f32 * font.line_height();
+
+        let mut pen = 0.0;
+        let mut previous = None;
+        for &(c, color) in line.iter() {
+            if let Some(previous) = previous {
+                pen += font.kerning(previous, c);
+            }
+            previous = Some(c);
+            layout.chars.push(PlacedChar {
+                c,
+                color,
+                position: (x + pen, y),
+            });
+            pen += font.advance(c);
+        }
+    }
+    layout
+}
+
+/// Splits the text at newlines and, if `max_width` is set, at the last
+/// space before a line gets too wide. Words wider than a line are split.
+fn break_lines<M: FontMetrics>(
+    font: &M,
+    chars: &[(char, [f32; 4])],
+    max_width: Option<f32>,
+) -> Vec<Vec<(char, [f32; 4])>> {
+    let mut lines = Vec::new();
+    for paragraph in chars.split(|(c, _)| *c == '\n') {
+        let paragraph: Vec<(char, [f32; 4])> = paragraph
+            .iter()
+            .flat_map(|&(c, color)| layout_chars(c).map(move |c| (c, color)))
+            .collect();
+        let max_width = match max_width {
+            Some(max_width) => max_width,
+            None => {
+                lines.push(paragraph);
+                continue;
+            }
+        };
+
+        let mut line: Vec<(char, [f32; 4])> = Vec::new();
+        for (c, color) in paragraph {
+            line.push((c, color));
+            if c.is_whitespace() || line_width(font, &line) <= max_width {
+                continue;
+            }
+            match line.iter().rposition(|(c, _)| c.is_whitespace()) {
+                // Wrap the current word onto the next line
+                Some(space) => {
+                    let rest = line.split_off(space + 1);
+                    lines.push(std::mem::replace(&mut line, rest));
+                }
+                // A single word is too wide, so it is split
+                None if line.len() > 1 => {
+                    let rest = line.split_off(line.len() - 1);
+                    lines.push(std::mem::replace(&mut line, rest));
+                }
+                None => {}
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// The number of spaces a tab is laid out as.
+pub const TAB_WIDTH: usize = 4;
+
+/// The characters `c` is laid out as. Carriage returns are dropped and
+/// tabs become spaces, as fonts rarely have glyphs for them and missing
+/// characters are drawn as `?`.
+pub(crate) fn layout_chars(c: char) -> impl Iterator<Item = char> {
+    let (c, count) = match c {
+        '\r' => (' ', 0),
+        '\t' => (' ', TAB_WIDTH),
+        c => (c, 1),
+    };
+    std::iter::repeat_n(c, count)
+}
+
+/// The pen position after the last glyph of the line.
+fn line_width<M: FontMetrics>(font: &M, line: &[(char, [f32; 4])]) -> f32 {
+    let mut width = 0.0;
+    let mut previous = None;
+    for &(c, _) in line {
+        if let Some(previous) = previous {
+            width += font.kerning(previous, c);
+        }
+        previous = Some(c);
+        width += font.advance(c);
+    }
+    width
+}
+
+/// The line without trailing whitespace, which doesn't count when aligning.
+fn trim_end(line: &[(char, [f32; 4])]) -> &[(char, [f32; 4])] {
+    let end = line
+        .iter()
+        .rposition(|(c, _)| !c.is_whitespace())
+        .map(|index| index + 1)
+        .unwrap_or(0);
+    &line[..end]
+}
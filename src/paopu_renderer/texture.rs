@@ -1,6 +1,11 @@
 use anyhow::*;
 use image::GenericImageView;
 
+/// A reference to a texture loaded by the
+/// [`Renderer`](crate::renderer::renderer::Renderer).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TextureHandle(pub(crate) usize);
+
 pub struct Texture {
     pub texture: wgpu::Texture,
     pub view: wgpu::TextureView,
@@ -23,6 +28,18 @@ impl Texture {
         queue: &wgpu::Queue,
         img: &image::DynamicImage,
         label: Option<&str>,
+    ) -> Result<Self> {
+        Self::from_image_with_filter(device, queue, img, label, wgpu::FilterMode::Nearest)
+    }
+
+    /// Like [`Texture::from_image`], sampled with `filter` instead of the
+    /// nearest pixel.
+    pub fn from_image_with_filter(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        img: &image::DynamicImage,
+        label: Option<&str>,
+        filter: wgpu::FilterMode,
     ) -> Result<Self> {
         let rgba = img.as_rgba8().unwrap();
         let dimensions = img.dimensions();
@@ -66,8 +83,8 @@ impl Texture {
             address_mode_u: wgpu::AddressMode::ClampToEdge,
             address_mode_v: wgpu::AddressMode::ClampToEdge,
             address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Nearest,
-            min_filter: wgpu::FilterMode::Nearest,
+            mag_filter: filter,
+            min_filter: filter,
             mipmap_filter: wgpu::FilterMode::Nearest,
             ..Default::default()
         });
@@ -78,4 +95,66 @@ impl Texture {
             sampler,
         })
     }
+
+    /// Creates a texture that can be both drawn into and sampled, for a
+    /// render target.
+    pub fn new_render_target(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        label: Option<&str>,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label,
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::RENDER_ATTACHMENT,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        Self {
+            texture,
+            view,
+            sampler,
+        }
+    }
+
+    /// Replaces the pixels of an area whose top left corner is `x`, `y`.
+    pub fn write_region(&self, queue: &wgpu::Queue, x: u32, y: u32, img: &image::RgbaImage) {
+        queue.write_texture(
+            wgpu::TextureCopyView {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x, y, z: 0 },
+            },
+            img.as_raw(),
+            wgpu::TextureDataLayout {
+                offset: 0,
+                bytes_per_row: 4 * img.width(),
+                rows_per_image: img.height(),
+            },
+            wgpu::Extent3d {
+                width: img.width(),
+                height: img.height(),
+                depth: 1,
+            },
+        );
+    }
 }
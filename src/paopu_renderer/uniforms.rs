@@ -6,37 +6,32 @@ use crate::renderer::camera::*;
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct Uniforms {
     // Can't use cgmath with bytemuck directly
-    model_view_projection: [[f32; 4]; 4],
+    view_projection: [[f32; 4]; 4],
 }
 
 impl Uniforms {
     pub fn new() -> Self {
         Self {
-            model_view_projection: cgmath::Matrix4::identity().into(),
+            view_projection: cgmath::Matrix4::identity().into(),
         }
     }
 }
 
 pub struct UniformStaging {
-    pub camera: Camera,
-    pub model_rotation: cgmath::Deg<f32>,
+    pub camera: Camera2D,
 }
 
 impl UniformStaging {
-    pub fn new(camera: Camera) -> Self {
-        Self {
-            camera,
-            model_rotation: cgmath::Deg(0.0),
-        }
+    pub fn new(camera: Camera2D) -> Self {
+        Self { camera }
     }
     pub fn update_uniforms(&self, uniforms: &mut Uniforms) {
-        uniforms.model_view_projection = (OPENGL_TO_WGPU_MATRIX
-            * self.camera.build_view_projection_matrix()
-            * cgmath::Matrix4::from_angle_z(self.model_rotation))
-        .into();
+        // Sprites are transformed on the CPU, so only the camera is needed
+        uniforms.view_projection = self.camera.build_view_projection_matrix().into();
     }
 
-    pub fn set_camera_aspect(&mut self, aspect: f32) {
-        self.camera.aspect = aspect;
+    pub fn set_camera_viewport(&mut self, width: f32, height: f32) {
+        self.camera.viewport_width = width;
+        self.camera.viewport_height = height;
     }
 }
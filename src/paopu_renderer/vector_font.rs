@@ -0,0 +1,139 @@
+use std::path::Path;
+
+use anyhow::Context;
+
+use crate::renderer::{font::FontHandle, text::FontMetrics};
+
+// -------------------------------------------------------------------------------
+//                      - Vector Font -
+// -------------------------------------------------------------------------------
+
+/// A TrueType or OpenType font. Glyphs are rasterized into a glyph atlas
+/// the first time they are drawn at a size.
+pub struct VectorFont {
+    font: rusttype::Font<'static>,
+    sdf: bool,
+}
+
+impl VectorFont {
+    pub fn parse(bytes: Vec<u8>) -> anyhow::Result<Self> {
+        let font = rusttype::Font::try_from_vec(bytes)
+            .ok_or_else(|| anyhow::anyhow!("Invalid TrueType/OpenType font"))?;
+        Ok(Self { font, sdf: false })
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)
+            .with_context(|| format!("Unable to read font {}", path.display()))?;
+        Self::parse(bytes).with_context(|| format!("Unable to parse font {}", path.display()))
+    }
+
+    /// Rasterizes glyphs once as signed distance fields, which stay smooth
+    /// at any size, instead of once per size.
+    pub fn with_sdf(mut self, sdf: bool) -> Self {
+        self.sdf = sdf;
+        self
+    }
+
+    pub fn is_sdf(&self) -> bool {
+        self.sdf
+    }
+
+    pub fn has_glyph(&self, c: char) -> bool {
+        self.font.glyph(c).id().0 != 0
+    }
+
+    /// The distance between two lines at `size` pixels.
+    pub fn line_height(&self, size: f32) -> f32 {
+        let v_metrics = self.font.v_metrics(rusttype::Scale::uniform(size));
+        (v_metrics.ascent - v_metrics.descent + v_metrics.line_gap).ceil()
+    }
+
+    /// The distance from the top of a line to the baseline at `size` pixels.
+    pub fn ascent(&self, size: f32) -> f32 {
+        self.font.v_metrics(rusttype::Scale::uniform(size)).ascent.round()
+    }
+
+    pub(crate) fn font(&self) -> &rusttype::Font<'static> {
+        &self.font
+    }
+}
+
+/// A font and its fallbacks at a size, picking the first font that has
+/// a glyph for each character.
+pub(crate) struct VectorMetrics<'a> {
+    pub fonts: Vec<(FontHandle, &'a VectorFont)>,
+    // Whole pixels, so glyphs are laid out, rasterized and cached at the same size.
+    pixel_size: u32,
+}
+
+impl<'a> VectorMetrics<'a> {
+    /// Rounds `size` to whole pixels, at least one.
+    pub fn new(fonts: Vec<(FontHandle, &'a VectorFont)>, size: f32) -> Self {
+        Self {
+            fonts,
+            pixel_size: size.round().max(1.0) as u32,
+        }
+    }
+
+    pub fn pixel_size(&self) -> u32 {
+        self.pixel_size
+    }
+
+    pub fn size(&self) -> f32 {
+        self.pixel_size as f32
+    }
+
+    /// The font drawing `c` and the glyph's id in it. Characters missing
+    /// from every font use the first font's missing glyph.
+    pub fn resolve(&self, c: char) -> (FontHandle, &'a VectorFont, rusttype::GlyphId) {
+        for &(handle, font) in self.fonts.iter() {
+            let id = font.font.glyph(c).id();
+            if id.0 != 0 {
+                return (handle, font, id);
+            }
+        }
+        let (handle, font) = self.fonts[0];
+        (handle, font, rusttype::GlyphId(0))
+    }
+
+    fn scale(&self) -> rusttype::Scale {
+        rusttype::Scale::uniform(self.size())
+    }
+}
+
+impl<'a> FontMetrics for VectorMetrics<'a> {
+    fn advance(&self, c: char) -> f32 {
+        let (_, font, id) = self.resolve(c);
+        font.font.glyph(id).scaled(self.scale()).h_metrics().advance_width
+    }
+
+    /// Only glyphs of the same font are kerned.
+    fn kerning(&self, first: char, second: char) -> f32 {
+        let (first_handle, font, first_id) = self.resolve(first);
+        let (second_handle, _, second_id) = self.resolve(second);
+        if first_handle != second_handle {
+            return 0.0;
+        }
+        font.font.pair_kerning(self.scale(), first_id, second_id)
+    }
+
+    fn line_height(&self) -> f32 {
+        self.fonts[0].1.line_height(self.size())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::renderer::vector_font::VectorMetrics;
+
+    #[test]
+    fn metrics_use_whole_pixel_sizes() {
+        let metrics = VectorMetrics::new(Vec::new(), 12.6);
+        assert_eq!(metrics.pixel_size(), 13);
+        assert_eq!(metrics.size(), 13.0);
+        assert_eq!(VectorMetrics::new(Vec::new(), 12.4).size(), 12.0);
+        assert_eq!(VectorMetrics::new(Vec::new(), 0.2).pixel_size(), 1);
+    }
+}
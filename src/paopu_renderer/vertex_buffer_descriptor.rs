@@ -1,3 +1,5 @@
+use crate::renderer::shader_reflection::{VertexInput, VertexScalar};
+
 // -------------------------------------------------------
 //              - Vertex Buffer -
 // -------------------------------------------------------
@@ -7,6 +9,7 @@
 pub struct Vertex {
     pub position: [f32; 3],
     pub tex_coords: [f32; 2],
+    pub color: [f32; 4],
 }
 
 impl Vertex {
@@ -25,7 +28,34 @@ impl Vertex {
                     shader_location: 1,
                     format: wgpu::VertexFormat::Float2,
                 },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 5]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float4,
+                },
             ],
         }
     }
+
+    /// The attributes as shader inputs, for checking shaders against them.
+    pub fn reflected_inputs() -> Vec<VertexInput> {
+        Self::descriptor()
+            .attributes
+            .iter()
+            .filter_map(|attribute| {
+                let (scalar, components) = match attribute.format {
+                    wgpu::VertexFormat::Float => (VertexScalar::Float, 1),
+                    wgpu::VertexFormat::Float2 => (VertexScalar::Float, 2),
+                    wgpu::VertexFormat::Float3 => (VertexScalar::Float, 3),
+                    wgpu::VertexFormat::Float4 => (VertexScalar::Float, 4),
+                    _ => return None,
+                };
+                Some(VertexInput {
+                    location: attribute.shader_location,
+                    scalar,
+                    components,
+                })
+            })
+            .collect()
+    }
 }
@@ -0,0 +1,497 @@
+use std::{
+    borrow::Cow,
+    ffi::OsString,
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use anyhow::Context;
+
+/// Where the clipboard contents are stored.
+pub trait ClipboardBackend: Send {
+    /// Returns `None` if the clipboard holds no text.
+    fn get_text(&mut self) -> anyhow::Result<Option<String>>;
+    fn set_text(&mut self, text: &str) -> anyhow::Result<()>;
+    /// Returns `None` if the clipboard holds no image.
+    fn get_image(&mut self) -> anyhow::Result<Option<image::RgbaImage>>;
+    fn set_image(&mut self, image: &image::RgbaImage) -> anyhow::Result<()>;
+}
+
+// -------------------------------------------------------------------------------
+//                      - Clipboard -
+// -------------------------------------------------------------------------------
+
+/// The application's clipboard, reached through the
+/// [`Input`](crate::input::input::Input) singleton.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut input = INPUT.lock().unwrap();
+/// input.get_clipboard_mut().set_text("spawn slime 3 4")?;
+/// ```
+pub struct Clipboard {
+    // `None` until the system clipboard is first used.
+    backend: Option<Box<dyn ClipboardBackend>>,
+}
+
+impl Clipboard {
+    /// Uses the system clipboard, or an in-memory one if none is found.
+    /// The system clipboard is connected to when it is first used.
+    pub fn system() -> Self {
+        Self { backend: None }
+    }
+
+    /// A clipboard that only lives as long as the application.
+    pub fn memory() -> Self {
+        Self::with_backend(MemoryClipboard::default())
+    }
+
+    pub fn with_backend(backend: impl ClipboardBackend + 'static) -> Self {
+        Self {
+            backend: Some(Box::new(backend)),
+        }
+    }
+
+    /// Replaces the backend, e.g. with a [`MemoryClipboard`] in tests.
+    pub fn set_backend(&mut self, backend: impl ClipboardBackend + 'static) {
+        self.backend = Some(Box::new(backend));
+    }
+
+    pub fn get_text(&mut self) -> anyhow::Result<Option<String>> {
+        self.backend().get_text()
+    }
+
+    pub fn set_text(&mut self, text: &str) -> anyhow::Result<()> {
+        self.backend().set_text(text)
+    }
+
+    pub fn get_image(&mut self) -> anyhow::Result<Option<image::RgbaImage>> {
+        self.backend().get_image()
+    }
+
+    pub fn set_image(&mut self, image: &image::RgbaImage) -> anyhow::Result<()> {
+        self.backend().set_image(image)
+    }
+
+    fn backend(&mut self) -> &mut dyn ClipboardBackend {
+        self.backend.get_or_insert_with(detect_system_backend).as_mut()
+    }
+}
+
+/// Prefers the native clipboard, then the command line tools.
+fn detect_system_backend() -> Box<dyn ClipboardBackend> {
+    let native_error = match NativeClipboard::new() {
+        Ok(backend) => return Box::new(backend),
+        Err(e) => e,
+    };
+    if let Some(backend) = CommandClipboard::detect() {
+        return Box::new(backend);
+    }
+    eprintln!(
+        "[Clipboard]: No system clipboard found ({}), using an in-memory one",
+        native_error
+    );
+    Box::new(MemoryClipboard::default())
+}
+
+// -------------------------------------------------------------------------------
+//                      - Native Clipboard -
+// -------------------------------------------------------------------------------
+
+/// The system clipboard, accessed through the platform's clipboard API.
+pub struct NativeClipboard {
+    clipboard: arboard::Clipboard,
+}
+
+impl NativeClipboard {
+    pub fn new() -> anyhow::Result<Self> {
+        let clipboard = arboard::Clipboard::new().context("Unable to open the system clipboard")?;
+        Ok(Self { clipboard })
+    }
+}
+
+impl ClipboardBackend for NativeClipboard {
+    fn get_text(&mut self) -> anyhow::Result<Option<String>> {
+        match self.clipboard.get_text() {
+            Ok(text) => Ok(Some(text)),
+            Err(arboard::Error::ContentNotAvailable) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn set_text(&mut self, text: &str) -> anyhow::Result<()> {
+        Ok(self.clipboard.set_text(text)?)
+    }
+
+    fn get_image(&mut self) -> anyhow::Result<Option<image::RgbaImage>> {
+        let image = match self.clipboard.get_image() {
+            Ok(image) => image,
+            Err(arboard::Error::ContentNotAvailable) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let image = image::RgbaImage::from_raw(image.width as u32, image.height as u32, image.bytes.into_owned())
+            .ok_or_else(|| anyhow::anyhow!("[Clipboard]: The clipboard image has the wrong size"))?;
+        Ok(Some(image))
+    }
+
+    fn set_image(&mut self, image: &image::RgbaImage) -> anyhow::Result<()> {
+        Ok(self.clipboard.set_image(arboard::ImageData {
+            width: image.width() as usize,
+            height: image.height() as usize,
+            bytes: Cow::Borrowed(image.as_raw()),
+        })?)
+    }
+}
+
+// -------------------------------------------------------------------------------
+//                      - Memory Clipboard -
+// -------------------------------------------------------------------------------
+
+/// Keeps the clipboard contents in memory. Setting text clears the image
+/// and the other way around, like a system clipboard.
+#[derive(Default)]
+pub struct MemoryClipboard {
+    text: Option<String>,
+    image: Option<image::RgbaImage>,
+}
+
+impl ClipboardBackend for MemoryClipboard {
+    fn get_text(&mut self) -> anyhow::Result<Option<String>> {
+        Ok(self.text.clone())
+    }
+
+    fn set_text(&mut self, text: &str) -> anyhow::Result<()> {
+        self.text = Some(text.to_string());
+        self.image = None;
+        Ok(())
+    }
+
+    fn get_image(&mut self) -> anyhow::Result<Option<image::RgbaImage>> {
+        Ok(self.image.clone())
+    }
+
+    fn set_image(&mut self, image: &image::RgbaImage) -> anyhow::Result<()> {
+        self.image = Some(image.clone());
+        self.text = None;
+        Ok(())
+    }
+}
+
+// -------------------------------------------------------------------------------
+//                      - Command Clipboard -
+// -------------------------------------------------------------------------------
+
+/// The command line tool the system clipboard is accessed through.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ClipboardTool {
+    /// `wl-copy` and `wl-paste` on Wayland.
+    WlClipboard,
+    /// `xclip` on X11.
+    Xclip,
+    /// `xsel` on X11. Doesn't support images.
+    Xsel,
+}
+
+/// Accesses the X11 or Wayland clipboard through `wl-clipboard`, `xclip`
+/// or `xsel`, whichever is installed. Used when the [`NativeClipboard`]
+/// can't connect, e.g. on Wayland without XWayland.
+pub struct CommandClipboard {
+    tool: ClipboardTool,
+    // The `PATH` the tool is looked up in, `None` for the process's own.
+    path: Option<OsString>,
+}
+
+impl CommandClipboard {
+    pub fn new(tool: ClipboardTool) -> Self {
+        Self { tool, path: None }
+    }
+
+    /// Picks a tool for the current display server, if one is installed.
+    pub fn detect() -> Option<Self> {
+        Self::detect_with(|name| std::env::var_os(name))
+    }
+
+    /// Picks a tool from the environment variables `var` returns, running
+    /// it from the `PATH` it was found in.
+    fn detect_with(var: impl Fn(&str) -> Option<OsString>) -> Option<Self> {
+        let path = var("PATH");
+        let is_installed = |program| is_installed(program, path.as_ref());
+        let tool = if var("WAYLAND_DISPLAY").is_some() && is_installed("wl-copy") {
+            ClipboardTool::WlClipboard
+        } else if var("DISPLAY").is_some() && is_installed("xclip") {
+            ClipboardTool::Xclip
+        } else if var("DISPLAY").is_some() && is_installed("xsel") {
+            ClipboardTool::Xsel
+        } else {
+            return None;
+        };
+        Some(Self { tool, path })
+    }
+
+    pub fn get_tool(&self) -> ClipboardTool {
+        self.tool
+    }
+
+    fn command(&self, program: &str) -> Command {
+        let mut command = Command::new(program);
+        if let Some(path) = &self.path {
+            command.env("PATH", path);
+        }
+        command
+    }
+
+    fn paste_command(&self, mime_type: Option<&str>) -> anyhow::Result<Command> {
+        let mut command;
+        match self.tool {
+            ClipboardTool::WlClipboard => {
+                command = self.command("wl-paste");
+                command.arg("--no-newline");
+                if let Some(mime_type) = mime_type {
+                    command.args(["--type", mime_type]);
+                }
+            }
+            ClipboardTool::Xclip => {
+                command = self.command("xclip");
+                command.args(["-selection", "clipboard", "-out"]);
+                if let Some(mime_type) = mime_type {
+                    command.args(["-target", mime_type]);
+                }
+            }
+            ClipboardTool::Xsel => {
+                if mime_type.is_some() {
+                    anyhow::bail!("[Clipboard]: xsel only supports text");
+                }
+                command = self.command("xsel");
+                command.args(["--clipboard", "--output"]);
+            }
+        }
+        Ok(command)
+    }
+
+    fn copy_command(&self, mime_type: Option<&str>) -> anyhow::Result<Command> {
+        let mut command;
+        match self.tool {
+            ClipboardTool::WlClipboard => {
+                command = self.command("wl-copy");
+                if let Some(mime_type) = mime_type {
+                    command.args(["--type", mime_type]);
+                }
+            }
+            ClipboardTool::Xclip => {
+                command = self.command("xclip");
+                command.args(["-selection", "clipboard", "-in"]);
+                if let Some(mime_type) = mime_type {
+                    command.args(["-target", mime_type]);
+                }
+            }
+            ClipboardTool::Xsel => {
+                if mime_type.is_some() {
+                    anyhow::bail!("[Clipboard]: xsel only supports text");
+                }
+                command = self.command("xsel");
+                command.args(["--clipboard", "--input"]);
+            }
+        }
+        Ok(command)
+    }
+
+    /// Reads the clipboard. An empty clipboard makes the tools fail, so
+    /// any failure is reported as `None`.
+    fn paste(&self, mime_type: Option<&str>) -> anyhow::Result<Option<Vec<u8>>> {
+        let output = self
+            .paste_command(mime_type)?
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .with_context(|| format!("Unable to run {:?}", self.tool))?;
+        if !output.status.success() {
+            return Ok(None);
+        }
+        Ok(Some(output.stdout))
+    }
+
+    fn copy(&self, mime_type: Option<&str>, data: &[u8]) -> anyhow::Result<()> {
+        // The tools stay in the background to serve the clipboard, so
+        // nothing may keep their output open.
+        let mut child = self
+            .copy_command(mime_type)?
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .with_context(|| format!("Unable to run {:?}", self.tool))?;
+        child.stdin.take().unwrap().write_all(data)?;
+        let status = child.wait()?;
+        if !status.success() {
+            anyhow::bail!("[Clipboard]: {:?} failed with {}", self.tool, status);
+        }
+        Ok(())
+    }
+}
+
+impl ClipboardBackend for CommandClipboard {
+    fn get_text(&mut self) -> anyhow::Result<Option<String>> {
+        match self.paste(None)? {
+            Some(bytes) => Ok(Some(String::from_utf8(bytes).context("Clipboard text isn't utf-8")?)),
+            None => Ok(None),
+        }
+    }
+
+    fn set_text(&mut self, text: &str) -> anyhow::Result<()> {
+        self.copy(None, text.as_bytes())
+    }
+
+    fn get_image(&mut self) -> anyhow::Result<Option<image::RgbaImage>> {
+        match self.paste(Some("image/png"))? {
+            Some(bytes) if !bytes.is_empty() => {
+                let img = image::load_from_memory_with_format(&bytes, image::ImageFormat::Png)
+                    .context("Unable to decode the clipboard image")?;
+                Ok(Some(img.to_rgba8()))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn set_image(&mut self, image: &image::RgbaImage) -> anyhow::Result<()> {
+        let mut png = Vec::new();
+        image::png::PngEncoder::new(&mut png).encode(
+            image.as_raw(),
+            image.width(),
+            image.height(),
+            image::ColorType::Rgba8,
+        )?;
+        self.copy(Some("image/png"), &png)
+    }
+}
+
+fn is_installed(program: &str, path: Option<&OsString>) -> bool {
+    match path {
+        Some(paths) => std::env::split_paths(paths).any(|dir| dir.join(program).is_file()),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(unix)]
+    use std::ffi::OsString;
+
+    use crate::window::clipboard::{Clipboard, ClipboardBackend, MemoryClipboard};
+    #[cfg(unix)]
+    use crate::window::clipboard::{ClipboardTool, CommandClipboard};
+
+    /// A directory to stand in for `PATH`, holding a fake tool for each
+    /// name in `tools`. The tools keep the clipboard in a file next to them.
+    #[cfg(unix)]
+    fn stub_path(name: &str, tools: &[&str]) -> OsString {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("paopu-clipboard-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let contents = dir.join("contents");
+        for tool in tools {
+            // xclip and xsel copy when given `-in` or `--input`
+            let copies = match *tool {
+                "wl-copy" => "true",
+                "wl-paste" => "false",
+                _ => "case \"$*\" in *-in*) true ;; *) false ;; esac",
+            };
+            let script = format!(
+                "#!/bin/sh\nif {}; then /bin/cat > '{1}'; else /bin/cat '{1}'; fi\n",
+                copies,
+                contents.display()
+            );
+            let program = dir.join(tool);
+            std::fs::write(&program, script).unwrap();
+            std::fs::set_permissions(&program, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        dir.into_os_string()
+    }
+
+    #[cfg(unix)]
+    fn detect(display: bool, wayland: bool, path: &OsString) -> Option<CommandClipboard> {
+        CommandClipboard::detect_with(|name| match name {
+            "DISPLAY" if display => Some(OsString::from(":0")),
+            "WAYLAND_DISPLAY" if wayland => Some(OsString::from("wayland-0")),
+            "PATH" => Some(path.clone()),
+            _ => None,
+        })
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn command_clipboard_picks_the_tool_for_the_display_server() {
+        let tool = |display, wayland, path| detect(display, wayland, path).map(|c| c.get_tool());
+
+        let all = stub_path("all", &["wl-copy", "wl-paste", "xclip", "xsel"]);
+        assert_eq!(tool(true, true, &all), Some(ClipboardTool::WlClipboard));
+        assert_eq!(tool(true, false, &all), Some(ClipboardTool::Xclip));
+        assert_eq!(tool(false, false, &all), None);
+
+        let xsel = stub_path("xsel", &["xsel"]);
+        assert_eq!(tool(true, true, &xsel), Some(ClipboardTool::Xsel));
+        assert_eq!(tool(false, true, &xsel), None);
+
+        let none = stub_path("none", &[]);
+        assert_eq!(tool(true, true, &none), None);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn command_clipboard_runs_the_tool_from_its_path() {
+        let cases: [(&str, &[&str], bool); 3] = [
+            ("wl-clipboard", &["wl-copy", "wl-paste"], true),
+            ("xclip-only", &["xclip"], false),
+            ("xsel-only", &["xsel"], false),
+        ];
+        for (name, tools, wayland) in cases {
+            let path = stub_path(name, tools);
+            let mut clipboard = detect(true, wayland, &path).unwrap();
+            clipboard.set_text("spawn slime 3 4").unwrap();
+            assert_eq!(clipboard.get_text().unwrap().as_deref(), Some("spawn slime 3 4"));
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn command_clipboard_fails_without_the_tool() {
+        let mut clipboard = CommandClipboard::new(ClipboardTool::Xclip);
+        clipboard.path = Some(stub_path("missing", &[]));
+        let error = clipboard.set_text("spawn slime 3 4").unwrap_err();
+        assert!(error.to_string().contains("Unable to run Xclip"), "{}", error);
+        assert!(clipboard.get_text().is_err());
+    }
+
+    #[test]
+    fn memory_clipboard_starts_empty() {
+        let mut clipboard = MemoryClipboard::default();
+        assert_eq!(clipboard.get_text().unwrap(), None);
+        assert_eq!(clipboard.get_image().unwrap(), None);
+    }
+
+    #[test]
+    fn memory_clipboard_holds_text_or_an_image() {
+        let mut clipboard = Clipboard::memory();
+        clipboard.set_text("spawn slime 3 4").unwrap();
+        assert_eq!(clipboard.get_text().unwrap().as_deref(), Some("spawn slime 3 4"));
+
+        let image = image::RgbaImage::from_pixel(2, 3, image::Rgba([1, 2, 3, 4]));
+        clipboard.set_image(&image).unwrap();
+        assert_eq!(clipboard.get_image().unwrap(), Some(image));
+        assert_eq!(clipboard.get_text().unwrap(), None);
+
+        clipboard.set_text("").unwrap();
+        assert_eq!(clipboard.get_text().unwrap().as_deref(), Some(""));
+        assert_eq!(clipboard.get_image().unwrap(), None);
+    }
+
+    #[test]
+    fn backends_can_be_replaced() {
+        let mut clipboard = Clipboard::memory();
+        clipboard.set_text("old").unwrap();
+        clipboard.set_backend(MemoryClipboard::default());
+        assert_eq!(clipboard.get_text().unwrap(), None);
+    }
+}
@@ -0,0 +1,75 @@
+// -------------------------------------------------------------------------------
+//                      - Cursor -
+// -------------------------------------------------------------------------------
+
+/// How the cursor is kept inside the window.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CursorGrabMode {
+    /// The cursor moves freely.
+    None,
+    /// The cursor can't leave the window while it is focused.
+    Confined,
+}
+
+/// The OS cursor icons.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum CursorIcon {
+    Default,
+    Crosshair,
+    Hand,
+    Arrow,
+    Move,
+    Text,
+    Wait,
+    Help,
+    Progress,
+    NotAllowed,
+    ContextMenu,
+    Cell,
+    Grab,
+    Grabbing,
+    /// Resizes horizontally.
+    EwResize,
+    /// Resizes vertically.
+    NsResize,
+    /// Resizes diagonally, from the top right to the bottom left.
+    NeswResize,
+    /// Resizes diagonally, from the top left to the bottom right.
+    NwseResize,
+    ZoomIn,
+    ZoomOut,
+}
+
+impl From<CursorIcon> for winit::window::CursorIcon {
+    fn from(icon: CursorIcon) -> Self {
+        match icon {
+            CursorIcon::Default => winit::window::CursorIcon::Default,
+            CursorIcon::Crosshair => winit::window::CursorIcon::Crosshair,
+            CursorIcon::Hand => winit::window::CursorIcon::Hand,
+            CursorIcon::Arrow => winit::window::CursorIcon::Arrow,
+            CursorIcon::Move => winit::window::CursorIcon::Move,
+            CursorIcon::Text => winit::window::CursorIcon::Text,
+            CursorIcon::Wait => winit::window::CursorIcon::Wait,
+            CursorIcon::Help => winit::window::CursorIcon::Help,
+            CursorIcon::Progress => winit::window::CursorIcon::Progress,
+            CursorIcon::NotAllowed => winit::window::CursorIcon::NotAllowed,
+            CursorIcon::ContextMenu => winit::window::CursorIcon::ContextMenu,
+            CursorIcon::Cell => winit::window::CursorIcon::Cell,
+            CursorIcon::Grab => winit::window::CursorIcon::Grab,
+            CursorIcon::Grabbing => winit::window::CursorIcon::Grabbing,
+            CursorIcon::EwResize => winit::window::CursorIcon::EwResize,
+            CursorIcon::NsResize => winit::window::CursorIcon::NsResize,
+            CursorIcon::NeswResize => winit::window::CursorIcon::NeswResize,
+            CursorIcon::NwseResize => winit::window::CursorIcon::NwseResize,
+            CursorIcon::ZoomIn => winit::window::CursorIcon::ZoomIn,
+            CursorIcon::ZoomOut => winit::window::CursorIcon::ZoomOut,
+        }
+    }
+}
+
+/// Confines the cursor to the window, or releases it.
+pub(crate) fn apply_cursor_grab(window: &winit::window::Window, grab: CursorGrabMode) {
+    if let Err(e) = window.set_cursor_grab(grab == CursorGrabMode::Confined) {
+        eprintln!("[Window]: Unable to set the cursor grab to {:?}: {:?}", grab, e);
+    }
+}
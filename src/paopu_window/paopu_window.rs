@@ -1 +1,4 @@
+pub mod clipboard;
+pub mod cursor;
 pub mod window;
+pub mod window_descriptor;
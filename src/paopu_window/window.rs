@@ -1,19 +1,267 @@
 use winit::{
+    dpi::{LogicalPosition, LogicalSize, PhysicalSize},
     event::*,
-    event_loop::{ControlFlow, EventLoop},
-    window::WindowBuilder,
+    event_loop::{ControlFlow, EventLoop, EventLoopWindowTarget},
 };
 
 
-use std::any::Any;
+use std::{
+    any::Any,
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::Instant,
+};
 
 use crate::{
     core::application::Application,
+    core::events::{
+        CursorEntered, CursorLeft, FileDropped, FileHoverCancelled, FileHovered,
+        ScaleFactorChanged, Theme, ThemeChanged, WindowCloseRequested, WindowClosed, WindowFocused, WindowId,
+        WindowMinimized, WindowMoved, WindowOpened, WindowResized,
+    },
+    core::frame_limiter::{FrameLimiter, FrameRate},
     core::module::Module,
+    input::input::INPUT,
+    renderer::{present_mode::PresentMode, renderer::Renderer},
+    window::cursor::{apply_cursor_grab, CursorGrabMode, CursorIcon},
+    window::window_descriptor::{
+        load_icon, place_window, to_fullscreen, WindowDescriptor, WindowMode, WindowPosition,
+    },
 };
 
+/// A change to a window requested through one of the
+/// [`WindowState`] setters, applied by the event loop.
+#[derive(Debug, Clone, PartialEq)]
+enum WindowChange {
+    Title(String),
+    Size(u32, u32),
+    MinSize(Option<(u32, u32)>),
+    MaxSize(Option<(u32, u32)>),
+    Resizable(bool),
+    Decorations(bool),
+    Mode(WindowMode),
+    Maximized(bool),
+    Minimized(bool),
+    AlwaysOnTop(bool),
+    Position(WindowPosition),
+    Icon(Option<PathBuf>),
+    CursorVisible(bool),
+    CursorGrab(CursorGrabMode),
+    CursorIcon(CursorIcon),
+    CursorPosition(f32, f32),
+}
+
+/// The settings and OS reported state of one of the application's windows.
+pub struct WindowState {
+    descriptor: WindowDescriptor,
+    changes: Vec<WindowChange>,
+    // Set from a close request until the end of the frame that handles it.
+    close_requested: bool,
+    close_vetoed: bool,
+    scale_factor: f64,
+    physical_size: PhysicalSize<u32>,
+    focused: bool,
+    minimized: bool,
+}
+
+impl WindowState {
+    fn new(descriptor: WindowDescriptor) -> Self {
+        WindowState {
+            descriptor,
+            changes: Vec::new(),
+            close_requested: false,
+            close_vetoed: false,
+            scale_factor: 1.0,
+            physical_size: PhysicalSize::new(0, 0),
+            focused: true,
+            minimized: false,
+        }
+    }
+
+    /// The window's settings, including the changes requested at runtime.
+    pub fn get_descriptor(&self) -> &WindowDescriptor {
+        &self.descriptor
+    }
+
+    pub fn get_title(&self) -> &str {
+        self.descriptor.title.as_str()
+    }
+
+    /// Keeps the window open after a
+    /// [`WindowCloseRequested`] event, e.g. to ask about unsaved progress.
+    /// Has to be called during the frame the event is received in.
+    pub fn veto_close(&mut self) {
+        if self.close_requested {
+            self.close_vetoed = true;
+        }
+    }
+
+    /// Whether a close request is waiting for the end of the frame.
+    pub fn is_close_requested(&self) -> bool {
+        self.close_requested
+    }
+
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
+
+    pub fn is_minimized(&self) -> bool {
+        self.minimized
+    }
+
+    /// The ratio of physical to logical pixels of the window's monitor.
+    pub fn get_scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+
+    /// The size of the window's client area in physical pixels.
+    pub fn get_physical_size(&self) -> PhysicalSize<u32> {
+        self.physical_size
+    }
+
+    /// The size of the window's client area in logical pixels.
+    pub fn get_logical_size(&self) -> LogicalSize<f64> {
+        self.physical_size.to_logical(self.scale_factor)
+    }
+
+    // ---------------------------------------------------------
+    //                  Runtime changes
+    //   Applied by the event loop at the start of the next frame.
+    // ---------------------------------------------------------
+
+    pub fn set_title(&mut self, title: &str) {
+        self.descriptor.title = title.to_string();
+        self.changes.push(WindowChange::Title(title.to_string()));
+    }
+
+    /// Resizes the window, in logical pixels.
+    pub fn set_size(&mut self, width: u32, height: u32) {
+        self.descriptor.width = width;
+        self.descriptor.height = height;
+        self.changes.push(WindowChange::Size(width, height));
+    }
+
+    pub fn set_min_size(&mut self, min_size: Option<(u32, u32)>) {
+        self.descriptor.min_size = min_size;
+        self.changes.push(WindowChange::MinSize(min_size));
+    }
+
+    pub fn set_max_size(&mut self, max_size: Option<(u32, u32)>) {
+        self.descriptor.max_size = max_size;
+        self.changes.push(WindowChange::MaxSize(max_size));
+    }
+
+    pub fn set_resizable(&mut self, resizable: bool) {
+        self.descriptor.resizable = resizable;
+        self.changes.push(WindowChange::Resizable(resizable));
+    }
+
+    pub fn set_decorations(&mut self, decorations: bool) {
+        self.descriptor.decorations = decorations;
+        self.changes.push(WindowChange::Decorations(decorations));
+    }
+
+    pub fn get_mode(&self) -> WindowMode {
+        self.descriptor.mode
+    }
+
+    pub fn set_mode(&mut self, mode: WindowMode) {
+        self.descriptor.mode = mode;
+        self.changes.push(WindowChange::Mode(mode));
+    }
+
+    /// Switches between windowed and borderless fullscreen.
+    pub fn toggle_fullscreen(&mut self) {
+        let mode = match self.descriptor.mode {
+            WindowMode::Windowed => WindowMode::BorderlessFullscreen,
+            _ => WindowMode::Windowed,
+        };
+        self.set_mode(mode);
+    }
+
+    pub fn set_maximized(&mut self, maximized: bool) {
+        self.descriptor.maximized = maximized;
+        self.changes.push(WindowChange::Maximized(maximized));
+    }
+
+    pub fn set_minimized(&mut self, minimized: bool) {
+        self.changes.push(WindowChange::Minimized(minimized));
+    }
+
+    pub fn set_always_on_top(&mut self, always_on_top: bool) {
+        self.descriptor.always_on_top = always_on_top;
+        self.changes.push(WindowChange::AlwaysOnTop(always_on_top));
+    }
+
+    /// Moves the window on its current monitor.
+    pub fn set_position(&mut self, position: WindowPosition) {
+        self.descriptor.position = position;
+        self.changes.push(WindowChange::Position(position));
+    }
+
+    /// Sets the window icon from an image file, or removes it.
+    pub fn set_icon<P: AsRef<Path>>(&mut self, icon: Option<P>) {
+        let icon = icon.map(|path| path.as_ref().to_path_buf());
+        self.descriptor.icon = icon.clone();
+        self.changes.push(WindowChange::Icon(icon));
+    }
+
+    // ---------------------------------------------------------
+    //                  Cursor
+    // ---------------------------------------------------------
+
+    pub fn get_cursor_visible(&self) -> bool {
+        self.descriptor.cursor_visible
+    }
+
+    /// Shows or hides the OS cursor while it is over the window, e.g. when
+    /// the renderer draws a [`SoftwareCursor`](crate::renderer::software_cursor::SoftwareCursor).
+    pub fn set_cursor_visible(&mut self, visible: bool) {
+        self.descriptor.cursor_visible = visible;
+        self.changes.push(WindowChange::CursorVisible(visible));
+    }
+
+    pub fn get_cursor_grab(&self) -> CursorGrabMode {
+        self.descriptor.cursor_grab
+    }
+
+    pub fn set_cursor_grab(&mut self, grab: CursorGrabMode) {
+        self.descriptor.cursor_grab = grab;
+        self.changes.push(WindowChange::CursorGrab(grab));
+    }
+
+    pub fn get_cursor_icon(&self) -> CursorIcon {
+        self.descriptor.cursor_icon
+    }
+
+    pub fn set_cursor_icon(&mut self, icon: CursorIcon) {
+        self.descriptor.cursor_icon = icon;
+        self.changes.push(WindowChange::CursorIcon(icon));
+    }
+
+    /// Moves the cursor, in logical pixels from the window's top left corner.
+    pub fn set_cursor_position(&mut self, x: f32, y: f32) {
+        self.changes.push(WindowChange::CursorPosition(x, y));
+    }
+}
+
+// -------------------------------------------------------------------------------
+//                      - Window -
+// -------------------------------------------------------------------------------
+
+/// Opens the primary window and runs the event loop. Further windows,
+/// e.g. tool palettes, can be opened with [`Window::open_window`].
 pub struct Window {
-    title: String,
+    windows: BTreeMap<WindowId, WindowState>,
+    next_id: usize,
+    // Opened by the event loop at the start of the next frame.
+    opening: Vec<WindowId>,
+    // Closed by the event loop at the end of the frame.
+    closing: Vec<WindowId>,
+    frame_rate: FrameRate,
+    low_power_when_inactive: bool,
+    fullscreen_shortcut: bool,
+    pause_on_focus_lost: bool,
 }
 
 impl Module for Window {
@@ -26,22 +274,308 @@ impl Module for Window {
     }
 
     fn build(&self, app: &mut Application) {
-        app.set_loop_function(run);
+        app.add_event::<WindowResized>()
+            .add_event::<WindowFocused>()
+            .add_event::<WindowMinimized>()
+            .add_event::<WindowMoved>()
+            .add_event::<WindowCloseRequested>()
+            .add_event::<WindowOpened>()
+            .add_event::<WindowClosed>()
+            .add_event::<CursorEntered>()
+            .add_event::<CursorLeft>()
+            .add_event::<FileHovered>()
+            .add_event::<FileHoverCancelled>()
+            .add_event::<FileDropped>()
+            .add_event::<ThemeChanged>()
+            .set_loop_function(run);
     }
 
 }
 
 impl Default for Window {
     fn default() -> Self {
-        Window {
-            title: String::from("Paopu-rs Application"),
-        }
+        Window::new(WindowDescriptor::default())
     }
 }
 
 impl Window {
+    /// Creates the module with the primary window's settings.
+    pub fn new(descriptor: WindowDescriptor) -> Self {
+        let mut windows = BTreeMap::new();
+        windows.insert(WindowId::PRIMARY, WindowState::new(descriptor));
+        Window {
+            windows,
+            next_id: 1,
+            opening: Vec::new(),
+            closing: Vec::new(),
+            frame_rate: FrameRate::default(),
+            low_power_when_inactive: true,
+            fullscreen_shortcut: true,
+            pause_on_focus_lost: false,
+        }
+    }
+
+    /// Sets the target frame rate of the main game loop.
+    pub fn with_frame_rate(mut self, frame_rate: FrameRate) -> Self {
+        self.frame_rate = frame_rate;
+        self
+    }
+
+    /// Sets whether the game loop should stop polling and wait for
+    /// events while no window is focused or every window is minimized.
+    pub fn with_low_power_when_inactive(mut self, enabled: bool) -> Self {
+        self.low_power_when_inactive = enabled;
+        self
+    }
+
+    /// Sets whether Alt+Enter toggles borderless fullscreen. Enabled by default.
+    pub fn with_fullscreen_shortcut(mut self, enabled: bool) -> Self {
+        self.fullscreen_shortcut = enabled;
+        self
+    }
+
+    /// Sets whether [`Time`](crate::core::time::Time) pauses while no
+    /// window is focused. Disabled by default.
+    pub fn with_pause_on_focus_lost(mut self, enabled: bool) -> Self {
+        self.pause_on_focus_lost = enabled;
+        self
+    }
+
+    /// The primary window's settings, including the changes requested at runtime.
+    pub fn get_descriptor(&self) -> &WindowDescriptor {
+        self.primary().get_descriptor()
+    }
+
     pub fn get_title(&self) -> &str {
-        self.title.as_str()
+        self.primary().get_title()
+    }
+
+    /// The window the application started with.
+    pub fn primary(&self) -> &WindowState {
+        &self.windows[&WindowId::PRIMARY]
+    }
+
+    pub fn primary_mut(&mut self) -> &mut WindowState {
+        self.windows.get_mut(&WindowId::PRIMARY).unwrap()
+    }
+
+    /// Returns the window if it is open, or about to be opened.
+    pub fn window(&self, id: WindowId) -> Option<&WindowState> {
+        self.windows.get(&id)
+    }
+
+    pub fn window_mut(&mut self, id: WindowId) -> Option<&mut WindowState> {
+        self.windows.get_mut(&id)
+    }
+
+    /// The open windows, in order of their IDs.
+    pub fn window_ids(&self) -> impl Iterator<Item = WindowId> + '_ {
+        self.windows.keys().copied()
+    }
+
+    /// Opens another window at the start of the next frame, sharing the
+    /// renderer's device and textures. A [`WindowOpened`] event is sent
+    /// once it exists.
+    pub fn open_window(&mut self, descriptor: WindowDescriptor) -> WindowId {
+        let id = WindowId(self.next_id);
+        self.next_id += 1;
+        self.windows.insert(id, WindowState::new(descriptor));
+        self.opening.push(id);
+        id
+    }
+
+    /// Closes a window at the end of the frame, sending a [`WindowClosed`]
+    /// event. Closing the primary window quits the application.
+    pub fn close_window(&mut self, id: WindowId) {
+        if self.windows.contains_key(&id) && !self.closing.contains(&id) {
+            self.closing.push(id);
+        }
+    }
+
+    pub fn get_frame_rate(&self) -> FrameRate {
+        self.frame_rate
+    }
+
+    /// Changes the target frame rate at runtime.
+    /// Takes effect at the start of the next frame.
+    pub fn set_frame_rate(&mut self, frame_rate: FrameRate) {
+        self.frame_rate = frame_rate;
+    }
+
+    pub fn get_low_power_when_inactive(&self) -> bool {
+        self.low_power_when_inactive
+    }
+
+    pub fn set_low_power_when_inactive(&mut self, enabled: bool) {
+        self.low_power_when_inactive = enabled;
+    }
+
+    pub fn get_fullscreen_shortcut(&self) -> bool {
+        self.fullscreen_shortcut
+    }
+
+    pub fn set_fullscreen_shortcut(&mut self, enabled: bool) {
+        self.fullscreen_shortcut = enabled;
+    }
+
+    pub fn get_pause_on_focus_lost(&self) -> bool {
+        self.pause_on_focus_lost
+    }
+
+    pub fn set_pause_on_focus_lost(&mut self, enabled: bool) {
+        self.pause_on_focus_lost = enabled;
+    }
+
+    /// Whether any window is waiting to close at the end of the frame.
+    fn is_close_pending(&self) -> bool {
+        !self.closing.is_empty() || self.windows.values().any(|state| state.close_requested)
+    }
+
+    /// Whether no window is focused or every window is minimized.
+    fn is_inactive(&self) -> bool {
+        !self.windows.values().any(|state| state.focused)
+            || self.windows.values().all(|state| state.minimized)
+    }
+
+    /// Ends the pending close requests and returns the windows to close.
+    fn take_closing(&mut self) -> Vec<WindowId> {
+        let mut closing = std::mem::take(&mut self.closing);
+        for (id, state) in self.windows.iter_mut() {
+            if state.close_requested && !state.close_vetoed && !closing.contains(id) {
+                closing.push(*id);
+            }
+            state.close_requested = false;
+        }
+        closing
+    }
+}
+
+/// Applies the changes requested through a [`WindowState`]'s setters.
+fn apply_window_changes(app: &mut Application, id: WindowId, window: &winit::window::Window) {
+    let changes = match app.get_module_mut::<Window>().and_then(|w| w.window_mut(id)) {
+        Some(state) => std::mem::take(&mut state.changes),
+        None => return,
+    };
+    for change in changes {
+        match change {
+            WindowChange::Title(title) => window.set_title(&title),
+            WindowChange::Size(width, height) => {
+                window.set_inner_size(LogicalSize::new(width, height))
+            }
+            WindowChange::MinSize(size) => window
+                .set_min_inner_size(size.map(|(width, height)| LogicalSize::new(width, height))),
+            WindowChange::MaxSize(size) => window
+                .set_max_inner_size(size.map(|(width, height)| LogicalSize::new(width, height))),
+            WindowChange::Resizable(resizable) => window.set_resizable(resizable),
+            WindowChange::Decorations(decorations) => window.set_decorations(decorations),
+            WindowChange::Mode(mode) => {
+                window.set_fullscreen(to_fullscreen(mode, window.current_monitor()))
+            }
+            WindowChange::Maximized(maximized) => window.set_maximized(maximized),
+            WindowChange::Minimized(minimized) => window.set_minimized(minimized),
+            WindowChange::AlwaysOnTop(always_on_top) => window.set_always_on_top(always_on_top),
+            WindowChange::Position(position) => {
+                place_window(window, position, window.current_monitor())
+            }
+            WindowChange::Icon(icon) => {
+                window.set_window_icon(icon.as_ref().and_then(|path| load_icon(path)))
+            }
+            WindowChange::CursorVisible(visible) => window.set_cursor_visible(visible),
+            WindowChange::CursorGrab(grab) => apply_cursor_grab(window, grab),
+            WindowChange::CursorIcon(icon) => window.set_cursor_icon(icon.into()),
+            WindowChange::CursorPosition(x, y) => {
+                if let Err(e) = window.set_cursor_position(LogicalPosition::new(x, y)) {
+                    eprintln!("[Window]: Unable to move the cursor: {:?}", e);
+                }
+            }
+        }
+    }
+}
+
+/// Keeps the renderer's present mode in sync with the frame rate. `VSync`
+/// forces `Fifo` and `Uncapped` forces `Immediate`, which falls back to
+/// `Fifo` where unsupported. `Fixed` uses the present mode set on the
+/// renderer, which forcing a mode leaves untouched.
+fn apply_frame_rate(app: &mut Application, frame_rate: FrameRate) {
+    let forced = match frame_rate {
+        FrameRate::VSync => Some(PresentMode::Fifo),
+        FrameRate::Uncapped => Some(PresentMode::Immediate),
+        FrameRate::Fixed(_) => None,
+    };
+    if let Some(r) = app.get_module_mut::<Renderer>() {
+        r.set_forced_present_mode(forced);
+    }
+}
+
+/// Tracks a window's size and scale factor as reported by the OS.
+fn set_metrics(app: &mut Application, id: WindowId, physical_size: PhysicalSize<u32>, scale_factor: f64) {
+    if let Some(state) = app.get_module_mut::<Window>().and_then(|w| w.window_mut(id)) {
+        state.physical_size = physical_size;
+        state.scale_factor = scale_factor;
+    }
+    INPUT.lock().unwrap().set_scale_factor(id, scale_factor);
+}
+
+/// Creates the windows requested through [`Window::open_window`].
+fn open_windows(
+    app: &mut Application,
+    event_loop: &EventLoopWindowTarget<()>,
+    windows: &mut BTreeMap<WindowId, winit::window::Window>,
+) {
+    let opening: Vec<(WindowId, WindowDescriptor)> = {
+        let w = app.get_module_mut::<Window>().unwrap();
+        let opening = std::mem::take(&mut w.opening);
+        // Windows closed before they were opened are gone already
+        opening
+            .into_iter()
+            .filter_map(|id| w.window(id).map(|state| (id, state.descriptor.clone())))
+            .collect()
+    };
+    for (id, descriptor) in opening {
+        let window = descriptor.build(event_loop);
+        set_metrics(app, id, window.inner_size(), window.scale_factor());
+        app.create_window_state(id, &window);
+        windows.insert(id, window);
+        app.send_event(WindowOpened { window: id });
+    }
+}
+
+/// Closes the windows whose close request wasn't vetoed and the ones
+/// closed through [`Window::close_window`].
+/// Returns true if the primary window closed.
+fn close_windows(app: &mut Application, windows: &mut BTreeMap<WindowId, winit::window::Window>) -> bool {
+    let closing = app.get_module_mut::<Window>().unwrap().take_closing();
+    for id in closing {
+        if id == WindowId::PRIMARY {
+            return true;
+        }
+        // The swap chain has to go before the window it presents to
+        app.remove_window_state(id);
+        windows.remove(&id);
+        app.get_module_mut::<Window>().unwrap().windows.remove(&id);
+        {
+            let mut input = INPUT.lock().unwrap();
+            if input.get_focused_window() == Some(id) {
+                input.set_focused_window(None);
+            }
+        }
+        app.send_event(WindowClosed { window: id });
+    }
+    false
+}
+
+fn is_fullscreen_shortcut(event: &WindowEvent, modifiers: ModifiersState) -> bool {
+    match event {
+        WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(VirtualKeyCode::Return),
+                    ..
+                },
+            ..
+        } => modifiers.alt(),
+        _ => false,
     }
 }
 
@@ -50,58 +584,231 @@ pub fn run(app: Application) {
 }
 
 pub fn winit_run(mut app: Application, event_loop: EventLoop<()>) {
-    let window = WindowBuilder::new().build(&event_loop).unwrap();
-    let actual_wid = window.id();
-    {
-        window.set_title(app.get_module_mut::<Window>().unwrap().get_title());
-    }
+    let (window, frame_rate) = {
+        let w = app.get_module_mut::<Window>().unwrap();
+        (w.get_descriptor().build(&event_loop), w.get_frame_rate())
+    };
+    set_metrics(&mut app, WindowId::PRIMARY, window.inner_size(), window.scale_factor());
+    INPUT.lock().unwrap().set_focused_window(Some(WindowId::PRIMARY));
+    apply_frame_rate(&mut app, frame_rate);
 
     // Request that the State Descriptor be created
     app.create_state( &window );
 
-    event_loop.run(move |event, _, control_flow| {
-        // ControlFlow::Poll continuously runs the event loop, even if the os hasn't
-        // dispatched any events. This is ideal for games and similar applications.
-        *control_flow = ControlFlow::Poll;
-        
+    let mut windows = BTreeMap::new();
+    windows.insert(WindowId::PRIMARY, window);
+
+    let mut frame_limiter = FrameLimiter::new(frame_rate);
+    let mut modifiers = ModifiersState::empty();
+    // Every window requests a redraw, but all of them are drawn at once.
+    let mut redraw_pending = false;
+
+    event_loop.run(move |event, target, control_flow| {
         match event {
             Event::WindowEvent {
                 ref event,
                 window_id,
-            } if window_id == actual_wid => {
-                // Only triggered if termination is requested in another module.
-                if app.requested_termination {
-                    *control_flow = ControlFlow::Exit;
-                // Else check for non-window inputs
-                }else if !app.update_input(event) {
-                    match event {
-                        WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
-                        WindowEvent::Resized(physical_size) => {
-                            // resize event
-                            app.window_resize(
-                                physical_size.width,
-                                physical_size.height,
-                            )
-                            
-                        },
-                        WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
-                            // resize event
-                            app.window_resize(
-                                new_inner_size.width,
-                                new_inner_size.height,
-                            )
-                        },
-                        _ => (),
+            } => {
+                let id = match windows
+                    .iter()
+                    .find(|(_, window)| window.id() == window_id)
+                    .map(|(id, _)| *id)
+                {
+                    Some(id) => id,
+                    None => return,
+                };
+
+                if let WindowEvent::ModifiersChanged(state) = event {
+                    modifiers = *state;
+                }
+                if is_fullscreen_shortcut(event, modifiers) {
+                    let w = app.get_module_mut::<Window>().unwrap();
+                    if w.get_fullscreen_shortcut() {
+                        if let Some(state) = w.window_mut(id) {
+                            state.toggle_fullscreen();
+                        }
+                        return;
                     }
                 }
+
+                // Check for non-window inputs
+                if app.update_input(id, event) {
+                    return;
+                }
+
+                match event {
+                    WindowEvent::CloseRequested => {
+                        // Closed at the end of the next frame, unless vetoed
+                        if let Some(state) = app.get_module_mut::<Window>().unwrap().window_mut(id) {
+                            state.close_requested = true;
+                            state.close_vetoed = false;
+                        }
+                        app.send_event(WindowCloseRequested { window: id });
+                    },
+                    WindowEvent::Focused(focused) => {
+                        let focused = *focused;
+                        let (pause, any_focused) = {
+                            let w = app.get_module_mut::<Window>().unwrap();
+                            if let Some(state) = w.window_mut(id) {
+                                state.focused = focused;
+                            }
+                            (w.get_pause_on_focus_lost(), w.windows.values().any(|state| state.focused))
+                        };
+                        {
+                            let mut input = INPUT.lock().unwrap();
+                            if focused {
+                                input.set_focused_window(Some(id));
+                            } else if input.get_focused_window() == Some(id) {
+                                input.set_focused_window(None);
+                            }
+                        }
+                        app.send_event(WindowFocused { window: id, focused });
+                        // Focus moving between windows doesn't pause
+                        if pause {
+                            if any_focused {
+                                app.get_time_mut().resume();
+                            } else {
+                                app.get_time_mut().pause();
+                            }
+                        }
+                    },
+                    WindowEvent::Resized(physical_size) => {
+                        // Minimizing reports a zero sized window on most platforms
+                        let minimized = physical_size.width == 0 || physical_size.height == 0;
+                        let (was_minimized, scale_factor) = {
+                            let w = app.get_module_mut::<Window>().unwrap();
+                            match w.window_mut(id) {
+                                Some(state) => {
+                                    let was_minimized = state.minimized;
+                                    state.minimized = minimized;
+                                    (was_minimized, state.scale_factor)
+                                }
+                                None => return,
+                            }
+                        };
+                        if minimized != was_minimized {
+                            app.send_event(WindowMinimized { window: id, minimized });
+                        }
+                        if !minimized {
+                            set_metrics(&mut app, id, *physical_size, scale_factor);
+                            app.send_event(WindowResized {
+                                window: id,
+                                width: physical_size.width,
+                                height: physical_size.height,
+                            });
+                        }
+                    },
+                    WindowEvent::Moved(position) => {
+                        app.send_event(WindowMoved {
+                            window: id,
+                            x: position.x,
+                            y: position.y,
+                        });
+                    },
+                    WindowEvent::ScaleFactorChanged {
+                        scale_factor,
+                        new_inner_size,
+                    } => {
+                        set_metrics(&mut app, id, **new_inner_size, *scale_factor);
+                        app.send_event(ScaleFactorChanged {
+                            window: id,
+                            scale_factor: *scale_factor,
+                        });
+                        app.send_event(WindowResized {
+                            window: id,
+                            width: new_inner_size.width,
+                            height: new_inner_size.height,
+                        });
+                    },
+                    WindowEvent::CursorEntered { .. } => app.send_event(CursorEntered { window: id }),
+                    WindowEvent::CursorLeft { .. } => app.send_event(CursorLeft { window: id }),
+                    WindowEvent::HoveredFile(path) => {
+                        app.send_event(FileHovered {
+                            window: id,
+                            path: path.clone(),
+                        });
+                    },
+                    WindowEvent::HoveredFileCancelled => {
+                        app.send_event(FileHoverCancelled { window: id })
+                    },
+                    WindowEvent::DroppedFile(path) => {
+                        app.send_event(FileDropped {
+                            window: id,
+                            path: path.clone(),
+                        });
+                    },
+                    WindowEvent::ThemeChanged(theme) => {
+                        let theme = match theme {
+                            winit::window::Theme::Light => Theme::Light,
+                            winit::window::Theme::Dark => Theme::Dark,
+                        };
+                        app.send_event(ThemeChanged { window: id, theme });
+                    },
+                    _ => (),
+                }
             }
             Event::MainEventsCleared => {
-                // Application update code
-                app.update();
-                // Queue redraw requested event
-                window.request_redraw();
+                if *control_flow == ControlFlow::Exit {
+                    return;
+                }
+
+                open_windows(&mut app, target, &mut windows);
+                let (frame_rate, low_power, inactive, close_pending) = {
+                    let w = app.get_module::<Window>().unwrap();
+                    (
+                        w.get_frame_rate(),
+                        w.get_low_power_when_inactive(),
+                        w.is_inactive(),
+                        w.is_close_pending(),
+                    )
+                };
+                for (id, window) in windows.iter() {
+                    apply_window_changes(&mut app, *id, window);
+                }
+                if frame_rate != frame_limiter.frame_rate() {
+                    frame_limiter.set_frame_rate(frame_rate);
+                    apply_frame_rate(&mut app, frame_rate);
+                }
+
+                // While idle, sleep until the OS dispatches another event.
+                // A close request still needs a frame to be handled in.
+                if low_power && inactive && !close_pending {
+                    *control_flow = ControlFlow::Wait;
+                    return;
+                }
+
+                let now = Instant::now();
+                if frame_limiter.should_run(now) || close_pending {
+                    frame_limiter.begin_frame(now);
+                    // Application update code
+                    app.update();
+                    // Only triggered if termination is requested by a module or state.
+                    if app.is_termination_requested() || close_windows(&mut app, &mut windows) {
+                        *control_flow = ControlFlow::Exit;
+                        return;
+                    }
+                    // Queue redraw requested events
+                    redraw_pending = true;
+                    for window in windows.values() {
+                        window.request_redraw();
+                    }
+                }
+
+                // ControlFlow::Poll continuously runs the event loop, even if the os hasn't
+                // dispatched any events. This is ideal for uncapped and vsync'd games, a fixed
+                // frame rate sleeps until the next frame is due instead.
+                *control_flow = match frame_limiter.next_frame() {
+                    Some(next_frame) => ControlFlow::WaitUntil(next_frame),
+                    None => ControlFlow::Poll,
+                };
             }
             Event::RedrawRequested(_) => {
+                // The first window's redraw draws all of them,
+                // minimized windows are skipped by the renderer
+                if !redraw_pending {
+                    return;
+                }
+                redraw_pending = false;
                 // Redraw the application
                 if app.render() {
                     // If there was a fatal error, then request a shutdown.
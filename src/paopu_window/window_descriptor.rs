@@ -0,0 +1,253 @@
+use std::path::{Path, PathBuf};
+
+use winit::{
+    dpi::{LogicalSize, PhysicalPosition},
+    event_loop::EventLoopWindowTarget,
+    monitor::{MonitorHandle, VideoMode},
+    window::{Fullscreen, Icon, WindowBuilder},
+};
+
+use crate::window::cursor::{apply_cursor_grab, CursorGrabMode, CursorIcon};
+
+// -------------------------------------------------------------------------------
+//                      - Window Mode -
+// -------------------------------------------------------------------------------
+
+/// Whether the window is windowed or covers a whole monitor.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WindowMode {
+    Windowed,
+    /// A borderless window covering the monitor, without changing its video mode.
+    BorderlessFullscreen,
+    /// Exclusive fullscreen, switching the monitor to the selected video mode.
+    Fullscreen(VideoModeSelection),
+}
+
+/// The video mode used by exclusive fullscreen.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum VideoModeSelection {
+    /// The largest mode with the highest refresh rate the monitor supports.
+    Best,
+    /// The mode with this resolution, and refresh rate if given.
+    /// Falls back to [`VideoModeSelection::Best`] if the monitor lacks it.
+    Exact {
+        width: u32,
+        height: u32,
+        refresh_rate: Option<u16>,
+    },
+}
+
+/// Where the window is placed when it is created.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WindowPosition {
+    /// Lets the OS decide.
+    Automatic,
+    /// Centered on the monitor.
+    Centered,
+    /// At this position on the monitor, in physical pixels.
+    At(i32, i32),
+}
+
+// -------------------------------------------------------------------------------
+//                      - Window Descriptor -
+// -------------------------------------------------------------------------------
+
+/// Describes the window the [`Window`](crate::window::window::Window) module
+/// creates. Sizes are in logical pixels.
+///
+/// # Example
+///
+/// ```ignore
+/// let window = Window::new(WindowDescriptor {
+///     title: String::from("My Game"),
+///     width: 1280,
+///     height: 720,
+///     resizable: false,
+///     ..Default::default()
+/// });
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowDescriptor {
+    pub title: String,
+    pub width: u32,
+    pub height: u32,
+    pub min_size: Option<(u32, u32)>,
+    pub max_size: Option<(u32, u32)>,
+    pub resizable: bool,
+    pub decorations: bool,
+    pub mode: WindowMode,
+    pub maximized: bool,
+    pub transparent: bool,
+    pub always_on_top: bool,
+    pub position: WindowPosition,
+    /// The index of the monitor to open on, the primary monitor if `None`.
+    pub monitor: Option<usize>,
+    /// An image file used as the window icon.
+    pub icon: Option<PathBuf>,
+    /// Whether the OS cursor is shown over the window.
+    pub cursor_visible: bool,
+    pub cursor_grab: CursorGrabMode,
+    pub cursor_icon: CursorIcon,
+}
+
+impl Default for WindowDescriptor {
+    fn default() -> Self {
+        WindowDescriptor {
+            title: String::from("Paopu-rs Application"),
+            width: 1280,
+            height: 720,
+            min_size: None,
+            max_size: None,
+            resizable: true,
+            decorations: true,
+            mode: WindowMode::Windowed,
+            maximized: false,
+            transparent: false,
+            always_on_top: false,
+            position: WindowPosition::Automatic,
+            monitor: None,
+            icon: None,
+            cursor_visible: true,
+            cursor_grab: CursorGrabMode::None,
+            cursor_icon: CursorIcon::Default,
+        }
+    }
+}
+
+impl WindowDescriptor {
+    /// Creates the winit window described by this descriptor.
+    pub(crate) fn build<T: 'static>(
+        &self,
+        event_loop: &EventLoopWindowTarget<T>,
+    ) -> winit::window::Window {
+        let monitor = self
+            .monitor
+            .and_then(|index| event_loop.available_monitors().nth(index))
+            .or_else(|| event_loop.primary_monitor());
+
+        let mut builder = WindowBuilder::new()
+            .with_title(self.title.as_str())
+            .with_inner_size(LogicalSize::new(self.width, self.height))
+            .with_resizable(self.resizable)
+            .with_decorations(self.decorations)
+            .with_maximized(self.maximized)
+            .with_transparent(self.transparent)
+            .with_always_on_top(self.always_on_top)
+            .with_fullscreen(to_fullscreen(self.mode, monitor.clone()))
+            .with_window_icon(self.icon.as_ref().and_then(|path| load_icon(path)))
+            // Shown once it has been moved into place
+            .with_visible(false);
+        if let Some((width, height)) = self.min_size {
+            builder = builder.with_min_inner_size(LogicalSize::new(width, height));
+        }
+        if let Some((width, height)) = self.max_size {
+            builder = builder.with_max_inner_size(LogicalSize::new(width, height));
+        }
+
+        let window = builder.build(event_loop).unwrap();
+        if self.mode == WindowMode::Windowed && !self.maximized {
+            place_window(&window, self.position, monitor);
+        }
+        window.set_cursor_visible(self.cursor_visible);
+        window.set_cursor_icon(self.cursor_icon.into());
+        if self.cursor_grab != CursorGrabMode::None {
+            apply_cursor_grab(&window, self.cursor_grab);
+        }
+        window.set_visible(true);
+        window
+    }
+}
+
+/// Converts a window mode to winit's fullscreen setting on `monitor`.
+pub(crate) fn to_fullscreen(mode: WindowMode, monitor: Option<MonitorHandle>) -> Option<Fullscreen> {
+    match mode {
+        WindowMode::Windowed => None,
+        WindowMode::BorderlessFullscreen => Some(Fullscreen::Borderless(monitor)),
+        WindowMode::Fullscreen(selection) => {
+            let video_mode = monitor.and_then(|monitor| select_video_mode(&monitor, selection));
+            match video_mode {
+                Some(video_mode) => Some(Fullscreen::Exclusive(video_mode)),
+                None => {
+                    eprintln!("[Window]: No video mode available, using borderless fullscreen");
+                    Some(Fullscreen::Borderless(None))
+                }
+            }
+        }
+    }
+}
+
+fn select_video_mode(monitor: &MonitorHandle, selection: VideoModeSelection) -> Option<VideoMode> {
+    let best = || {
+        monitor.video_modes().max_by_key(|mode| {
+            let size = mode.size();
+            (size.width * size.height, mode.refresh_rate(), mode.bit_depth())
+        })
+    };
+    match selection {
+        VideoModeSelection::Best => best(),
+        VideoModeSelection::Exact {
+            width,
+            height,
+            refresh_rate,
+        } => {
+            let exact = monitor
+                .video_modes()
+                .filter(|mode| mode.size().width == width && mode.size().height == height)
+                .filter(|mode| refresh_rate.map(|rate| mode.refresh_rate() == rate).unwrap_or(true))
+                .max_by_key(|mode| (mode.refresh_rate(), mode.bit_depth()));
+            if exact.is_none() {
+                eprintln!(
+                    "[Window]: Video mode {}x{} is not supported, using the best one",
+                    width, height
+                );
+            }
+            exact.or_else(best)
+        }
+    }
+}
+
+/// Moves the window to `position` on `monitor`.
+pub(crate) fn place_window(
+    window: &winit::window::Window,
+    position: WindowPosition,
+    monitor: Option<MonitorHandle>,
+) {
+    let (monitor_position, monitor_size) = match monitor {
+        Some(monitor) => (monitor.position(), monitor.size()),
+        None => return,
+    };
+    match position {
+        WindowPosition::Automatic => {}
+        WindowPosition::Centered => {
+            let size = window.outer_size();
+            let x = monitor_position.x + (monitor_size.width as i32 - size.width as i32) / 2;
+            let y = monitor_position.y + (monitor_size.height as i32 - size.height as i32) / 2;
+            window.set_outer_position(PhysicalPosition::new(x, y));
+        }
+        WindowPosition::At(x, y) => {
+            window.set_outer_position(PhysicalPosition::new(
+                monitor_position.x + x,
+                monitor_position.y + y,
+            ));
+        }
+    }
+}
+
+/// Loads an image file as a window icon.
+pub(crate) fn load_icon(path: &Path) -> Option<Icon> {
+    let img = match image::open(path) {
+        Ok(img) => img.to_rgba8(),
+        Err(e) => {
+            eprintln!("[Window]: Unable to load icon {}: {:?}", path.display(), e);
+            return None;
+        }
+    };
+    let (width, height) = img.dimensions();
+    match Icon::from_rgba(img.into_raw(), width, height) {
+        Ok(icon) => Some(icon),
+        Err(e) => {
+            eprintln!("[Window]: Invalid icon {}: {:?}", path.display(), e);
+            None
+        }
+    }
+}
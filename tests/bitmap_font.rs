@@ -0,0 +1,133 @@
+use std::path::{Path, PathBuf};
+
+use paopu_rs::renderer::{
+    font::{BitmapFont, FontHandle, Glyph},
+    renderer::Renderer,
+    sprite::Rect,
+    text::{layout_text, Text, TAB_WIDTH},
+};
+
+// The same font in the text, XML and binary variants
+const TEXT_FONT: &str = "tests/fonts/pixel.fnt";
+const XML_FONT: &str = "tests/fonts/pixel.xml";
+const BINARY_FONT: &str = "tests/fonts/pixel_binary.fnt";
+
+fn load(path: &str) -> BitmapFont {
+    BitmapFont::load(path).unwrap()
+}
+
+fn check_pixel_font(font: &BitmapFont) {
+    assert_eq!(font.line_height, 10.0);
+    assert_eq!(font.base, 8.0);
+    assert_eq!(font.page_files, vec!["pixel_0.png", "pixel_1.png"]);
+    assert_eq!(font.glyphs.len(), 5);
+    assert_eq!(
+        font.glyphs[&'A'],
+        Glyph {
+            region: Rect::new(5.0, 0.0, 5.0, 7.0),
+            offset: (0.0, 1.0),
+            advance: 6.0,
+            page: 0,
+        }
+    );
+    assert_eq!(font.glyphs[&'V'].page, 1);
+    assert_eq!(
+        font.glyphs[&'é'],
+        Glyph {
+            region: Rect::new(6.0, 0.0, 4.0, 9.0),
+            offset: (0.0, -1.0),
+            advance: 5.0,
+            page: 1,
+        }
+    );
+    assert_eq!(font.glyphs[&' '].region.width, 0.0);
+
+    assert_eq!(font.kernings.len(), 3);
+    assert_eq!(font.kerning('A', 'V'), -2.0);
+    assert_eq!(font.kerning('V', 'A'), -2.0);
+    assert_eq!(font.kerning('V', 'é'), -1.0);
+    assert_eq!(font.kerning('A', 'A'), 0.0);
+}
+
+#[test]
+fn parses_the_text_variant() {
+    check_pixel_font(&load(TEXT_FONT));
+}
+
+#[test]
+fn parses_the_xml_variant() {
+    check_pixel_font(&load(XML_FONT));
+}
+
+#[test]
+fn parses_the_binary_variant() {
+    check_pixel_font(&load(BINARY_FONT));
+}
+
+#[test]
+fn variants_describe_the_same_font() {
+    let text = load(TEXT_FONT);
+    assert_eq!(text, load(XML_FONT));
+    assert_eq!(text, load(BINARY_FONT));
+}
+
+#[test]
+fn page_paths_are_relative_to_the_descriptor() {
+    let font = load(TEXT_FONT);
+    assert_eq!(
+        font.page_paths(Path::new(TEXT_FONT)),
+        vec![PathBuf::from("tests/fonts/pixel_0.png"), PathBuf::from("tests/fonts/pixel_1.png")]
+    );
+}
+
+#[test]
+fn rejects_other_binary_versions() {
+    let mut bytes = std::fs::read(BINARY_FONT).unwrap();
+    bytes[3] = 2;
+    assert!(BitmapFont::parse(&bytes).is_err());
+    bytes[3] = 3;
+    bytes.truncate(bytes.len() - 1);
+    assert!(BitmapFont::parse(&bytes).is_err());
+}
+
+fn font_handle() -> FontHandle {
+    Renderer::default().add_font(load(TEXT_FONT), Vec::new())
+}
+
+fn positions(font: &BitmapFont, text: &Text) -> Vec<(f32, f32)> {
+    layout_text(font, text).glyphs.iter().map(|glyph| glyph.position).collect()
+}
+
+#[test]
+fn layout_applies_kerning_across_pages() {
+    let font = load(TEXT_FONT);
+    let text = Text::new(font_handle(), "AVAé");
+    let layout = layout_text(&font, &text);
+    assert_eq!(
+        layout.glyphs.iter().map(|glyph| glyph.page).collect::<Vec<_>>(),
+        vec![0, 1, 0, 1]
+    );
+    // A, then V pulled in by 2, A pulled in by 2, é after A without kerning
+    assert_eq!(positions(&font, &text), vec![(0.0, 1.0), (4.0, 1.0), (8.0, 1.0), (14.0, -1.0)]);
+    assert_eq!(layout.width, 19.0);
+}
+
+#[test]
+fn layout_ignores_carriage_returns() {
+    let font = load(TEXT_FONT);
+    let handle = font_handle();
+    let crlf = layout_text(&font, &Text::new(handle, "A\r\nA\r"));
+    let lf = layout_text(&font, &Text::new(handle, "A\nA"));
+    assert_eq!(crlf, lf);
+    // No `?` stands in for the carriage returns
+    assert!(crlf.glyphs.iter().all(|glyph| glyph.region.x == 5.0));
+}
+
+#[test]
+fn layout_expands_tabs_to_spaces() {
+    let font = load(TEXT_FONT);
+    let text = Text::new(font_handle(), "\tA");
+    let layout = layout_text(&font, &text);
+    assert_eq!(layout.glyphs.len(), 1);
+    assert_eq!(layout.glyphs[0].position, (TAB_WIDTH as f32 * 3.0, 1.0));
+}
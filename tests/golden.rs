@@ -0,0 +1,180 @@
+use paopu_rs::{
+    core::{application::Application, transform::Transform2D},
+    renderer::{
+        blend_mode::BlendMode,
+        font::BitmapFont,
+        golden::{is_adapter_available, GoldenTest},
+        renderer::Renderer,
+        sprite::{Rect, Sprite},
+        text::Text,
+        texture::TextureHandle,
+    },
+};
+
+const RED: [u8; 4] = [255, 0, 0, 255];
+const GREEN: [u8; 4] = [0, 255, 0, 255];
+const BLUE: [u8; 4] = [0, 0, 255, 255];
+const WHITE: [u8; 4] = [255, 255, 255, 255];
+
+// Golden tests render on a GPU adapter, a software one like lavapipe works,
+// so they are ignored by default. Run them with
+// `cargo test --test golden -- --ignored`, setting PAOPU_UPDATE_GOLDEN to
+// write missing references, which are reviewed before being checked in.
+
+/// Runs a golden test, failing if the machine has no adapter to render with.
+fn run_golden<F>(test: GoldenTest, setup: F)
+where
+    F: FnOnce(&mut Application),
+{
+    assert!(
+        is_adapter_available(),
+        "[GoldenTest]: No adapter found to render {} with",
+        test.reference_path().display()
+    );
+    if let Err(e) = test.run(setup) {
+        panic!("{:#}", e);
+    }
+}
+
+fn add_texture(app: &mut Application, width: u32, height: u32, pixel: impl Fn(u32, u32) -> [u8; 4]) -> TextureHandle {
+    let img = image::RgbaImage::from_fn(width, height, |x, y| image::Rgba(pixel(x, y)));
+    app.get_module_mut::<Renderer>()
+        .unwrap()
+        .add_texture(image::DynamicImage::ImageRgba8(img), "golden")
+}
+
+#[test]
+#[ignore = "needs a GPU adapter, run with --ignored"]
+fn sprites() {
+    run_golden(GoldenTest::new("sprites", 64, 64), |app| {
+        // Red, green, blue and white quadrants
+        let quadrants = add_texture(app, 4, 4, |x, y| match (x < 2, y < 2) {
+            (true, true) => RED,
+            (false, true) => GREEN,
+            (true, false) => BLUE,
+            (false, false) => WHITE,
+        });
+
+        app.world.spawn((
+            Transform2D::from_translation(-16.0, 8.0).with_scale(4.0, 4.0),
+            Sprite::new(quadrants),
+        ));
+
+        let mut flipped = Sprite::new(quadrants).with_color([1.0, 1.0, 0.0, 1.0]);
+        flipped.flip_x = true;
+        app.world.spawn((
+            Transform2D::from_translation(16.0, 8.0).with_scale(4.0, 4.0),
+            flipped,
+        ));
+
+        let mut region = Sprite::new(quadrants).with_region(Rect::new(2.0, 0.0, 2.0, 2.0));
+        region.anchor = [0.0, 0.0];
+        app.world.spawn((
+            Transform2D::from_translation(-8.0, -24.0).with_scale(8.0, 8.0),
+            region,
+        ));
+    });
+}
+
+#[test]
+#[ignore = "needs a GPU adapter, run with --ignored"]
+fn tilemap() {
+    run_golden(GoldenTest::new("tilemap", 64, 64), |app| {
+        // Four 4x4 tiles: grass, dirt, water and stone
+        let tileset = add_texture(app, 8, 8, |x, y| match (x / 4, y / 4) {
+            (0, 0) => [34, 139, 34, 255],
+            (1, 0) => [139, 69, 19, 255],
+            (0, 1) if y % 2 == 0 => [0, 0, 255, 255],
+            (0, 1) => [0, 255, 255, 255],
+            _ if (x + y) % 2 == 0 => [255, 255, 255, 255],
+            _ => [128, 128, 128, 255],
+        });
+
+        let map = [
+            "00001100",
+            "00011100",
+            "02211000",
+            "02210033",
+            "00110033",
+            "01100000",
+            "11022200",
+            "10022200",
+        ];
+        for (row, line) in map.iter().enumerate() {
+            for (column, tile) in line.chars().enumerate() {
+                let tile = tile.to_digit(10).unwrap() as f32;
+                let region = Rect::new((tile % 2.0) * 4.0, (tile / 2.0).floor() * 4.0, 4.0, 4.0);
+                let mut sprite = Sprite::new(tileset).with_region(region);
+                sprite.anchor = [0.0, 1.0];
+                let x = -32.0 + column as f32 * 8.0;
+                let y = 32.0 - row as f32 * 8.0;
+                app.world.spawn((Transform2D::from_translation(x, y).with_scale(2.0, 2.0), sprite));
+            }
+        }
+    });
+}
+
+#[test]
+#[ignore = "needs a GPU adapter, run with --ignored"]
+fn bitmap_text() {
+    run_golden(GoldenTest::new("bitmap_text", 64, 64), |app| {
+        let descriptor = "info face=\"golden\" size=6\n\
+            common lineHeight=7 base=6 scaleW=12 scaleH=5 pages=1\n\
+            page id=0 file=\"golden.png\"\n\
+            chars count=3\n\
+            char id=65 x=0 y=0 width=3 height=5 xoffset=0 yoffset=1 xadvance=4 page=0\n\
+            char id=66 x=4 y=0 width=3 height=5 xoffset=0 yoffset=1 xadvance=4 page=0\n\
+            char id=63 x=8 y=0 width=3 height=5 xoffset=0 yoffset=1 xadvance=4 page=0\n\
+            kernings count=1\n\
+            kerning first=65 second=66 amount=1\n";
+        let font = BitmapFont::parse(descriptor.as_bytes()).unwrap();
+
+        let glyphs = [
+            ["###", "#.#", "###", "#.#", "#.#"],
+            ["##.", "#.#", "##.", "#.#", "##."],
+            ["##.", "..#", ".#.", "...", ".#."],
+        ];
+        let page = add_texture(app, 12, 5, |x, y| {
+            let row = glyphs.get(x as usize / 4).map(|glyph| glyph[y as usize].as_bytes());
+            match row.and_then(|row| row.get(x as usize % 4)) {
+                Some(b'#') => WHITE,
+                _ => [0, 0, 0, 0],
+            }
+        });
+        let font = app.get_module_mut::<Renderer>().unwrap().add_font(font, vec![page]);
+
+        // The missing `Z` falls back to `?`
+        let text = Text::new(font, "AB\n")
+            .with_section("BZA", [1.0, 0.0, 0.0, 1.0])
+            .with_scale(2.0);
+        app.world.spawn((Transform2D::from_translation(-24.0, 16.0), text));
+    });
+}
+
+#[test]
+#[ignore = "needs a GPU adapter, run with --ignored"]
+fn blending() {
+    run_golden(GoldenTest::new("blending", 64, 64), |app| {
+        let white = add_texture(app, 1, 1, |_, _| WHITE);
+
+        // The bottom half is gray, the top half stays black
+        app.world.spawn((
+            Transform2D::from_translation(0.0, -16.0).with_scale(64.0, 32.0),
+            Sprite::new(white).with_color([0.5, 0.5, 0.5, 1.0]).with_z_index(-1),
+        ));
+
+        let columns = [
+            (BlendMode::Alpha, [1.0, 0.0, 0.0, 0.5]),
+            (BlendMode::Additive, [0.0, 0.5, 1.0, 1.0]),
+            (BlendMode::Multiply, [1.0, 0.5, 0.0, 0.5]),
+            (BlendMode::Screen, [0.0, 0.5, 1.0, 0.5]),
+        ];
+        for (index, (blend_mode, color)) in columns.iter().enumerate() {
+            let x = -24.0 + index as f32 * 16.0;
+            app.world.spawn((
+                Transform2D::from_translation(x, 0.0).with_scale(12.0, 48.0),
+                Sprite::new(white).with_color(*color).with_blend_mode(*blend_mode),
+            ));
+        }
+    });
+}